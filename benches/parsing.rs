@@ -0,0 +1,123 @@
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use toy_browser::{css, html};
+
+// Mede o tempo de parsing para entradas de tamanhos crescentes e verifica
+// que o tempo por byte se mantém estável, ou seja, que o parsing é linear.
+
+const SIZES_IN_KILOBYTES: [usize; 4] = [1024, 2048, 4096, 8192];
+
+// Entradas profundas ficam no limite de profundidade, onde cada elemento
+// custa mais, então usam tamanhos menores
+const DEEP_SIZES_IN_KILOBYTES: [usize; 4] = [128, 256, 512, 1024];
+
+// Tolerância para ruído de medição entre o menor e o maior tamanho
+const MAX_TIME_PER_BYTE_RATIO: f64 = 3.0;
+
+fn generate_html(size: usize) -> String {
+    let mut html = String::from("<!DOCTYPE html><html><head><title>Página</title></head><body>");
+    let mut i = 0;
+
+    while html.len() < size {
+        html.push_str(&format!(
+            "<div class=\"cartão item-{i}\"><p title=\"Olá\">Ação &amp; reação — café {i}</p><br><img src=\"a.png\"/></div>\n"
+        ));
+        i += 1;
+    }
+
+    html.push_str("</body></html>");
+    html
+}
+
+// Aninhamento que cresce com a entrada, com elementos de formatação mal
+// aninhados que acionam o adoption agency
+fn generate_deep_html(size: usize) -> String {
+    let mut html = String::from("<!DOCTYPE html><html><head></head><body>");
+
+    while html.len() < size {
+        html.push_str("<div><span><a><b>Ação &amp; reação");
+    }
+
+    html
+}
+
+fn generate_css(size: usize) -> String {
+    let mut css = String::new();
+    let mut i = 0;
+
+    while css.len() < size {
+        css.push_str(&format!(
+            ".título-{i}, div#ação {{ display: block; padding: 12px; background-color: #ff0000ff; }}\n"
+        ));
+        i += 1;
+    }
+
+    css
+}
+
+fn measure<F: Fn(String)>(input: &str, parse: F) -> Duration {
+    // Melhor de três execuções para reduzir ruído
+    (0..3)
+        .map(|_| {
+            let input = input.to_owned();
+            let start = Instant::now();
+            parse(input);
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn bench<F: Fn(String)>(
+    name: &str,
+    sizes_in_kilobytes: [usize; 4],
+    generate: fn(usize) -> String,
+    parse: F,
+) {
+    let mut times_per_byte = Vec::new();
+
+    for kilobytes in sizes_in_kilobytes {
+        let input = generate(kilobytes * 1024);
+        let elapsed = measure(&input, &parse);
+        let time_per_byte = elapsed.as_nanos() as f64 / input.len() as f64;
+
+        println!(
+            "{name:>5} {kilobytes:>4} KB: {:>9.2} ms ({time_per_byte:.2} ns/byte)",
+            elapsed.as_secs_f64() * 1000.0,
+        );
+
+        times_per_byte.push(time_per_byte);
+    }
+
+    let ratio = times_per_byte.last().unwrap() / times_per_byte.first().unwrap();
+
+    println!(
+        "{name:>5} razão ns/byte entre {} KB e {} KB: {ratio:.2}",
+        sizes_in_kilobytes[0], sizes_in_kilobytes[3]
+    );
+
+    assert!(
+        ratio < MAX_TIME_PER_BYTE_RATIO,
+        "{name}: o tempo de parsing não cresce linearmente (razão {ratio:.2})"
+    );
+}
+
+fn main() {
+    bench("html", SIZES_IN_KILOBYTES, generate_html, |input| {
+        black_box(html::parse(input));
+    });
+
+    bench(
+        "deep",
+        DEEP_SIZES_IN_KILOBYTES,
+        generate_deep_html,
+        |input| {
+            black_box(html::parse(input));
+        },
+    );
+
+    bench("css", SIZES_IN_KILOBYTES, generate_css, |input| {
+        black_box(css::parse(input));
+    });
+}
@@ -1,4 +1,9 @@
-use std::u8;
+mod shorthands;
+mod tokenizer;
+
+pub use tokenizer::{Number, Token, Tokenizer};
+
+use crate::diagnostics::{Diagnostic, ParseResult, Span};
 
 #[derive(Debug)]
 pub struct Stylesheet {
@@ -7,21 +12,130 @@ pub struct Stylesheet {
 
 #[derive(Debug)]
 pub struct Rule {
-    pub selectors: Vec<SimpleSelector>,
+    pub selectors: Vec<ComplexSelector>,
     pub declarations: Vec<Declaration>,
+    // Do primeiro seletor até o `}` do bloco de declarações
+    pub span: Span,
 }
 
+// Seletores compostos ligados por combinadores, ex: `ul > li.ativo a`
 #[derive(Debug)]
-pub struct SimpleSelector {
+pub struct ComplexSelector {
+    // Da esquerda para a direita; o último corresponde ao próprio elemento
+    pub compounds: Vec<CompoundSelector>,
+    // `combinators[i]` liga `compounds[i]` a `compounds[i + 1]`
+    pub combinators: Vec<Combinator>,
+    pub span: Span,
+}
+
+// Seletores simples aplicados a um mesmo elemento, ex: `div#menu.ativo`
+#[derive(Debug)]
+pub struct CompoundSelector {
     pub tag_name: Option<String>,
     pub id: Option<String>,
     pub classes: Vec<String>,
+    pub attributes: Vec<AttributeSelector>,
+    pub pseudo_classes: Vec<PseudoClass>,
+    // Só pode aparecer no último seletor composto
+    pub pseudo_element: Option<PseudoElement>,
+    pub span: Span,
+}
+
+// `[name]` ou `[name <operador> "valor" <flag>]`
+#[derive(Debug)]
+pub struct AttributeSelector {
+    pub name: String,
+    // `None` em `[name]`, que só exige a presença do atributo
+    pub value: Option<(AttributeOperator, String)>,
+    // Flag `i` ou `s`. Sem ela, vale o padrão do HTML para o atributo.
+    pub case_sensitivity: Option<CaseSensitivity>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeOperator {
+    // `=`: valor exato
+    Equals,
+    // `~=`: uma das palavras separadas por espaços
+    Includes,
+    // `|=`: valor exato ou seguido de `-`, como em `lang|=en`
+    DashMatch,
+    // `^=`
+    Prefix,
+    // `$=`
+    Suffix,
+    // `*=`
+    Substring,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaseSensitivity {
+    Sensitive,
+    AsciiInsensitive,
+}
+
+#[derive(Debug)]
+pub enum PseudoClass {
+    Root,
+    Empty,
+    FirstChild,
+    LastChild,
+    OnlyChild,
+    FirstOfType,
+    LastOfType,
+    OnlyOfType,
+    // `:nth-child(An+B of S)`: conta apenas os irmãos que correspondem a `S`
+    NthChild(Nth, Option<Vec<ComplexSelector>>),
+    NthLastChild(Nth, Option<Vec<ComplexSelector>>),
+    NthOfType(Nth),
+    NthLastOfType(Nth),
+    Not(Vec<ComplexSelector>),
+    Is(Vec<ComplexSelector>),
+    // Igual a `:is`, mas sem especificidade
+    Where(Vec<ComplexSelector>),
+    Has(Vec<RelativeSelector>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PseudoElement {
+    Before,
+    After,
+}
+
+// Expressão `An+B`: corresponde às posições `A * n + B` para algum n >= 0
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Nth {
+    pub a: i32,
+    pub b: i32,
+}
+
+// Argumento de `:has`, ex: `> img`. O combinador liga o elemento testado
+// ao primeiro seletor composto.
+#[derive(Debug)]
+pub struct RelativeSelector {
+    pub combinator: Combinator,
+    pub selector: ComplexSelector,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Combinator {
+    // `a b`
+    Descendant,
+    // `a > b`
+    Child,
+    // `a + b`
+    NextSibling,
+    // `a ~ b`
+    SubsequentSibling,
 }
 
 #[derive(Debug)]
 pub struct Declaration {
     pub name: String,
     pub value: CSSValue,
+    // Marcada com `!important`
+    pub important: bool,
+    // Do nome da propriedade até o fim do valor
+    pub span: Span,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -36,7 +150,39 @@ pub struct Color {
 pub enum CSSValue {
     Keyword(String),
     Length(f32, CSSUnit),
+    Number(f32),
+    Percentage(f32),
     Color(Color),
+    String(String),
+    // `,` ou `/` entre componentes, como em `font: 12px/1.5 serif`
+    Separator(char),
+    // Valor com mais de um componente, ex: `font-family: Arial, serif`.
+    // Shorthands nunca aparecem aqui: são expandidos pelo parser.
+    List(Vec<CSSValue>),
+    // Valor de `content` diferente de `none` e `normal`
+    Content(Vec<ContentItem>),
+    // Valor de `counter-reset` e `counter-increment`: nomes dos contadores
+    // e o valor (ou incremento) de cada um
+    Counters(Vec<(String, i32)>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ContentItem {
+    String(String),
+    // `attr(name)`: valor do atributo, ou vazio se ele não existir
+    Attr(String),
+    // `counter(name, style)`
+    Counter {
+        name: String,
+        style: String,
+    },
+    // `counters(name, "separador", style)`: os valores de todos os
+    // contadores aninhados com esse nome, do mais externo ao mais interno
+    Counters {
+        name: String,
+        separator: String,
+        style: String,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -45,38 +191,191 @@ pub enum CSSUnit {
 }
 
 pub fn parse(input: String) -> Stylesheet {
-    let mut parser = Parser { input, cursor: 0 };
+    parse_with_diagnostics(input).value
+}
+
+// Regras e declarações inválidas ou não suportadas são descartadas com um
+// diagnóstico, e o parsing continua a partir do próximo ponto seguro
+pub fn parse_with_diagnostics(input: String) -> ParseResult<Stylesheet> {
+    let mut parser = Parser::new(input);
+    let stylesheet = parser.consume_stylesheet();
+
+    parser.finish(stylesheet)
+}
+
+// Analisa uma lista de seletores avulsa, como a usada por `query_selector`
+pub fn parse_selectors(input: String) -> ParseResult<Vec<ComplexSelector>> {
+    let mut parser = Parser::new(input);
+    let mut result = parser.consume_selector_list().unwrap_or_default();
+
+    // Uma lista com algum seletor inválido é inválida por inteiro
+    if !parser.diagnostics.is_empty() {
+        result.clear();
+    }
 
-    parser.consume_stylesheet()
+    parser.finish(result)
 }
 
+// Analisa uma lista de declarações avulsa, como a do atributo `style`
+pub fn parse_declarations(input: String) -> ParseResult<Vec<Declaration>> {
+    let mut parser = Parser::new(input);
+    let declarations = parser.consume_declaration_list();
+
+    parser.finish(declarations)
+}
+
+// Valores aceitos por todas as propriedades
+// (https://www.w3.org/TR/css-cascade-4/#defaulting-keywords)
+pub const CSS_WIDE_KEYWORDS: &[&str] = &["inherit", "initial", "unset", "revert"];
+
 pub type Specificity = (usize, usize, usize);
 
-impl SimpleSelector {
+impl ComplexSelector {
+    pub fn pseudo_element(&self) -> Option<PseudoElement> {
+        self.compounds
+            .last()
+            .and_then(|compound| compound.pseudo_element)
+    }
+
+    // Soma das especificidades de todos os seletores compostos
+    pub fn specificity(&self) -> Specificity {
+        self.compounds
+            .iter()
+            .map(CompoundSelector::specificity)
+            .fold((0, 0, 0), add_specificity)
+    }
+}
+
+impl CompoundSelector {
     pub fn specificity(&self) -> Specificity {
         let a = self.id.iter().count();
-        let b = self.classes.len();
-        let c = self.tag_name.iter().count();
+        let b = self.classes.len() + self.attributes.len();
+        let c = self.tag_name.iter().count() + self.pseudo_element.iter().count();
 
-        (a, b, c)
+        self.pseudo_classes
+            .iter()
+            .map(PseudoClass::specificity)
+            .fold((a, b, c), add_specificity)
     }
 }
 
-impl CSSValue {
-    pub fn to_px(&self) -> f32 {
+impl PseudoClass {
+    // `:is`, `:not` e `:has` valem o seu argumento mais específico, e
+    // `:nth-child(An+B of S)` soma o mais específico de `S`
+    pub fn specificity(&self) -> Specificity {
         match self {
-            &CSSValue::Length(length, CSSUnit::Px) => length,
-            _ => 0.0
+            PseudoClass::Where(_) => (0, 0, 0),
+            PseudoClass::Is(selectors) | PseudoClass::Not(selectors) => max_specificity(selectors),
+            PseudoClass::Has(selectors) => selectors
+                .iter()
+                .map(|relative| relative.selector.specificity())
+                .max()
+                .unwrap_or_default(),
+            PseudoClass::NthChild(_, Some(selectors))
+            | PseudoClass::NthLastChild(_, Some(selectors)) => {
+                add_specificity((0, 1, 0), max_specificity(selectors))
+            }
+            _ => (0, 1, 0),
+        }
+    }
+}
+
+impl Nth {
+    // `position` começa em 1
+    pub fn matches(&self, position: usize) -> bool {
+        let offset = position as i64 - self.b as i64;
+        let a = self.a as i64;
+
+        if a == 0 {
+            offset == 0
+        } else {
+            offset % a == 0 && offset / a >= 0
+        }
+    }
+}
+
+fn add_specificity((a, b, c): Specificity, (d, e, f): Specificity) -> Specificity {
+    (a + d, b + e, c + f)
+}
+
+fn max_specificity(selectors: &[ComplexSelector]) -> Specificity {
+    selectors
+        .iter()
+        .map(ComplexSelector::specificity)
+        .max()
+        .unwrap_or_default()
+}
+
+impl CSSValue {
+    // Comprimento em px. O número 0 dispensa unidade e também é aceito onde
+    // se espera um comprimento.
+    pub fn length(&self) -> Option<f32> {
+        match *self {
+            CSSValue::Length(length, CSSUnit::Px) => Some(length),
+            CSSValue::Number(0.0) => Some(0.0),
+            _ => None,
         }
     }
+
+    pub fn to_px(&self) -> f32 {
+        self.length().unwrap_or(0.0)
+    }
 }
 
 struct Parser {
-    input: String,
-    cursor: usize,
+    tokens: Vec<(Token, Span)>,
+    index: usize,
+    // Índice que o parser trata como fim da entrada. Dentro de um bloco ou
+    // do prelúdio de uma regra, é o fim desse trecho.
+    limit: usize,
+    // Quantas pseudo-classes funcionais (`:not(`, `:is(`, ...) envolvem o
+    // seletor sendo analisado
+    depth: usize,
+    diagnostics: Vec<Diagnostic>,
 }
 
+static EOF: Token = Token::Eof;
+
+// Aninhamento máximo de pseudo-classes funcionais. Seletores mais profundos
+// são inválidos, para que o parsing e o matching recursivos não estourem a
+// pilha.
+const MAX_SELECTOR_DEPTH: usize = 256;
+
 impl Parser {
+    fn new(input: String) -> Parser {
+        let mut tokenizer = Tokenizer::new(input);
+        let mut tokens = Vec::new();
+
+        loop {
+            let token = tokenizer.next_token();
+            let eof = token == Token::Eof;
+
+            tokens.push((token, tokenizer.token_span()));
+
+            if eof {
+                break;
+            }
+        }
+
+        Parser {
+            limit: tokens.len() - 1,
+            tokens,
+            index: 0,
+            depth: 0,
+            diagnostics: tokenizer.errors().to_vec(),
+        }
+    }
+
+    fn finish<T>(mut self, value: T) -> ParseResult<T> {
+        self.diagnostics
+            .sort_by_key(|diagnostic| diagnostic.span.start.offset);
+
+        ParseResult {
+            value,
+            diagnostics: self.diagnostics,
+        }
+    }
+
     fn consume_stylesheet(&mut self) -> Stylesheet {
         Stylesheet {
             rules: self.consume_rules(),
@@ -86,209 +385,1444 @@ impl Parser {
     fn consume_rules(&mut self) -> Vec<Rule> {
         let mut rules = Vec::new();
 
-        self.consume_whitespace();
-
-        while !self.eof() {
-            rules.push(self.consume_rule());
-            self.consume_whitespace();
+        loop {
+            match self.peek() {
+                Token::Eof => break,
+                // `<!--` e `-->` são permitidos no nível superior para
+                // esconder o CSS de navegadores antigos
+                Token::Whitespace | Token::Cdo | Token::Cdc => self.index += 1,
+                Token::AtKeyword(_) => self.consume_at_rule(),
+                _ => {
+                    if let Some(rule) = self.consume_qualified_rule() {
+                        rules.push(rule);
+                    }
+                }
+            }
         }
 
         rules
     }
 
-    fn consume_rule(&mut self) -> Rule {
-        Rule {
-            selectors: self.consume_selectors(),
-            declarations: self.consume_declaration_block(),
+    // Nenhuma regra @ é suportada: ela é descartada até o `;` ou até o fim
+    // do seu bloco
+    fn consume_at_rule(&mut self) {
+        let (token, span) = self.consume();
+
+        self.error(
+            "unsupported-at-rule",
+            format!("Regra {} não suportada", token),
+            span,
+        );
+
+        loop {
+            match self.peek() {
+                Token::Eof => break,
+                Token::Semicolon => {
+                    self.index += 1;
+                    break;
+                }
+                Token::OpenCurly => {
+                    self.skip_component_value();
+                    break;
+                }
+                _ => {
+                    self.skip_component_value();
+                }
+            }
+        }
+    }
+
+    // Regras com seletores inválidos são descartadas por inteiro
+    fn consume_qualified_rule(&mut self) -> Option<Rule> {
+        let start = self.index;
+
+        loop {
+            match self.peek() {
+                Token::Eof => {
+                    self.error(
+                        "eof-in-selector",
+                        "Fim do arquivo dentro de um seletor".to_owned(),
+                        self.span(),
+                    );
+                    return None;
+                }
+                Token::OpenCurly => break,
+                _ => {
+                    self.skip_component_value();
+                }
+            }
         }
+
+        let selectors = self.within(start, self.index, Parser::consume_selector_list);
+
+        let selectors = match selectors {
+            Some(selectors) => selectors,
+            None => {
+                self.skip_component_value();
+                return None;
+            }
+        };
+
+        let declarations = self.consume_block(Parser::consume_declaration_list);
+
+        Some(Rule {
+            selectors,
+            declarations,
+            span: self.span_from(start),
+        })
     }
 
-    fn consume_selectors(&mut self) -> Vec<SimpleSelector> {
+    fn consume_selector_list(&mut self) -> Option<Vec<ComplexSelector>> {
         let mut selectors = Vec::new();
 
-        while !self.eof() && self.peek() != '{' {
-            self.consume_whitespace();
-            selectors.push(self.consume_simple_selector());
+        loop {
+            self.skip_whitespace();
+            selectors.push(self.consume_complex_selector()?);
 
-            if self.peek() == ',' {
-                self.consume_char();
+            match self.peek() {
+                Token::Eof => break,
+                Token::Comma => self.index += 1,
+                _ => return self.unexpected_token_in_selector(),
             }
         }
 
-        selectors.sort_by(|a, b| a.specificity().cmp(&b.specificity()));
+        Some(selectors)
+    }
 
-        selectors
+    // Termina, sem consumir, na `,` seguinte ou no fim da entrada
+    fn consume_complex_selector(&mut self) -> Option<ComplexSelector> {
+        let start = self.index;
+        let mut compounds = vec![self.consume_compound_selector()?];
+        let mut combinators = Vec::new();
+        let span = loop {
+            let end = self.span_from(start);
+            let whitespace = self.peek() == &Token::Whitespace;
+
+            self.skip_whitespace();
+
+            let combinator = match self.peek() {
+                Token::Eof | Token::Comma => break end,
+                Token::Delim('>') => Combinator::Child,
+                Token::Delim('+') => Combinator::NextSibling,
+                Token::Delim('~') => Combinator::SubsequentSibling,
+                _ if whitespace => Combinator::Descendant,
+                _ => return self.unexpected_token_in_selector(),
+            };
+
+            if combinator != Combinator::Descendant {
+                self.index += 1;
+                self.skip_whitespace();
+            }
+
+            if compounds
+                .last()
+                .is_some_and(|compound| compound.pseudo_element.is_some())
+            {
+                return self.pseudo_element_not_last();
+            }
+
+            combinators.push(combinator);
+            compounds.push(self.consume_compound_selector()?);
+        };
+
+        Some(ComplexSelector {
+            compounds,
+            combinators,
+            span,
+        })
     }
 
-    fn consume_simple_selector(&mut self) -> SimpleSelector {
-        let mut selector = SimpleSelector {
+    fn consume_compound_selector(&mut self) -> Option<CompoundSelector> {
+        let mut selector = CompoundSelector {
             tag_name: None,
             id: None,
             classes: Vec::new(),
+            attributes: Vec::new(),
+            pseudo_classes: Vec::new(),
+            pseudo_element: None,
+            span: Span::default(),
         };
 
-        while !self.eof() && self.peek() != '{' && self.peek() != ',' {
+        let start = self.index;
+
+        loop {
             match self.peek() {
-                '.' => {
-                    self.consume_char();
-                    selector.classes.push(self.consume_word());
+                Token::Eof | Token::Whitespace | Token::Comma => break,
+                Token::Delim('>' | '+' | '~') => break,
+                // Nada pode vir depois de um pseudo-elemento no mesmo seletor composto
+                _ if selector.pseudo_element.is_some() => return self.pseudo_element_not_last(),
+                // Seletores de tipo só podem vir no início
+                Token::Ident(name) if self.index == start => {
+                    selector.tag_name = Some(name.clone());
+                    self.index += 1;
                 }
-                '#' => {
-                    self.consume_char();
-                    selector.id = Some(self.consume_word());
+                Token::Delim('*') if self.index == start => self.index += 1,
+                Token::Hash { value, id: true } => {
+                    selector.id = Some(value.clone());
+                    self.index += 1;
                 }
-                '*' => {
-                    self.consume_char();
+                Token::Delim('.') => {
+                    self.index += 1;
+                    selector.classes.push(self.consume_identifier("classe")?);
                 }
-                _ => {
-                    selector.tag_name = Some(self.consume_word());
+                Token::OpenSquare => selector.attributes.push(self.consume_attribute_selector()?),
+                Token::Colon => {
+                    self.index += 1;
+
+                    match self.consume_pseudo_element()? {
+                        Some(pseudo_element) => selector.pseudo_element = Some(pseudo_element),
+                        None => selector.pseudo_classes.push(self.consume_pseudo_class()?),
+                    }
                 }
+                _ => return self.unexpected_token_in_selector(),
             }
+        }
 
-            self.consume_whitespace();
+        if self.index == start {
+            self.error(
+                "missing-selector",
+                "Seletor ausente".to_owned(),
+                self.span(),
+            );
+            return None;
         }
 
-        selector
+        selector.span = self.span_from(start);
+
+        Some(selector)
     }
 
-    fn consume_declaration_block(&mut self) -> Vec<Declaration> {
-        self.consume_char(); // '{'
+    // Lista de seletores em que os itens inválidos são ignorados em vez de
+    // invalidar a lista, como no argumento de `:is` e `:where`
+    fn consume_forgiving_selector_list(&mut self) -> Vec<ComplexSelector> {
+        let mut selectors = Vec::new();
 
-        let mut declarations = Vec::new();
+        loop {
+            let start = self.index;
 
-        while !self.eof() && self.peek() != '}' {
-            self.consume_whitespace();
-            declarations.push(self.consume_declaration());
-            self.consume_whitespace();
+            while !matches!(self.peek(), Token::Eof | Token::Comma) {
+                self.skip_component_value();
+            }
+
+            let diagnostics = self.diagnostics.len();
+            let selector = self.within(start, self.index, |parser| {
+                parser.skip_whitespace();
+                parser.consume_complex_selector()
+            });
+
+            match selector {
+                Some(selector) => selectors.push(selector),
+                None => self.diagnostics.truncate(diagnostics),
+            }
 
-            if self.peek() == ';' {
-                self.consume_char();
+            if self.peek() != &Token::Comma {
+                break;
             }
 
-            self.consume_whitespace();
+            self.index += 1;
         }
 
-        self.consume_char(); // '}'
+        selectors
+    }
 
-        declarations
+    // Argumento de `:has`: seletores que podem começar por um combinador
+    fn consume_relative_selector_list(&mut self) -> Option<Vec<RelativeSelector>> {
+        let mut selectors = Vec::new();
+
+        loop {
+            self.skip_whitespace();
+
+            let combinator = match self.peek() {
+                Token::Delim('>') => Combinator::Child,
+                Token::Delim('+') => Combinator::NextSibling,
+                Token::Delim('~') => Combinator::SubsequentSibling,
+                _ => Combinator::Descendant,
+            };
+
+            if combinator != Combinator::Descendant {
+                self.index += 1;
+                self.skip_whitespace();
+            }
+
+            selectors.push(RelativeSelector {
+                combinator,
+                selector: self.consume_complex_selector()?,
+            });
+
+            match self.peek() {
+                Token::Eof => break,
+                Token::Comma => self.index += 1,
+                _ => return self.unexpected_token_in_selector(),
+            }
+        }
+
+        Some(selectors)
+    }
+
+    // Chamado após o primeiro `:`. Retorna `Some(None)` se não for um
+    // pseudo-elemento, sem consumir nada.
+    fn consume_pseudo_element(&mut self) -> Option<Option<PseudoElement>> {
+        // `:before` e `:after` com um `:` só são aceitos por compatibilidade
+        // com o CSS 2
+        let double_colon = self.peek() == &Token::Colon;
+        let name_index = if double_colon {
+            self.index + 1
+        } else {
+            self.index
+        };
+
+        let name = match self.tokens.get(name_index) {
+            Some((Token::Ident(name), _)) if name_index < self.limit => name.to_ascii_lowercase(),
+            _ if double_colon => {
+                self.index += 1;
+                return self.unexpected_token_in_selector();
+            }
+            _ => return Some(None),
+        };
+
+        let pseudo_element = match name.as_str() {
+            "before" => PseudoElement::Before,
+            "after" => PseudoElement::After,
+            _ if double_colon => {
+                self.index = name_index;
+
+                let (token, span) = self.consume();
+
+                self.error(
+                    "unknown-pseudo-element",
+                    format!("Pseudo-elemento desconhecido: '::{}'", token),
+                    span,
+                );
+                return None;
+            }
+            _ => return Some(None),
+        };
+
+        self.index = name_index + 1;
+
+        Some(Some(pseudo_element))
+    }
+
+    // Chamado após o `:`
+    fn consume_pseudo_class(&mut self) -> Option<PseudoClass> {
+        let (token, span) = match self.peek() {
+            Token::Ident(_) | Token::Function(_) => self.consume(),
+            _ => return self.unexpected_token_in_selector(),
+        };
+
+        let name = match token {
+            Token::Ident(ref name) | Token::Function(ref name) => name.to_ascii_lowercase(),
+            _ => return None,
+        };
+
+        if let Token::Ident(_) = token {
+            let pseudo_class = match name.as_str() {
+                "root" => PseudoClass::Root,
+                "empty" => PseudoClass::Empty,
+                "first-child" => PseudoClass::FirstChild,
+                "last-child" => PseudoClass::LastChild,
+                "only-child" => PseudoClass::OnlyChild,
+                "first-of-type" => PseudoClass::FirstOfType,
+                "last-of-type" => PseudoClass::LastOfType,
+                "only-of-type" => PseudoClass::OnlyOfType,
+                _ => return self.unknown_pseudo_class(&token, span),
+            };
+
+            return Some(pseudo_class);
+        }
+
+        if !matches!(
+            name.as_str(),
+            "nth-child"
+                | "nth-last-child"
+                | "nth-of-type"
+                | "nth-last-of-type"
+                | "not"
+                | "is"
+                | "where"
+                | "has"
+        ) {
+            return self.unknown_pseudo_class(&token, span);
+        }
+
+        // Volta ao token da função para pular o bloco inteiro até o `)`
+        let start = self.index;
+        self.index -= 1;
+
+        if !self.skip_component_value() {
+            self.error(
+                "unclosed-pseudo-class",
+                format!("'{}' sem o ')' correspondente no seletor", token),
+                span,
+            );
+            return None;
+        }
+
+        if self.depth >= MAX_SELECTOR_DEPTH {
+            self.error(
+                "selector-too-deep",
+                "Pseudo-classes aninhadas demais no seletor".to_owned(),
+                span,
+            );
+            return None;
+        }
+
+        self.depth += 1;
+
+        let pseudo_class = self.within(start, self.index - 1, |parser| {
+            parser.skip_whitespace();
+
+            let pseudo_class = match name.as_str() {
+                "nth-child" | "nth-last-child" => {
+                    let nth = parser.consume_nth()?;
+                    parser.skip_whitespace();
+
+                    let of = match parser.peek() {
+                        Token::Ident(of) if of.eq_ignore_ascii_case("of") => {
+                            parser.index += 1;
+                            Some(parser.consume_selector_list()?)
+                        }
+                        _ => None,
+                    };
+
+                    if name == "nth-child" {
+                        PseudoClass::NthChild(nth, of)
+                    } else {
+                        PseudoClass::NthLastChild(nth, of)
+                    }
+                }
+                "nth-of-type" => PseudoClass::NthOfType(parser.consume_nth()?),
+                "nth-last-of-type" => PseudoClass::NthLastOfType(parser.consume_nth()?),
+                "not" => PseudoClass::Not(parser.consume_selector_list()?),
+                "is" => PseudoClass::Is(parser.consume_forgiving_selector_list()),
+                "where" => PseudoClass::Where(parser.consume_forgiving_selector_list()),
+                _ => PseudoClass::Has(parser.consume_relative_selector_list()?),
+            };
+
+            parser.skip_whitespace();
+
+            if parser.peek() != &Token::Eof {
+                return parser.unexpected_token_in_selector();
+            }
+
+            Some(pseudo_class)
+        });
+
+        self.depth -= 1;
+
+        pseudo_class
+    }
+
+    fn unknown_pseudo_class<T>(&mut self, token: &Token, span: Span) -> Option<T> {
+        self.error(
+            "unknown-pseudo-class",
+            format!("Pseudo-classe desconhecida: ':{}'", token),
+            span,
+        );
+
+        None
+    }
+
+    // Expressão `An+B` (https://www.w3.org/TR/css-syntax-3/#anb-microsyntax).
+    // O tokenizador divide `2n-1` em uma dimensão com unidade `n-1`, `-n+1`
+    // em um identificador `-n` e o número `+1`, e assim por diante.
+    fn consume_nth(&mut self) -> Option<Nth> {
+        let (token, span) = self.consume();
+
+        let nth = match token {
+            Token::Ident(ref ident) if ident.eq_ignore_ascii_case("odd") => {
+                Some(Nth { a: 2, b: 1 })
+            }
+            Token::Ident(ref ident) if ident.eq_ignore_ascii_case("even") => {
+                Some(Nth { a: 2, b: 0 })
+            }
+            Token::Number(number) if number.integer => Some(Nth {
+                a: 0,
+                b: number.value as i32,
+            }),
+            Token::Dimension(number, ref unit) if number.integer => {
+                self.consume_nth_b(number.value as i32, unit)
+            }
+            // `+n`: o `+` vira um delimitador, mas não pode haver espaço
+            // antes do `n`
+            Token::Delim('+') => match self.peek() {
+                Token::Ident(ident) if !ident.starts_with('-') => {
+                    let ident = ident.clone();
+                    self.index += 1;
+                    self.consume_nth_b(1, &ident)
+                }
+                _ => None,
+            },
+            Token::Ident(ref ident) => match ident.strip_prefix('-') {
+                Some(rest) => self.consume_nth_b(-1, rest),
+                None => self.consume_nth_b(1, ident),
+            },
+            _ => None,
+        };
+
+        if nth.is_none() {
+            self.error("invalid-nth", "Expressão An+B inválida".to_owned(), span);
+        }
+
+        nth
+    }
+
+    // `rest` é o que segue o coeficiente `a` no mesmo token: `n`, `n-` ou
+    // `n-<dígitos>`. O restante de `B` pode vir nos tokens seguintes.
+    fn consume_nth_b(&mut self, a: i32, rest: &str) -> Option<Nth> {
+        let rest = rest.to_ascii_lowercase();
+        let rest = rest.strip_prefix('n')?;
+
+        let b = match rest {
+            "" => {
+                self.skip_whitespace();
+
+                match *self.peek() {
+                    // `2n+1`, `2n -1`
+                    Token::Number(number) if number.integer && number.signed => {
+                        self.index += 1;
+                        number.value as i32
+                    }
+                    // `2n + 1`, `2n - 1`
+                    Token::Delim(sign @ ('+' | '-')) => {
+                        self.index += 1;
+                        self.skip_whitespace();
+
+                        let b = self.consume_signless_integer()?;
+
+                        if sign == '-' {
+                            -b
+                        } else {
+                            b
+                        }
+                    }
+                    _ => 0,
+                }
+            }
+            // `2n- 1`
+            "-" => {
+                self.skip_whitespace();
+                -self.consume_signless_integer()?
+            }
+            // `2n-1`
+            rest => {
+                let digits = rest.strip_prefix('-')?;
+
+                if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+                    return None;
+                }
+
+                -digits.parse::<i32>().unwrap_or(i32::MAX)
+            }
+        };
+
+        Some(Nth { a, b })
+    }
+
+    fn consume_signless_integer(&mut self) -> Option<i32> {
+        match *self.peek() {
+            Token::Number(number) if number.integer && !number.signed => {
+                self.index += 1;
+                Some(number.value as i32)
+            }
+            _ => None,
+        }
+    }
+
+    fn consume_attribute_selector(&mut self) -> Option<AttributeSelector> {
+        let start = self.index + 1;
+
+        if !self.skip_component_value() {
+            self.error(
+                "unclosed-attribute-selector",
+                "'[' sem o ']' correspondente no seletor".to_owned(),
+                self.span(),
+            );
+            return None;
+        }
+
+        self.within(start, self.index - 1, |parser| {
+            parser.skip_whitespace();
+
+            let name = parser.consume_identifier("atributo")?;
+
+            parser.skip_whitespace();
+
+            if parser.peek() == &Token::Eof {
+                return Some(AttributeSelector {
+                    name,
+                    value: None,
+                    case_sensitivity: None,
+                });
+            }
+
+            let operator = parser.consume_attribute_operator()?;
+
+            parser.skip_whitespace();
+
+            let value = match parser.peek() {
+                Token::Ident(value) | Token::String(value) => value.clone(),
+                _ => {
+                    parser.error(
+                        "missing-attribute-value",
+                        format!("Valor ausente no seletor do atributo '{}'", name),
+                        parser.span(),
+                    );
+                    return None;
+                }
+            };
+
+            parser.index += 1;
+            parser.skip_whitespace();
+
+            let case_sensitivity = match parser.peek() {
+                Token::Eof => None,
+                Token::Ident(flag) if flag.eq_ignore_ascii_case("i") => {
+                    Some(CaseSensitivity::AsciiInsensitive)
+                }
+                Token::Ident(flag) if flag.eq_ignore_ascii_case("s") => {
+                    Some(CaseSensitivity::Sensitive)
+                }
+                _ => return parser.unexpected_token_in_selector(),
+            };
+
+            if case_sensitivity.is_some() {
+                parser.index += 1;
+                parser.skip_whitespace();
+            }
+
+            if parser.peek() != &Token::Eof {
+                return parser.unexpected_token_in_selector();
+            }
+
+            Some(AttributeSelector {
+                name,
+                value: Some((operator, value)),
+                case_sensitivity,
+            })
+        })
     }
 
-    fn consume_declaration(&mut self) -> Declaration {
-        let name = self.consume_word();
+    fn consume_attribute_operator(&mut self) -> Option<AttributeOperator> {
+        let operator = match self.peek() {
+            Token::Delim('=') => {
+                self.index += 1;
+                return Some(AttributeOperator::Equals);
+            }
+            Token::Delim('~') => AttributeOperator::Includes,
+            Token::Delim('|') => AttributeOperator::DashMatch,
+            Token::Delim('^') => AttributeOperator::Prefix,
+            Token::Delim('$') => AttributeOperator::Suffix,
+            Token::Delim('*') => AttributeOperator::Substring,
+            _ => return self.unexpected_token_in_selector(),
+        };
 
-        self.consume_whitespace();
+        self.index += 1;
 
-        self.consume_char(); // ':'
+        // O `=` deve vir colado ao primeiro caractere do operador
+        if self.peek() != &Token::Delim('=') {
+            return self.unexpected_token_in_selector();
+        }
 
-        self.consume_whitespace();
+        self.index += 1;
 
-        let value = self.consume_value();
+        Some(operator)
+    }
+
+    fn pseudo_element_not_last<T>(&mut self) -> Option<T> {
+        self.error(
+            "pseudo-element-not-last",
+            "Pseudo-elementos só podem aparecer no fim do seletor".to_owned(),
+            self.span(),
+        );
+
+        None
+    }
 
-        self.consume_whitespace();
+    fn unexpected_token_in_selector<T>(&mut self) -> Option<T> {
+        self.error(
+            "unexpected-token-in-selector",
+            format!("Token inesperado no seletor: '{}'", self.peek()),
+            self.span(),
+        );
 
-        Declaration { name, value }
+        None
     }
 
-    fn consume_value(&mut self) -> CSSValue {
+    fn consume_identifier(&mut self, what: &str) -> Option<String> {
         match self.peek() {
-            '#' => self.consume_hex_color(),
-            '0'..='9' => self.consume_length(),
-            _ => self.consume_keyword(),
+            Token::Ident(identifier) => {
+                let identifier = identifier.clone();
+                self.index += 1;
+
+                Some(identifier)
+            }
+            _ => {
+                self.error(
+                    "missing-identifier",
+                    format!("Nome de {} ausente no seletor", what),
+                    self.span(),
+                );
+                None
+            }
         }
     }
 
-    fn consume_hex_color(&mut self) -> CSSValue {
-        self.consume_char(); // '#'
-        let r = self.consume_hex_value();
-        let g = self.consume_hex_value();
-        let b = self.consume_hex_value();
-        let a = self.consume_hex_value();
+    fn consume_declaration_list(&mut self) -> Vec<Declaration> {
+        let mut declarations = Vec::new();
+
+        loop {
+            match self.peek() {
+                Token::Eof => break,
+                Token::Whitespace | Token::Semicolon => self.index += 1,
+                Token::AtKeyword(_) => self.consume_at_rule(),
+                Token::Ident(_) => {
+                    let start = self.index;
+                    self.skip_declaration();
+
+                    if let Some(expanded) =
+                        self.within(start, self.index, Parser::consume_declaration)
+                    {
+                        declarations.extend(expanded);
+                    }
+                }
+                _ => {
+                    self.error(
+                        "missing-property-name",
+                        "Nome da propriedade ausente".to_owned(),
+                        self.span(),
+                    );
+                    self.skip_declaration();
+                }
+            }
+        }
 
-        CSSValue::Color(Color { r, g, b, a })
+        declarations
     }
 
-    fn consume_hex_value(&mut self) -> u8 {
-        if self.eof() {
-            0
+    // Chamado com o trecho da declaração até o `;`, que começa com um
+    // identificador. Um shorthand resulta em uma declaração para cada
+    // longhand, todas com o mesmo trecho e a mesma importância.
+    fn consume_declaration(&mut self) -> Option<Vec<Declaration>> {
+        let start = self.index;
+        let Token::Ident(name) = self.consume().0 else {
+            return None;
+        };
+
+        // Propriedades customizadas diferenciam maiúsculas de minúsculas
+        let name = if name.starts_with("--") {
+            name
         } else {
-            let value = u8::from_str_radix(&self.input[self.cursor..=self.cursor + 1], 16)
-                .unwrap_or_default();
-            self.advance_by(2);
-            value
+            name.to_ascii_lowercase()
+        };
+
+        self.skip_whitespace();
+
+        if self.peek() != &Token::Colon {
+            self.error(
+                "missing-colon",
+                format!("':' ausente após a propriedade '{}'", name),
+                self.span(),
+            );
+            return None;
+        }
+
+        self.index += 1;
+        self.skip_whitespace();
+
+        let important = self.strip_important();
+        let mut values = match name.as_str() {
+            // Palavras-chave globais valem para todas as propriedades
+            _ if self.at_css_wide_keyword() => vec![self.consume_value(&name)?],
+            "content" => vec![self.consume_content()?],
+            "counter-reset" => vec![self.consume_counters(0)?],
+            "counter-increment" => vec![self.consume_counters(1)?],
+            _ => self.consume_component_values(&name)?,
+        };
+        let mut span = self.span_from(start);
+
+        self.skip_whitespace();
+
+        if self.peek() != &Token::Eof {
+            return self.unexpected_token_in_value(&name);
         }
+
+        if let Some(end) = important {
+            span.end = self.tokens[end - 1].1.end;
+        }
+
+        let declaration = |name: &str, value: CSSValue| Declaration {
+            name: name.to_owned(),
+            value,
+            important: important.is_some(),
+            span,
+        };
+
+        let Some(longhands) = shorthands::longhands(&name) else {
+            let value = match values.len() {
+                1 => values.remove(0),
+                _ => CSSValue::List(values),
+            };
+
+            return Some(vec![declaration(&name, value)]);
+        };
+
+        let values = match values.as_slice() {
+            [CSSValue::Keyword(keyword)] if CSS_WIDE_KEYWORDS.contains(&keyword.as_str()) => {
+                vec![values[0].clone(); longhands.len()]
+            }
+            values => match shorthands::expand(&name, values) {
+                Some(values) => values,
+                None => {
+                    self.error(
+                        "invalid-shorthand",
+                        format!("Valor inválido para o shorthand '{}'", name),
+                        span,
+                    );
+                    return None;
+                }
+            },
+        };
+
+        Some(
+            longhands
+                .iter()
+                .zip(values)
+                .map(|(longhand, value)| declaration(longhand, value))
+                .collect(),
+        )
     }
 
-    fn consume_length(&mut self) -> CSSValue {
-        let value = self.consume_number();
-        let unit = self.consume_unit();
+    // Um ou mais valores separados por espaços, como em `margin: 0 auto`.
+    // Vírgulas e `/` viram `CSSValue::Separator`.
+    fn consume_component_values(&mut self, name: &str) -> Option<Vec<CSSValue>> {
+        let mut values = Vec::new();
+
+        loop {
+            self.skip_whitespace();
+
+            let value = match self.peek() {
+                Token::Eof => break,
+                Token::Comma => CSSValue::Separator(','),
+                Token::Delim('/') => CSSValue::Separator('/'),
+                Token::String(value) => CSSValue::String(value.clone()),
+                _ => {
+                    values.push(self.consume_value(name)?);
+                    continue;
+                }
+            };
+
+            self.index += 1;
+            values.push(value);
+        }
+
+        // Sem nenhum componente, `consume_value` informa o valor ausente
+        if values.is_empty() {
+            values.push(self.consume_value(name)?);
+        }
 
-        CSSValue::Length(value, unit)
+        Some(values)
     }
 
-    fn consume_number(&mut self) -> f32 {
-        self.consume_while(is_real_number_digit)
-            .parse()
-            .unwrap_or_default()
+    fn at_css_wide_keyword(&self) -> bool {
+        match self.peek() {
+            Token::Ident(keyword) => CSS_WIDE_KEYWORDS
+                .iter()
+                .any(|wide_keyword| keyword.eq_ignore_ascii_case(wide_keyword)),
+            _ => false,
+        }
     }
 
-    fn consume_unit(&mut self) -> CSSUnit {
-        match self.consume_word().as_str() {
-            "px" => CSSUnit::Px,
-            unit => panic!("Unidade desconhecida: {}", unit),
+    // Se o valor termina com `!important` (espaços são permitidos antes e
+    // depois do `!`), exclui esses tokens do trecho sendo analisado e
+    // retorna o índice logo após `important`
+    fn strip_important(&mut self) -> Option<usize> {
+        let skip_whitespace_before = |parser: &Parser, mut end: usize| {
+            while end > parser.index && parser.tokens[end - 1].0 == Token::Whitespace {
+                end -= 1;
+            }
+
+            end
+        };
+
+        let end = skip_whitespace_before(self, self.limit);
+
+        match &self.tokens[end.checked_sub(1).filter(|&last| last >= self.index)?].0 {
+            Token::Ident(keyword) if keyword.eq_ignore_ascii_case("important") => {}
+            _ => return None,
         }
+
+        let bang = skip_whitespace_before(self, end - 1)
+            .checked_sub(1)
+            .filter(|&bang| bang >= self.index)?;
+
+        if self.tokens[bang].0 != Token::Delim('!') {
+            return None;
+        }
+
+        self.limit = bang;
+
+        Some(end)
     }
 
-    fn consume_keyword(&mut self) -> CSSValue {
-        CSSValue::Keyword(self.consume_word())
+    // `none`, `normal` ou uma sequência de strings, `attr()`, `counter()` e
+    // `counters()`
+    fn consume_content(&mut self) -> Option<CSSValue> {
+        if let Token::Ident(_) = self.peek() {
+            return self.consume_value("content");
+        }
+
+        let mut items = Vec::new();
+
+        loop {
+            let item = match self.peek() {
+                Token::Eof => break,
+                Token::Whitespace => {
+                    self.index += 1;
+                    continue;
+                }
+                Token::String(value) => {
+                    let item = ContentItem::String(value.clone());
+                    self.index += 1;
+                    item
+                }
+                Token::Function(name) => match name.to_ascii_lowercase().as_str() {
+                    "attr" => self.consume_function(|parser| {
+                        Some(ContentItem::Attr(parser.consume_argument_name()?))
+                    })?,
+                    "counter" => self.consume_function(|parser| {
+                        let name = parser.consume_argument_name()?;
+                        let style = parser.consume_counter_style()?;
+
+                        Some(ContentItem::Counter { name, style })
+                    })?,
+                    "counters" => self.consume_function(|parser| {
+                        let name = parser.consume_argument_name()?;
+
+                        parser.consume_argument_comma()?;
+
+                        let separator = match parser.peek() {
+                            Token::String(separator) => separator.clone(),
+                            _ => return parser.unexpected_token_in_value("content"),
+                        };
+
+                        parser.index += 1;
+                        parser.skip_whitespace();
+
+                        let style = parser.consume_counter_style()?;
+
+                        Some(ContentItem::Counters {
+                            name,
+                            separator,
+                            style,
+                        })
+                    })?,
+                    _ => return self.unexpected_token_in_value("content"),
+                },
+                _ => return self.unexpected_token_in_value("content"),
+            };
+
+            items.push(item);
+        }
+
+        if items.is_empty() {
+            self.error("missing-value", "Valor ausente".to_owned(), self.span());
+            return None;
+        }
+
+        Some(CSSValue::Content(items))
     }
 
-    fn eof(&self) -> bool {
-        self.cursor >= self.input.len()
+    // `none` ou uma lista de nomes, cada um seguido de um inteiro opcional
+    fn consume_counters(&mut self, default: i32) -> Option<CSSValue> {
+        let mut counters = Vec::new();
+
+        loop {
+            match self.peek() {
+                Token::Eof => break,
+                Token::Whitespace => self.index += 1,
+                Token::Ident(name) if name.eq_ignore_ascii_case("none") && counters.is_empty() => {
+                    self.index += 1;
+                    self.skip_whitespace();
+
+                    return Some(CSSValue::Counters(counters));
+                }
+                Token::Ident(name) => {
+                    let name = name.clone();
+                    self.index += 1;
+                    self.skip_whitespace();
+
+                    let value = match *self.peek() {
+                        Token::Number(number) if number.integer => {
+                            self.index += 1;
+                            number.value as i32
+                        }
+                        _ => default,
+                    };
+
+                    counters.push((name, value));
+                }
+                _ => return self.unexpected_token_in_value("contador"),
+            }
+        }
+
+        if counters.is_empty() {
+            self.error("missing-value", "Valor ausente".to_owned(), self.span());
+            return None;
+        }
+
+        Some(CSSValue::Counters(counters))
     }
 
-    fn peek(&self) -> char {
-        self.input.chars().nth(self.cursor).unwrap()
+    // Aplica `parse` aos argumentos da função atual e consome a função
+    // inteira. `parse` deve consumir todos os argumentos.
+    fn consume_function<T>(&mut self, parse: impl FnOnce(&mut Parser) -> Option<T>) -> Option<T> {
+        let (token, span) = (self.peek().clone(), self.span());
+        let start = self.index + 1;
+
+        if !self.skip_component_value() {
+            self.error(
+                "unclosed-function",
+                format!("'{}' sem o ')' correspondente", token),
+                span,
+            );
+            return None;
+        }
+
+        self.within(start, self.index - 1, |parser| {
+            parser.skip_whitespace();
+
+            let value = parse(parser)?;
+
+            parser.skip_whitespace();
+
+            if parser.peek() != &Token::Eof {
+                return parser.unexpected_token_in_value(&token.to_string());
+            }
+
+            Some(value)
+        })
+    }
+
+    fn consume_argument_name(&mut self) -> Option<String> {
+        let Token::Ident(name) = self.peek().clone() else {
+            return self.unexpected_token_in_value("função");
+        };
+
+        self.index += 1;
+        self.skip_whitespace();
+
+        Some(name)
+    }
+
+    fn consume_argument_comma(&mut self) -> Option<()> {
+        if self.peek() != &Token::Comma {
+            return self.unexpected_token_in_value("função");
+        }
+
+        self.index += 1;
+        self.skip_whitespace();
+
+        Some(())
+    }
+
+    // Estilo opcional após uma vírgula; o padrão é `decimal`
+    fn consume_counter_style(&mut self) -> Option<String> {
+        if self.peek() != &Token::Comma {
+            return Some("decimal".to_owned());
+        }
+
+        self.consume_argument_comma()?;
+
+        Some(self.consume_argument_name()?.to_ascii_lowercase())
+    }
+
+    fn unexpected_token_in_value<T>(&mut self, name: &str) -> Option<T> {
+        self.error(
+            "unexpected-token-in-value",
+            format!("Token inesperado no valor de '{}': '{}'", name, self.peek()),
+            self.span(),
+        );
+
+        None
+    }
+
+    fn consume_value(&mut self, name: &str) -> Option<CSSValue> {
+        let (token, span) = self.consume();
+
+        let value = match token {
+            Token::Ident(keyword) => CSSValue::Keyword(keyword.to_ascii_lowercase()),
+            Token::Hash { ref value, .. } => match parse_hex_color(value) {
+                Some(color) => CSSValue::Color(color),
+                None => {
+                    self.error("invalid-color", format!("Cor inválida: {}", token), span);
+                    return None;
+                }
+            },
+            Token::Dimension(number, ref unit) => match unit.to_ascii_lowercase().as_str() {
+                "px" => CSSValue::Length(number.value, CSSUnit::Px),
+                _ => {
+                    self.error(
+                        "unknown-unit",
+                        format!("Unidade desconhecida: {}", unit),
+                        span,
+                    );
+                    return None;
+                }
+            },
+            Token::Number(number) => CSSValue::Number(number.value),
+            Token::Percentage(number) => CSSValue::Percentage(number.value),
+            Token::Eof => {
+                self.error("missing-value", "Valor ausente".to_owned(), span);
+                return None;
+            }
+            token => {
+                self.error(
+                    "unsupported-value",
+                    format!("Valor não suportado para '{}': '{}'", name, token),
+                    span,
+                );
+                return None;
+            }
+        };
+
+        Some(value)
+    }
+
+    // Executa `parse` como se a entrada fosse apenas os tokens de `start`
+    // até `end`, sem mover a posição atual
+    fn within<T>(&mut self, start: usize, end: usize, parse: impl FnOnce(&mut Parser) -> T) -> T {
+        let (index, limit) = (self.index, self.limit);
+
+        self.index = start;
+        self.limit = end;
+
+        let value = parse(self);
+
+        self.index = index;
+        self.limit = limit;
+
+        value
     }
 
-    fn advance_by(&mut self, by: usize) {
-        self.cursor += by
+    // Aplica `parse` ao conteúdo do bloco `{}` atual e o consome por inteiro
+    fn consume_block<T>(&mut self, parse: impl FnOnce(&mut Parser) -> T) -> T {
+        let start = self.index + 1;
+        let closed = self.skip_component_value();
+        let end = if closed { self.index - 1 } else { self.index };
+
+        if !closed {
+            self.error(
+                "eof-in-declaration-block",
+                "Fim do arquivo dentro de um bloco de declarações".to_owned(),
+                self.span(),
+            );
+        }
+
+        self.within(start, end, parse)
     }
 
-    fn consume_char(&mut self) -> char {
-        let c = self.peek();
-        self.advance_by(1);
-        c
+    // Recuperação de erros: pula até o `;` que termina a declaração, sem
+    // consumi-lo, ignorando os que estiverem dentro de blocos
+    fn skip_declaration(&mut self) {
+        while !matches!(self.peek(), Token::Eof | Token::Semicolon) {
+            self.skip_component_value();
+        }
     }
 
-    fn consume_while<F: Fn(char) -> bool>(&mut self, predicate: F) -> String {
-        let mut str = String::new();
+    // Pula um valor componente: um token ou um bloco inteiro, de `(`, `[`,
+    // `{` ou de uma função até o delimitador que o fecha. Retorna se os
+    // blocos foram fechados antes do fim da entrada.
+    fn skip_component_value(&mut self) -> bool {
+        // Delimitadores de fechamento esperados, do bloco mais externo ao
+        // mais interno. Uma pilha em vez de recursão evita estourar a pilha
+        // de chamadas com blocos muito aninhados.
+        let mut closing = Vec::new();
+
+        while self.index < self.limit {
+            let token = &self.tokens[self.index].0;
+            self.index += 1;
+
+            match token {
+                Token::OpenCurly => closing.push(Token::CloseCurly),
+                Token::OpenSquare => closing.push(Token::CloseSquare),
+                Token::OpenParen | Token::Function(_) => closing.push(Token::CloseParen),
+                token if closing.last() == Some(token) => {
+                    closing.pop();
+                }
+                _ => {}
+            }
 
-        while !self.eof() && predicate(self.peek()) {
-            str.push(self.consume_char())
+            if closing.is_empty() {
+                return true;
+            }
         }
 
-        str
+        false
     }
 
-    fn consume_whitespace(&mut self) -> String {
-        self.consume_while(|c| c.is_whitespace())
+    fn skip_whitespace(&mut self) {
+        while self.peek() == &Token::Whitespace {
+            self.index += 1;
+        }
     }
 
-    fn consume_word(&mut self) -> String {
-        self.consume_while(is_word_char)
+    fn peek(&self) -> &Token {
+        if self.index < self.limit {
+            &self.tokens[self.index].0
+        } else {
+            &EOF
+        }
+    }
+
+    fn consume(&mut self) -> (Token, Span) {
+        let token = (self.peek().clone(), self.span());
+
+        if self.index < self.limit {
+            self.index += 1;
+        }
+
+        token
+    }
+
+    // Span do token atual; no fim da entrada, uma posição vazia
+    fn span(&self) -> Span {
+        let span = self.tokens[self.index.min(self.limit)].1;
+
+        if self.index < self.limit {
+            span
+        } else {
+            Span {
+                start: span.start,
+                end: span.start,
+            }
+        }
+    }
+
+    // Do token `start` até o último token consumido
+    fn span_from(&self, start: usize) -> Span {
+        Span {
+            start: self.tokens[start].1.start,
+            end: self.tokens[self.index.max(start + 1) - 1].1.end,
+        }
+    }
+
+    fn error(&mut self, kind: &'static str, message: String, span: Span) {
+        self.diagnostics.push(Diagnostic {
+            kind,
+            message,
+            span,
+        });
     }
 }
 
-fn is_word_char(c: char) -> bool {
-    match c {
-        'a'..='z' | 'A'..='Z' | '-' | '0'..='9' => true,
-        _ => false,
+// Cores básicas do CSS 2.1 (https://www.w3.org/TR/CSS21/syndata.html#color-units),
+// com o nome já em minúsculas
+pub fn named_color(name: &str) -> Option<Color> {
+    let hex = match name {
+        "black" => "000000",
+        "silver" => "c0c0c0",
+        "gray" => "808080",
+        "white" => "ffffff",
+        "maroon" => "800000",
+        "red" => "ff0000",
+        "purple" => "800080",
+        "fuchsia" => "ff00ff",
+        "green" => "008000",
+        "lime" => "00ff00",
+        "olive" => "808000",
+        "yellow" => "ffff00",
+        "navy" => "000080",
+        "blue" => "0000ff",
+        "teal" => "008080",
+        "aqua" => "00ffff",
+        "orange" => "ffa500",
+        _ => return None,
+    };
+
+    parse_hex_color(hex)
+}
+
+// `#rgb`, `#rgba`, `#rrggbb` ou `#rrggbbaa`
+fn parse_hex_color(value: &str) -> Option<Color> {
+    if !value.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
     }
+
+    let digit = |index: usize| u8::from_str_radix(&value[index..index + 1], 16).unwrap();
+    let pair = |index: usize| u8::from_str_radix(&value[index..index + 2], 16).unwrap();
+
+    let (r, g, b, a) = match value.len() {
+        3 => (digit(0) * 17, digit(1) * 17, digit(2) * 17, 255),
+        4 => (digit(0) * 17, digit(1) * 17, digit(2) * 17, digit(3) * 17),
+        6 => (pair(0), pair(2), pair(4), 255),
+        8 => (pair(0), pair(2), pair(4), pair(6)),
+        _ => return None,
+    };
+
+    Some(Color { r, g, b, a })
 }
 
-fn is_real_number_digit(c: char) -> bool {
-    match c {
-        '0'..='9' | '.' => true,
-        _ => false,
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(diagnostics: &[Diagnostic]) -> Vec<&str> {
+        diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.kind)
+            .collect()
+    }
+
+    fn names(declarations: &[Declaration]) -> Vec<&str> {
+        declarations
+            .iter()
+            .map(|declaration| declaration.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn invalid_declarations_are_dropped() {
+        let result = parse_with_diagnostics(
+            "p { color: red; width 10px; margin-top: 1px; height: ; padding-left: 2px }".to_owned(),
+        );
+        let rule = &result.value.rules[0];
+
+        assert_eq!(
+            names(&rule.declarations),
+            ["color", "margin-top", "padding-left"]
+        );
+        assert_eq!(
+            kinds(&result.diagnostics),
+            ["missing-colon", "missing-value"]
+        );
+    }
+
+    #[test]
+    fn invalid_rules_are_dropped() {
+        let result = parse_with_diagnostics(
+            "@media screen { a { color: red } } p$ { color: red } @import 'x.css'; div { width: 1px }"
+                .to_owned(),
+        );
+
+        assert_eq!(result.value.rules.len(), 1);
+        assert_eq!(
+            result.value.rules[0].selectors[0].compounds[0]
+                .tag_name
+                .as_deref(),
+            Some("div")
+        );
+        assert_eq!(
+            kinds(&result.diagnostics),
+            [
+                "unsupported-at-rule",
+                "unexpected-token-in-selector",
+                "unsupported-at-rule"
+            ]
+        );
+    }
+
+    #[test]
+    fn selectors_keep_source_order() {
+        let stylesheet = parse("#a, p, .b.c, * { color: red }".to_owned());
+        let specificities: Vec<Specificity> = stylesheet.rules[0]
+            .selectors
+            .iter()
+            .map(ComplexSelector::specificity)
+            .collect();
+
+        assert_eq!(specificities, [(1, 0, 0), (0, 0, 1), (0, 2, 0), (0, 0, 0)]);
+    }
+
+    #[test]
+    fn pseudo_element_must_be_last() {
+        for input in [
+            "p::before.x { color: red }",
+            "p::after#id { color: red }",
+            "p::before[a] { color: red }",
+        ] {
+            let result = parse_with_diagnostics(input.to_owned());
+
+            assert!(result.value.rules.is_empty(), "{input}");
+            assert_eq!(
+                kinds(&result.diagnostics),
+                ["pseudo-element-not-last"],
+                "{input}"
+            );
+        }
+
+        assert_eq!(
+            parse("p.x::before { color: red }".to_owned()).rules.len(),
+            1
+        );
+    }
+
+    #[test]
+    fn comments_strings_and_urls() {
+        let stylesheet = parse(
+            "/* a { } */ p { content: \"}\"; background: url(x.png) /* ; */; color: blue }"
+                .to_owned(),
+        );
+        let declarations = &stylesheet.rules[0].declarations;
+
+        assert_eq!(stylesheet.rules.len(), 1);
+        assert_eq!(
+            declarations[0].value,
+            CSSValue::Content(vec![ContentItem::String("}".to_owned())])
+        );
+        assert!(names(declarations).contains(&"color"));
+    }
+
+    #[test]
+    fn unclosed_blocks() {
+        let result = parse_with_diagnostics(
+            "p { color: red; margin-top: (1px; } div { width: 1px".to_owned(),
+        );
+
+        assert_eq!(result.value.rules.len(), 1);
+        assert_eq!(names(&result.value.rules[0].declarations), ["color"]);
+        assert!(kinds(&result.diagnostics).contains(&"eof-in-declaration-block"));
+    }
+
+    #[test]
+    fn deeply_nested_selectors() {
+        let nested = |name: &str, depth: usize| {
+            format!(
+                "{}a{}",
+                format!(":{name}(").repeat(depth),
+                ")".repeat(depth)
+            )
+        };
+
+        for name in ["not", "is", "has"] {
+            assert_eq!(parse_selectors(nested(name, 256)).value.len(), 1);
+        }
+
+        let result = parse_selectors(nested("not", 10_000));
+        assert!(result.value.is_empty());
+        assert!(kinds(&result.diagnostics).contains(&"selector-too-deep"));
+
+        // Em `:is` e `:where` o argumento aninhado demais é descartado
+        let result = parse_selectors(format!("{}, b", nested("where", 300)));
+        assert_eq!(result.value.len(), 2);
+        assert!(result.diagnostics.is_empty());
+
+        let stylesheet = parse(format!(
+            "{} {{ color: red }} p {{ color: red }}",
+            nested("has", 10_000)
+        ));
+        assert_eq!(stylesheet.rules.len(), 1);
+    }
+
+    #[test]
+    fn important_and_style_attribute() {
+        let result =
+            parse_declarations("color: red !important; ; width: 2px ! IMPORTANT; x".to_owned());
+        let declarations = result.value;
+
+        assert_eq!(names(&declarations), ["color", "width"]);
+        assert!(declarations.iter().all(|declaration| declaration.important));
+        assert_eq!(declarations[1].value, CSSValue::Length(2.0, CSSUnit::Px));
+        assert_eq!(kinds(&result.diagnostics), ["missing-colon"]);
     }
 }
@@ -0,0 +1,740 @@
+use super::{named_color, CSSValue, CSS_WIDE_KEYWORDS};
+
+// Propriedades geradas por cada shorthand, na ordem dos valores retornados
+// por `expand`
+pub(super) fn longhands(shorthand: &str) -> Option<&'static [&'static str]> {
+    let longhands: &[&str] = match shorthand {
+        "margin" => &["margin-top", "margin-right", "margin-bottom", "margin-left"],
+        "padding" => &[
+            "padding-top",
+            "padding-right",
+            "padding-bottom",
+            "padding-left",
+        ],
+        "inset" => &["top", "right", "bottom", "left"],
+        "border-width" => &[
+            "border-top-width",
+            "border-right-width",
+            "border-bottom-width",
+            "border-left-width",
+        ],
+        "border-style" => &[
+            "border-top-style",
+            "border-right-style",
+            "border-bottom-style",
+            "border-left-style",
+        ],
+        "border-color" => &[
+            "border-top-color",
+            "border-right-color",
+            "border-bottom-color",
+            "border-left-color",
+        ],
+        "border-top" => &["border-top-width", "border-top-style", "border-top-color"],
+        "border-right" => &[
+            "border-right-width",
+            "border-right-style",
+            "border-right-color",
+        ],
+        "border-bottom" => &[
+            "border-bottom-width",
+            "border-bottom-style",
+            "border-bottom-color",
+        ],
+        "border-left" => &[
+            "border-left-width",
+            "border-left-style",
+            "border-left-color",
+        ],
+        "border" => &[
+            "border-top-width",
+            "border-right-width",
+            "border-bottom-width",
+            "border-left-width",
+            "border-top-style",
+            "border-right-style",
+            "border-bottom-style",
+            "border-left-style",
+            "border-top-color",
+            "border-right-color",
+            "border-bottom-color",
+            "border-left-color",
+        ],
+        "background" => &[
+            "background-color",
+            "background-image",
+            "background-repeat",
+            "background-attachment",
+            "background-position",
+        ],
+        "font" => &[
+            "font-style",
+            "font-variant",
+            "font-weight",
+            "font-stretch",
+            "font-size",
+            "line-height",
+            "font-family",
+        ],
+        "flex" => &["flex-grow", "flex-shrink", "flex-basis"],
+        "gap" => &["row-gap", "column-gap"],
+        _ => return None,
+    };
+
+    Some(longhands)
+}
+
+// Valores das propriedades de `longhands(shorthand)`, ou `None` se os
+// componentes não formarem um valor válido para o shorthand. Longhands
+// omitidas recebem o valor inicial.
+pub(super) fn expand(shorthand: &str, values: &[CSSValue]) -> Option<Vec<CSSValue>> {
+    match shorthand {
+        "margin" | "inset" => sides(values, is_length_percentage_auto),
+        "padding" => sides(values, is_non_negative_length_percentage),
+        "border-width" => sides(values, is_border_width),
+        "border-style" => sides(values, is_border_style),
+        "border-color" => sides(values, is_color),
+        "border-top" | "border-right" | "border-bottom" | "border-left" => border_side(values),
+        "border" => {
+            let side = border_side(values)?;
+
+            Some(
+                side.iter()
+                    .flat_map(|value| [value, value, value, value])
+                    .cloned()
+                    .collect(),
+            )
+        }
+        "background" => background(values),
+        "font" => font(values),
+        "flex" => flex(values),
+        "gap" => gap(values),
+        _ => None,
+    }
+}
+
+// Um a quatro valores: topo, direita, base e esquerda, com os lados
+// omitidos copiados do lado oposto
+fn sides(values: &[CSSValue], valid: fn(&CSSValue) -> bool) -> Option<Vec<CSSValue>> {
+    if !values.iter().all(valid) {
+        return None;
+    }
+
+    let (top, right, bottom, left) = match values {
+        [all] => (all, all, all, all),
+        [vertical, horizontal] => (vertical, horizontal, vertical, horizontal),
+        [top, horizontal, bottom] => (top, horizontal, bottom, horizontal),
+        [top, right, bottom, left] => (top, right, bottom, left),
+        _ => return None,
+    };
+
+    Some(vec![
+        top.clone(),
+        right.clone(),
+        bottom.clone(),
+        left.clone(),
+    ])
+}
+
+// Largura, estilo e cor em qualquer ordem, cada um no máximo uma vez
+fn border_side(values: &[CSSValue]) -> Option<Vec<CSSValue>> {
+    let mut width = None;
+    let mut style = None;
+    let mut color = None;
+
+    for value in values {
+        let slot = if is_border_width(value) {
+            &mut width
+        } else if is_border_style(value) {
+            &mut style
+        } else if is_color(value) {
+            &mut color
+        } else {
+            return None;
+        };
+
+        if slot.replace(value.clone()).is_some() {
+            return None;
+        }
+    }
+
+    Some(vec![
+        width.unwrap_or_else(|| keyword("medium")),
+        style.unwrap_or_else(|| keyword("none")),
+        color.unwrap_or_else(|| keyword("currentcolor")),
+    ])
+}
+
+// Apenas uma camada: cor, `none` como imagem, repetição, `background-attachment`
+// e posição, em qualquer ordem. Os componentes da posição devem ser
+// consecutivos.
+fn background(values: &[CSSValue]) -> Option<Vec<CSSValue>> {
+    let mut color = None;
+    let mut image = None;
+    let mut repeat = None;
+    let mut attachment = None;
+    let mut position: Vec<CSSValue> = Vec::new();
+    let mut position_ended = false;
+
+    for value in values {
+        if is_background_position(value) {
+            if position_ended || position.len() == 4 {
+                return None;
+            }
+
+            position.push(value.clone());
+            continue;
+        }
+
+        position_ended = !position.is_empty();
+
+        let slot = if is_color(value) {
+            &mut color
+        } else if is_keyword(value, &["none"]) {
+            &mut image
+        } else if is_keyword(
+            value,
+            &[
+                "repeat",
+                "repeat-x",
+                "repeat-y",
+                "no-repeat",
+                "space",
+                "round",
+            ],
+        ) {
+            &mut repeat
+        } else if is_keyword(value, &["scroll", "fixed", "local"]) {
+            &mut attachment
+        } else {
+            return None;
+        };
+
+        if slot.replace(value.clone()).is_some() {
+            return None;
+        }
+    }
+
+    let position = match position.len() {
+        0 => CSSValue::List(vec![CSSValue::Percentage(0.0), CSSValue::Percentage(0.0)]),
+        1 => position.remove(0),
+        _ => CSSValue::List(position),
+    };
+
+    Some(vec![
+        color.unwrap_or_else(|| keyword("transparent")),
+        image.unwrap_or_else(|| keyword("none")),
+        repeat.unwrap_or_else(|| keyword("repeat")),
+        attachment.unwrap_or_else(|| keyword("scroll")),
+        position,
+    ])
+}
+
+// `[<style> || <variant> || <weight> || <stretch>]? <size> [/ <line-height>]? <family>`.
+// `normal` antes do tamanho não define nenhuma das propriedades opcionais.
+fn font(values: &[CSSValue]) -> Option<Vec<CSSValue>> {
+    let mut style = None;
+    let mut variant = None;
+    let mut weight = None;
+    let mut stretch = None;
+    let mut index = 0;
+
+    while index < values.len() && index < 4 && !is_font_size(&values[index]) {
+        let value = &values[index];
+        let slot = if is_keyword(value, &["normal"]) {
+            index += 1;
+            continue;
+        } else if is_keyword(value, &["italic", "oblique"]) {
+            &mut style
+        } else if is_keyword(value, &["small-caps"]) {
+            &mut variant
+        } else if is_font_weight(value) {
+            &mut weight
+        } else if is_keyword(value, FONT_STRETCH_KEYWORDS) {
+            &mut stretch
+        } else {
+            return None;
+        };
+
+        if slot.replace(value.clone()).is_some() {
+            return None;
+        }
+
+        index += 1;
+    }
+
+    let size = values
+        .get(index)
+        .filter(|value| is_font_size(value))?
+        .clone();
+    index += 1;
+
+    let line_height = match values.get(index) {
+        Some(CSSValue::Separator('/')) => {
+            let line_height = values.get(index + 1).filter(|value| {
+                is_keyword(value, &["normal"]) || is_number(value) || is_length_percentage(value)
+            })?;
+            index += 2;
+
+            line_height.clone()
+        }
+        _ => keyword("normal"),
+    };
+
+    let family = font_family(&values[index..])?;
+
+    Some(vec![
+        style.unwrap_or_else(|| keyword("normal")),
+        variant.unwrap_or_else(|| keyword("normal")),
+        weight.unwrap_or_else(|| keyword("normal")),
+        stretch.unwrap_or_else(|| keyword("normal")),
+        size,
+        line_height,
+        family,
+    ])
+}
+
+// Nomes separados por vírgulas, cada um uma string ou uma sequência de
+// identificadores. Mantém a mesma representação de `font-family`.
+fn font_family(values: &[CSSValue]) -> Option<CSSValue> {
+    // Palavras-chave globais só valem como o valor inteiro, nunca como
+    // nome dentro de uma lista
+    let is_name = |name: &CSSValue| match name {
+        CSSValue::Keyword(name) => values.len() == 1 || !CSS_WIDE_KEYWORDS.contains(&name.as_str()),
+        _ => false,
+    };
+
+    let valid = values
+        .split(|value| value == &CSSValue::Separator(','))
+        .all(|family| match family {
+            [CSSValue::String(_)] => true,
+            [] => false,
+            names => names.iter().all(is_name),
+        });
+
+    match values {
+        _ if !valid => None,
+        [family] => Some(family.clone()),
+        families => Some(CSSValue::List(families.to_vec())),
+    }
+}
+
+// `none`, `auto` ou `<grow> <shrink>? || <basis>`. Sem a base, ela é 0%.
+// Um zero sem unidade é lido como fator enquanto houver fatores a preencher.
+fn flex(values: &[CSSValue]) -> Option<Vec<CSSValue>> {
+    match values {
+        [value] if is_keyword(value, &["none"]) => {
+            return Some(vec![
+                CSSValue::Number(0.0),
+                CSSValue::Number(0.0),
+                keyword("auto"),
+            ]);
+        }
+        [value] if is_keyword(value, &["auto"]) => {
+            return Some(vec![
+                CSSValue::Number(1.0),
+                CSSValue::Number(1.0),
+                keyword("auto"),
+            ]);
+        }
+        _ => {}
+    }
+
+    let mut grow = None;
+    let mut shrink = None;
+    let mut basis = None;
+    let mut previous_was_factor = false;
+
+    for value in values {
+        let factor = match value {
+            CSSValue::Number(number) if *number >= 0.0 => Some(*number),
+            _ => None,
+        };
+
+        match factor {
+            Some(factor) if grow.is_none() => {
+                grow = Some(factor);
+                previous_was_factor = true;
+                continue;
+            }
+            Some(factor) if shrink.is_none() && previous_was_factor => {
+                shrink = Some(factor);
+                continue;
+            }
+            _ => previous_was_factor = false,
+        }
+
+        let is_basis = is_length_percentage(value) || is_keyword(value, &["auto", "content"]);
+
+        if !is_basis || basis.replace(value.clone()).is_some() {
+            return None;
+        }
+    }
+
+    let basis = match (basis, grow) {
+        (Some(basis), _) => basis,
+        (None, Some(_)) => CSSValue::Percentage(0.0),
+        (None, None) => return None,
+    };
+
+    Some(vec![
+        CSSValue::Number(grow.unwrap_or(1.0)),
+        CSSValue::Number(shrink.unwrap_or(1.0)),
+        basis,
+    ])
+}
+
+// `row-gap` e, opcionalmente, `column-gap`, que por padrão é igual
+fn gap(values: &[CSSValue]) -> Option<Vec<CSSValue>> {
+    let valid = |value: &CSSValue| is_length_percentage(value) || is_keyword(value, &["normal"]);
+
+    match values {
+        [both] if valid(both) => Some(vec![both.clone(), both.clone()]),
+        [row, column] if valid(row) && valid(column) => Some(vec![row.clone(), column.clone()]),
+        _ => None,
+    }
+}
+
+const FONT_STRETCH_KEYWORDS: &[&str] = &[
+    "ultra-condensed",
+    "extra-condensed",
+    "condensed",
+    "semi-condensed",
+    "semi-expanded",
+    "expanded",
+    "extra-expanded",
+    "ultra-expanded",
+];
+
+fn keyword(keyword: &str) -> CSSValue {
+    CSSValue::Keyword(keyword.to_owned())
+}
+
+fn is_keyword(value: &CSSValue, keywords: &[&str]) -> bool {
+    matches!(value, CSSValue::Keyword(keyword) if keywords.contains(&keyword.as_str()))
+}
+
+fn is_number(value: &CSSValue) -> bool {
+    matches!(value, CSSValue::Number(_))
+}
+
+fn is_length_percentage(value: &CSSValue) -> bool {
+    value.length().is_some() || matches!(value, CSSValue::Percentage(_))
+}
+
+fn is_non_negative_length_percentage(value: &CSSValue) -> bool {
+    value.length().is_some_and(|length| length >= 0.0)
+        || matches!(value, CSSValue::Percentage(percentage) if *percentage >= 0.0)
+}
+
+fn is_length_percentage_auto(value: &CSSValue) -> bool {
+    is_length_percentage(value) || is_keyword(value, &["auto"])
+}
+
+fn is_border_width(value: &CSSValue) -> bool {
+    value.length().is_some_and(|width| width >= 0.0)
+        || is_keyword(value, &["thin", "medium", "thick"])
+}
+
+fn is_border_style(value: &CSSValue) -> bool {
+    is_keyword(
+        value,
+        &[
+            "none", "hidden", "dotted", "dashed", "solid", "double", "groove", "ridge", "inset",
+            "outset",
+        ],
+    )
+}
+
+fn is_color(value: &CSSValue) -> bool {
+    match value {
+        CSSValue::Color(_) => true,
+        CSSValue::Keyword(keyword) => {
+            keyword == "currentcolor" || keyword == "transparent" || named_color(keyword).is_some()
+        }
+        _ => false,
+    }
+}
+
+fn is_background_position(value: &CSSValue) -> bool {
+    is_length_percentage(value) || is_keyword(value, &["left", "center", "right", "top", "bottom"])
+}
+
+fn is_font_size(value: &CSSValue) -> bool {
+    is_length_percentage(value)
+        || is_keyword(
+            value,
+            &[
+                "xx-small",
+                "x-small",
+                "small",
+                "medium",
+                "large",
+                "x-large",
+                "xx-large",
+                "xxx-large",
+                "larger",
+                "smaller",
+            ],
+        )
+}
+
+fn is_font_weight(value: &CSSValue) -> bool {
+    matches!(value, CSSValue::Number(weight) if (1.0..=1000.0).contains(weight))
+        || is_keyword(value, &["bold", "bolder", "lighter"])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::parse_declarations;
+    use super::*;
+
+    // Longhands geradas pelas declarações, com seus valores
+    fn expanded(declarations: &str) -> Vec<(String, CSSValue)> {
+        parse_declarations(declarations.to_owned())
+            .value
+            .into_iter()
+            .map(|declaration| (declaration.name, declaration.value))
+            .collect()
+    }
+
+    fn values(declarations: &str) -> Vec<CSSValue> {
+        expanded(declarations)
+            .into_iter()
+            .map(|(_, value)| value)
+            .collect()
+    }
+
+    fn px(length: f32) -> CSSValue {
+        CSSValue::Length(length, super::super::CSSUnit::Px)
+    }
+
+    fn names(declarations: &str) -> Vec<String> {
+        expanded(declarations)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    #[test]
+    fn sides() {
+        assert_eq!(values("margin: 1px"), [px(1.0), px(1.0), px(1.0), px(1.0)]);
+        assert_eq!(
+            values("padding: 1px 2px 3px"),
+            [px(1.0), px(2.0), px(3.0), px(2.0)]
+        );
+        assert_eq!(
+            values("margin: 1px 2px 3px 4px"),
+            [px(1.0), px(2.0), px(3.0), px(4.0)]
+        );
+        assert_eq!(names("inset: auto"), ["top", "right", "bottom", "left"]);
+        assert_eq!(
+            values("border-style: solid dashed"),
+            [
+                keyword("solid"),
+                keyword("dashed"),
+                keyword("solid"),
+                keyword("dashed")
+            ]
+        );
+
+        // `auto` não é válido em `padding`, nem cinco valores em `margin`
+        assert!(values("padding: auto").is_empty());
+        assert!(values("margin: 1px 1px 1px 1px 1px").is_empty());
+    }
+
+    #[test]
+    fn borders() {
+        assert_eq!(
+            expanded("border-left: red 2px solid"),
+            [
+                ("border-left-width".to_owned(), px(2.0)),
+                ("border-left-style".to_owned(), keyword("solid")),
+                ("border-left-color".to_owned(), keyword("red")),
+            ]
+        );
+        assert_eq!(
+            values("border-top: dotted"),
+            [
+                keyword("medium"),
+                keyword("dotted"),
+                keyword("currentcolor")
+            ]
+        );
+
+        let border = expanded("border: 1px solid");
+        assert_eq!(border.len(), 12);
+        assert_eq!(border[3], ("border-left-width".to_owned(), px(1.0)));
+        assert_eq!(
+            border[8],
+            ("border-top-color".to_owned(), keyword("currentcolor"))
+        );
+
+        assert!(values("border: 1px 2px solid").is_empty());
+    }
+
+    #[test]
+    fn background_and_gap() {
+        assert_eq!(
+            values("background: no-repeat blue"),
+            [
+                keyword("blue"),
+                keyword("none"),
+                keyword("no-repeat"),
+                keyword("scroll"),
+                CSSValue::List(vec![CSSValue::Percentage(0.0), CSSValue::Percentage(0.0)]),
+            ]
+        );
+        assert_eq!(
+            values("background: left 10px red")[4],
+            CSSValue::List(vec![keyword("left"), px(10.0)])
+        );
+        assert!(values("background: left red 10px").is_empty());
+
+        assert_eq!(values("gap: 4px"), [px(4.0), px(4.0)]);
+        assert_eq!(
+            values("gap: normal 10%"),
+            [keyword("normal"), CSSValue::Percentage(10.0)]
+        );
+    }
+
+    #[test]
+    fn font() {
+        assert_eq!(
+            expanded("font: italic bold 12px/1.5 Arial, serif"),
+            [
+                ("font-style".to_owned(), keyword("italic")),
+                ("font-variant".to_owned(), keyword("normal")),
+                ("font-weight".to_owned(), keyword("bold")),
+                ("font-stretch".to_owned(), keyword("normal")),
+                ("font-size".to_owned(), px(12.0)),
+                ("line-height".to_owned(), CSSValue::Number(1.5)),
+                (
+                    "font-family".to_owned(),
+                    CSSValue::List(vec![
+                        keyword("arial"),
+                        CSSValue::Separator(','),
+                        keyword("serif")
+                    ])
+                ),
+            ]
+        );
+        assert_eq!(
+            values("font: 10px 'Times New Roman'")[6],
+            CSSValue::String("Times New Roman".to_owned())
+        );
+        assert!(values("font: bold serif").is_empty());
+    }
+
+    #[test]
+    fn font_family_rejects_css_wide_keywords_in_lists() {
+        for family in ["Arial, initial", "unset, serif", "Times revert"] {
+            let declaration = format!("font: 12px {family}");
+            let result = parse_declarations(declaration.clone());
+
+            assert!(result.value.is_empty(), "{declaration}");
+            assert_eq!(result.diagnostics[0].kind, "invalid-shorthand");
+        }
+
+        // Sozinha, a palavra-chave vale para todas as longhands
+        assert_eq!(values("font: inherit"), vec![keyword("inherit"); 7]);
+        // Entre aspas é apenas um nome
+        assert_eq!(
+            values("font: 12px 'inherit', serif")[6],
+            CSSValue::List(vec![
+                CSSValue::String("inherit".to_owned()),
+                CSSValue::Separator(','),
+                keyword("serif")
+            ])
+        );
+    }
+
+    #[test]
+    fn padding_rejects_negative_values() {
+        for declaration in ["padding: -1px", "padding: 1px -2%", "padding: 0 0 0 -3px"] {
+            let result = parse_declarations(declaration.to_owned());
+
+            assert!(result.value.is_empty(), "{declaration}");
+            assert_eq!(
+                result.diagnostics.first().map(|diagnostic| diagnostic.kind),
+                Some("invalid-shorthand"),
+                "{declaration}"
+            );
+        }
+
+        assert_eq!(
+            values("padding: 0 5%"),
+            [
+                CSSValue::Number(0.0),
+                CSSValue::Percentage(5.0),
+                CSSValue::Number(0.0),
+                CSSValue::Percentage(5.0)
+            ]
+        );
+        // Na margem, valores negativos continuam válidos
+        assert_eq!(values("margin: -1px")[0], px(-1.0));
+    }
+
+    #[test]
+    fn keywords_and_diagnostics() {
+        assert_eq!(values("margin: inherit"), vec![keyword("inherit"); 4]);
+        assert!(values("margin: inherit 1px").is_empty());
+        assert_eq!(
+            names("flex: none"),
+            ["flex-grow", "flex-shrink", "flex-basis"]
+        );
+
+        let result = parse_declarations("border: solid solid; margin: 1px !important".to_owned());
+        assert_eq!(result.diagnostics[0].kind, "invalid-shorthand");
+        assert_eq!(result.value.len(), 4);
+        assert!(result.value.iter().all(|declaration| declaration.important));
+    }
+
+    #[test]
+    fn unitless_zero() {
+        assert_eq!(
+            values("margin: 0 auto"),
+            [
+                CSSValue::Number(0.0),
+                keyword("auto"),
+                CSSValue::Number(0.0),
+                keyword("auto")
+            ]
+        );
+        assert_eq!(
+            values("border-width: 0 1px")[..2],
+            [CSSValue::Number(0.0), px(1.0)]
+        );
+        assert!(values("padding: 1").is_empty());
+
+        // No `flex`, o zero sem unidade é fator enquanto faltarem fatores
+        assert_eq!(
+            values("flex: 0"),
+            [
+                CSSValue::Number(0.0),
+                CSSValue::Number(1.0),
+                CSSValue::Percentage(0.0)
+            ]
+        );
+        assert_eq!(
+            values("flex: 2 0"),
+            [
+                CSSValue::Number(2.0),
+                CSSValue::Number(0.0),
+                CSSValue::Percentage(0.0)
+            ]
+        );
+        assert_eq!(
+            values("flex: 1 1 0"),
+            [
+                CSSValue::Number(1.0),
+                CSSValue::Number(1.0),
+                CSSValue::Number(0.0)
+            ]
+        );
+        assert_eq!(
+            values("flex: 0 10px"),
+            [CSSValue::Number(0.0), CSSValue::Number(1.0), px(10.0)]
+        );
+    }
+}
@@ -0,0 +1,649 @@
+use std::fmt;
+
+use crate::diagnostics::{Diagnostic, Span};
+use crate::scanner::Scanner;
+
+// Tokenizador CSS seguindo a CSS Syntax Module Level 3
+// (https://www.w3.org/TR/css-syntax-3/#tokenization)
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    Ident(String),
+    // Nome seguido de `(`, ex: `rgb(`
+    Function(String),
+    AtKeyword(String),
+    // `id` indica se o valor é um identificador válido, ou seja, se o
+    // token pode ser usado como seletor de id
+    Hash { value: String, id: bool },
+    String(String),
+    BadString,
+    Url(String),
+    BadUrl,
+    Delim(char),
+    Number(Number),
+    Percentage(Number),
+    Dimension(Number, String),
+    Whitespace,
+    Cdo,
+    Cdc,
+    Colon,
+    Semicolon,
+    Comma,
+    OpenSquare,
+    CloseSquare,
+    OpenParen,
+    CloseParen,
+    OpenCurly,
+    CloseCurly,
+    Eof,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Number {
+    pub value: f32,
+    // Falso se o número foi escrito com parte fracionária ou expoente
+    pub integer: bool,
+    // Se foi escrito com `+` ou `-`, o que distingue `2n+1` de `2n 1` em
+    // expressões `An+B`
+    pub signed: bool,
+}
+
+pub struct Tokenizer {
+    scanner: Scanner,
+    token_span: Span,
+    errors: Vec<Diagnostic>,
+}
+
+const REPLACEMENT_CHARACTER: char = '\u{FFFD}';
+
+const MAX_CODE_POINT: u32 = 0x10FFFF;
+
+impl Tokenizer {
+    pub fn new(input: String) -> Tokenizer {
+        Tokenizer {
+            scanner: Scanner::new(input),
+            token_span: Span::default(),
+            errors: Vec::new(),
+        }
+    }
+
+    // Trecho da entrada que originou o último token retornado
+    pub fn token_span(&self) -> Span {
+        self.token_span
+    }
+
+    pub fn errors(&self) -> &[Diagnostic] {
+        &self.errors
+    }
+
+    // Comentários são descartados. Ao fim da entrada retorna sempre
+    // `Token::Eof`.
+    pub fn next_token(&mut self) -> Token {
+        self.consume_comments();
+
+        // O início é calculado antes de qualquer erro do token, para que as
+        // consultas a `Scanner::location` sejam feitas em ordem crescente
+        let start = self.scanner.location(self.scanner.position());
+        let token = self.consume_token();
+
+        self.token_span = Span {
+            start,
+            end: self.scanner.location(self.scanner.position()),
+        };
+
+        token
+    }
+
+    fn consume_comments(&mut self) {
+        while self.scanner.starts_with("/*") {
+            let start = self.scanner.position();
+
+            match self.scanner.rest()[2..].find("*/") {
+                Some(index) => self.scanner.advance_by(index + 4),
+                None => {
+                    self.scanner.advance_by(self.scanner.rest().len());
+                    self.error(
+                        "eof-in-comment",
+                        "Fim do arquivo dentro de um comentário",
+                        start,
+                    );
+                }
+            }
+        }
+    }
+
+    fn consume_token(&mut self) -> Token {
+        let (first, second, third) = self.lookahead();
+
+        let c = match first {
+            Some(c) => c,
+            None => return Token::Eof,
+        };
+
+        if is_whitespace(c) {
+            self.scanner.consume_while(is_whitespace);
+            return Token::Whitespace;
+        }
+
+        if starts_number(first, second, third) {
+            return self.consume_numeric();
+        }
+
+        // Testado antes de identificadores, já que `--` também inicia um
+        if self.scanner.starts_with("-->") {
+            self.scanner.advance_by(3);
+            return Token::Cdc;
+        }
+
+        if starts_identifier(first, second, third) {
+            return self.consume_ident_like();
+        }
+
+        let start = self.scanner.position();
+        self.scanner.consume_char();
+
+        match c {
+            '"' | '\'' => self.consume_string(c),
+            '#' => {
+                let (first, second, third) = self.lookahead();
+
+                if first.is_some_and(is_name_char) || is_valid_escape(first, second) {
+                    let id = starts_identifier(first, second, third);
+
+                    Token::Hash {
+                        value: self.consume_name(),
+                        id,
+                    }
+                } else {
+                    Token::Delim('#')
+                }
+            }
+            '<' if self.scanner.starts_with("!--") => {
+                self.scanner.advance_by(3);
+                Token::Cdo
+            }
+            '@' => {
+                let (first, second, third) = self.lookahead();
+
+                if starts_identifier(first, second, third) {
+                    Token::AtKeyword(self.consume_name())
+                } else {
+                    Token::Delim('@')
+                }
+            }
+            '\\' => {
+                self.error(
+                    "invalid-escape",
+                    "Escape inválido: '\\' seguido de quebra de linha",
+                    start,
+                );
+                Token::Delim('\\')
+            }
+            '(' => Token::OpenParen,
+            ')' => Token::CloseParen,
+            '[' => Token::OpenSquare,
+            ']' => Token::CloseSquare,
+            '{' => Token::OpenCurly,
+            '}' => Token::CloseCurly,
+            ',' => Token::Comma,
+            ':' => Token::Colon,
+            ';' => Token::Semicolon,
+            c => Token::Delim(c),
+        }
+    }
+
+    fn consume_numeric(&mut self) -> Token {
+        let number = self.consume_number();
+        let (first, second, third) = self.lookahead();
+
+        if starts_identifier(first, second, third) {
+            Token::Dimension(number, self.consume_name())
+        } else if first == Some('%') {
+            self.scanner.consume_char();
+            Token::Percentage(number)
+        } else {
+            Token::Number(number)
+        }
+    }
+
+    fn consume_number(&mut self) -> Number {
+        let start = self.scanner.position();
+        let mut integer = true;
+        let signed = matches!(self.scanner.peek(), Some('+' | '-'));
+
+        if signed {
+            self.scanner.consume_char();
+        }
+
+        self.scanner.consume_while(|c| c.is_ascii_digit());
+
+        if let (Some('.'), Some(c), _) = self.lookahead() {
+            if c.is_ascii_digit() {
+                self.scanner.consume_char();
+                self.scanner.consume_while(|c| c.is_ascii_digit());
+                integer = false;
+            }
+        }
+
+        if let (Some('e' | 'E'), Some(c), next) = self.lookahead() {
+            let signed_exponent =
+                matches!(c, '+' | '-') && next.is_some_and(|c| c.is_ascii_digit());
+
+            if c.is_ascii_digit() || signed_exponent {
+                self.scanner.advance_by(if signed_exponent { 2 } else { 1 });
+                self.scanner.consume_while(|c| c.is_ascii_digit());
+                integer = false;
+            }
+        }
+
+        let value = self
+            .scanner
+            .slice(start, self.scanner.position())
+            .parse()
+            .unwrap_or_default();
+
+        Number {
+            value,
+            integer,
+            signed,
+        }
+    }
+
+    fn consume_ident_like(&mut self) -> Token {
+        let name = self.consume_name();
+
+        if self.scanner.peek() != Some('(') {
+            return Token::Ident(name);
+        }
+
+        self.scanner.consume_char();
+
+        if !name.eq_ignore_ascii_case("url") {
+            return Token::Function(name);
+        }
+
+        // Mantém um espaço antes de uma string, que vira argumento da função `url(`
+        while let (Some(first), Some(second), _) = self.lookahead() {
+            if !is_whitespace(first) || !is_whitespace(second) {
+                break;
+            }
+
+            self.scanner.consume_char();
+        }
+
+        match self.lookahead() {
+            (Some('"' | '\''), _, _) => Token::Function(name),
+            (Some(c), Some('"' | '\''), _) if is_whitespace(c) => Token::Function(name),
+            _ => self.consume_url(),
+        }
+    }
+
+    fn consume_string(&mut self, ending: char) -> Token {
+        let mut value = String::new();
+
+        loop {
+            let start = self.scanner.position();
+
+            match self.scanner.consume_char() {
+                Some(c) if c == ending => return Token::String(value),
+                None => {
+                    self.error(
+                        "eof-in-string",
+                        "Fim do arquivo dentro de uma string",
+                        start,
+                    );
+                    return Token::String(value);
+                }
+                Some(c) if is_newline(c) => {
+                    // A quebra de linha não faz parte da string
+                    self.scanner.set_position(start);
+                    self.error(
+                        "newline-in-string",
+                        "Quebra de linha dentro de uma string",
+                        start,
+                    );
+                    return Token::BadString;
+                }
+                Some('\\') => match self.scanner.peek() {
+                    None => {}
+                    Some(c) if is_newline(c) => self.consume_newline(),
+                    Some(_) => value.push(self.consume_escape()),
+                },
+                Some(c) => value.push(preprocess(c)),
+            }
+        }
+    }
+
+    fn consume_url(&mut self) -> Token {
+        let mut value = String::new();
+
+        self.scanner.consume_while(is_whitespace);
+
+        loop {
+            let start = self.scanner.position();
+
+            match self.scanner.consume_char() {
+                Some(')') => return Token::Url(value),
+                None => {
+                    self.error("eof-in-url", "Fim do arquivo dentro de uma URL", start);
+                    return Token::Url(value);
+                }
+                Some(c) if is_whitespace(c) => {
+                    self.scanner.consume_while(is_whitespace);
+
+                    match self.scanner.peek() {
+                        Some(')') => {
+                            self.scanner.consume_char();
+                            return Token::Url(value);
+                        }
+                        None => {
+                            self.error("eof-in-url", "Fim do arquivo dentro de uma URL", start);
+                            return Token::Url(value);
+                        }
+                        Some(_) => return self.consume_bad_url(start),
+                    }
+                }
+                Some('"' | '\'' | '(') => return self.consume_bad_url(start),
+                Some(c) if is_non_printable(c) => return self.consume_bad_url(start),
+                Some('\\') => {
+                    if is_valid_escape(Some('\\'), self.scanner.peek()) {
+                        value.push(self.consume_escape());
+                    } else {
+                        return self.consume_bad_url(start);
+                    }
+                }
+                Some(c) => value.push(c),
+            }
+        }
+    }
+
+    // Consome o restante de uma URL inválida até o `)` que a fecharia
+    fn consume_bad_url(&mut self, start: usize) -> Token {
+        loop {
+            match self.scanner.consume_char() {
+                None | Some(')') => break,
+                Some('\\') if is_valid_escape(Some('\\'), self.scanner.peek()) => {
+                    self.consume_escape();
+                }
+                Some(_) => {}
+            }
+        }
+
+        self.error("invalid-url", "URL inválida", start);
+
+        Token::BadUrl
+    }
+
+    fn consume_name(&mut self) -> String {
+        let mut name = String::new();
+
+        loop {
+            match self.lookahead() {
+                (Some(c), _, _) if is_name_char(c) => {
+                    self.scanner.consume_char();
+                    name.push(preprocess(c));
+                }
+                (first, second, _) if is_valid_escape(first, second) => {
+                    self.scanner.consume_char();
+                    name.push(self.consume_escape());
+                }
+                _ => return name,
+            }
+        }
+    }
+
+    // Consome o que segue um `\` válido
+    fn consume_escape(&mut self) -> char {
+        let start = self.scanner.position();
+
+        match self.scanner.peek() {
+            None => {
+                self.error("eof-in-escape", "Fim do arquivo dentro de um escape", start);
+                REPLACEMENT_CHARACTER
+            }
+            Some(c) if c.is_ascii_hexdigit() => {
+                let rest = self.scanner.rest();
+                let length = rest
+                    .find(|c: char| !c.is_ascii_hexdigit())
+                    .unwrap_or(rest.len())
+                    .min(6);
+                let code = u32::from_str_radix(&rest[..length], 16).unwrap_or_default();
+
+                self.scanner.advance_by(length);
+
+                match self.scanner.peek() {
+                    Some(c) if is_newline(c) => self.consume_newline(),
+                    Some(c) if is_whitespace(c) => {
+                        self.scanner.consume_char();
+                    }
+                    _ => {}
+                }
+
+                match code {
+                    0 => REPLACEMENT_CHARACTER,
+                    code if code > MAX_CODE_POINT => REPLACEMENT_CHARACTER,
+                    // Surrogates não são caracteres válidos
+                    code => char::from_u32(code).unwrap_or(REPLACEMENT_CHARACTER),
+                }
+            }
+            Some(c) => {
+                self.scanner.consume_char();
+                preprocess(c)
+            }
+        }
+    }
+
+    // `\r\n` conta como uma única quebra de linha
+    fn consume_newline(&mut self) {
+        if self.scanner.starts_with("\r\n") {
+            self.scanner.advance_by(2);
+        } else {
+            self.scanner.consume_char();
+        }
+    }
+
+    fn lookahead(&self) -> (Option<char>, Option<char>, Option<char>) {
+        let mut chars = self.scanner.rest().chars();
+
+        (chars.next(), chars.next(), chars.next())
+    }
+
+    fn error(&mut self, kind: &'static str, message: &str, start: usize) {
+        self.errors.push(Diagnostic {
+            kind,
+            message: message.to_owned(),
+            span: self.scanner.span(start, self.scanner.position()),
+        });
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Ident(name) => write!(f, "{}", name),
+            Token::Function(name) => write!(f, "{}(", name),
+            Token::AtKeyword(name) => write!(f, "@{}", name),
+            Token::Hash { value, .. } => write!(f, "#{}", value),
+            Token::String(value) => write!(f, "{:?}", value),
+            Token::BadString => write!(f, "string inválida"),
+            Token::Url(value) => write!(f, "url({})", value),
+            Token::BadUrl => write!(f, "url inválida"),
+            Token::Delim(c) => write!(f, "{}", c),
+            Token::Number(number) => write!(f, "{}", number.value),
+            Token::Percentage(number) => write!(f, "{}%", number.value),
+            Token::Dimension(number, unit) => write!(f, "{}{}", number.value, unit),
+            Token::Whitespace => write!(f, " "),
+            Token::Cdo => write!(f, "<!--"),
+            Token::Cdc => write!(f, "-->"),
+            Token::Colon => write!(f, ":"),
+            Token::Semicolon => write!(f, ";"),
+            Token::Comma => write!(f, ","),
+            Token::OpenSquare => write!(f, "["),
+            Token::CloseSquare => write!(f, "]"),
+            Token::OpenParen => write!(f, "("),
+            Token::CloseParen => write!(f, ")"),
+            Token::OpenCurly => write!(f, "{{"),
+            Token::CloseCurly => write!(f, "}}"),
+            Token::Eof => write!(f, "fim do arquivo"),
+        }
+    }
+}
+
+// A especificação converte `\r`, `\r\n` e `\x0C` em `\n` antes da
+// tokenização; aqui eles são tratados como quebras de linha diretamente,
+// para que os spans correspondam à entrada original
+fn is_newline(c: char) -> bool {
+    matches!(c, '\n' | '\r' | '\x0C')
+}
+
+fn is_whitespace(c: char) -> bool {
+    is_newline(c) || matches!(c, ' ' | '\t')
+}
+
+fn preprocess(c: char) -> char {
+    match c {
+        '\0' => REPLACEMENT_CHARACTER,
+        c => c,
+    }
+}
+
+fn is_name_start_char(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_' || !c.is_ascii() || c == '\0'
+}
+
+fn is_name_char(c: char) -> bool {
+    is_name_start_char(c) || c.is_ascii_digit() || c == '-'
+}
+
+fn is_non_printable(c: char) -> bool {
+    matches!(c, '\0'..='\x08' | '\x0B' | '\x0E'..='\x1F' | '\x7F')
+}
+
+fn is_valid_escape(first: Option<char>, second: Option<char>) -> bool {
+    first == Some('\\') && !second.is_some_and(is_newline)
+}
+
+fn starts_identifier(first: Option<char>, second: Option<char>, third: Option<char>) -> bool {
+    match first {
+        Some('-') => {
+            second.is_some_and(|c| is_name_start_char(c) || c == '-')
+                || is_valid_escape(second, third)
+        }
+        Some('\\') => is_valid_escape(first, second),
+        Some(c) => is_name_start_char(c),
+        None => false,
+    }
+}
+
+fn starts_number(first: Option<char>, second: Option<char>, third: Option<char>) -> bool {
+    let is_digit = |c: Option<char>| c.is_some_and(|c| c.is_ascii_digit());
+
+    match first {
+        Some('+' | '-') => is_digit(second) || (second == Some('.') && is_digit(third)),
+        Some('.') => is_digit(second),
+        c => is_digit(c),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(input: &str) -> Vec<Token> {
+        let mut tokenizer = Tokenizer::new(input.to_owned());
+        let mut tokens = Vec::new();
+
+        loop {
+            match tokenizer.next_token() {
+                Token::Eof => return tokens,
+                token => tokens.push(token),
+            }
+        }
+    }
+
+    fn number(value: f32, integer: bool, signed: bool) -> Number {
+        Number {
+            value,
+            integer,
+            signed,
+        }
+    }
+
+    #[test]
+    fn numbers_and_dimensions() {
+        assert_eq!(
+            tokens("12 +1.5 -3e2 50% 2px .5EM"),
+            vec![
+                Token::Number(number(12.0, true, false)),
+                Token::Whitespace,
+                Token::Number(number(1.5, false, true)),
+                Token::Whitespace,
+                Token::Number(number(-300.0, false, true)),
+                Token::Whitespace,
+                Token::Percentage(number(50.0, true, false)),
+                Token::Whitespace,
+                Token::Dimension(number(2.0, true, false), "px".to_owned()),
+                Token::Whitespace,
+                Token::Dimension(number(0.5, false, false), "EM".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn identifiers_hashes_and_functions() {
+        assert_eq!(
+            tokens("-foo #a1 #1a rgb( @media \\31 0"),
+            vec![
+                Token::Ident("-foo".to_owned()),
+                Token::Whitespace,
+                Token::Hash {
+                    value: "a1".to_owned(),
+                    id: true
+                },
+                Token::Whitespace,
+                Token::Hash {
+                    value: "1a".to_owned(),
+                    id: false
+                },
+                Token::Whitespace,
+                Token::Function("rgb".to_owned()),
+                Token::Whitespace,
+                Token::AtKeyword("media".to_owned()),
+                Token::Whitespace,
+                Token::Ident("10".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn strings_urls_and_comments() {
+        assert_eq!(
+            tokens("'a\\'b' /* c */ url( x.png ) \"d\ne"),
+            vec![
+                Token::String("a'b".to_owned()),
+                Token::Whitespace,
+                Token::Whitespace,
+                Token::Url("x.png".to_owned()),
+                Token::Whitespace,
+                Token::BadString,
+                Token::Whitespace,
+                Token::Ident("e".to_owned()),
+            ]
+        );
+        assert_eq!(tokens("url(a b)"), vec![Token::BadUrl]);
+    }
+
+    #[test]
+    fn errors_and_spans() {
+        let mut tokenizer = Tokenizer::new("a\n  \"x".to_owned());
+        tokenizer.next_token();
+        tokenizer.next_token();
+
+        assert_eq!(tokenizer.next_token(), Token::String("x".to_owned()));
+        assert_eq!(tokenizer.token_span().start.line, 2);
+        assert_eq!(tokenizer.token_span().start.column, 3);
+        assert_eq!(tokenizer.errors().len(), 1);
+        assert_eq!(tokenizer.next_token(), Token::Eof);
+        assert_eq!(tokenizer.next_token(), Token::Eof);
+    }
+}
@@ -0,0 +1,31 @@
+// Diagnósticos produzidos pelos parsers de HTML e CSS. Os parsers nunca
+// falham: entradas inválidas geram diagnósticos e são recuperadas.
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    // Offset em bytes na entrada
+    pub offset: usize,
+    // Linha e coluna começam em 1; a coluna conta caracteres, não bytes
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    // Código do erro, ex: "eof-in-tag" ou "unknown-unit"
+    pub kind: &'static str,
+    pub message: String,
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub struct ParseResult<T> {
+    pub value: T,
+    pub diagnostics: Vec<Diagnostic>,
+}
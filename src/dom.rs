@@ -1,68 +1,440 @@
-use std::collections::{HashMap, HashSet};
+mod serialize;
+mod tree;
+
+use std::collections::HashSet;
+
+pub use serialize::{escape_attribute, escape_text};
+pub use tree::{
+    Ancestors, Children, Descendants, ElementData, Mutation, MutationError, NodeData, NodeId, Tree,
+};
+
+use crate::diagnostics::Span;
 
 #[derive(Debug)]
 pub enum Node {
-    Text(String),
+    Document(Document),
+    DocumentType(DocumentType),
+    Comment(Comment),
+    Text(Text),
     Element(Element),
 }
 
+// Raiz da árvore: contém o doctype, os comentários de nível superior
+// e o elemento `html`
+#[derive(Debug, Default)]
+pub struct Document {
+    pub children: Vec<Node>,
+    pub quirks_mode: QuirksMode,
+    pub span: Span,
+}
+
+// Modo de renderização decidido a partir do doctype
+// (https://dom.spec.whatwg.org/#concept-document-mode)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum QuirksMode {
+    #[default]
+    NoQuirks,
+    LimitedQuirks,
+    Quirks,
+}
+
+#[derive(Debug, Default)]
+pub struct DocumentType {
+    pub name: String,
+    pub public_id: String,
+    pub system_id: String,
+    pub span: Span,
+}
+
+#[derive(Debug, Default)]
+pub struct Comment {
+    pub data: String,
+    pub span: Span,
+}
+
+#[derive(Debug, Default)]
+pub struct Text {
+    pub data: String,
+    // Trecho da entrada que originou o nó
+    pub span: Span,
+}
+
 #[derive(Debug)]
 pub struct Element {
     pub tag_name: String,
     pub attributes: AttrMap,
     pub children: Vec<Node>,
+    pub span: Span,
+}
+
+// Atributos na ordem em que aparecem no código-fonte. Nomes são comparados
+// sem diferenciar maiúsculas de minúsculas (ASCII), como no HTML.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AttrMap {
+    attributes: Vec<(String, String)>,
 }
 
-pub type AttrMap = HashMap<String, String>;
+impl AttrMap {
+    pub fn new() -> AttrMap {
+        AttrMap::default()
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.attributes
+            .iter()
+            .position(|(existing, _)| existing.eq_ignore_ascii_case(name))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.position(name)
+            .map(|index| self.attributes[index].1.as_str())
+    }
+
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.position(name).is_some()
+    }
+
+    // Adiciona um atributo como o parser faz: se já existir um com o mesmo
+    // nome, vale o primeiro e o novo é descartado. Retorna se foi adicionado.
+    pub fn add(&mut self, name: String, value: String) -> bool {
+        if self.contains_key(&name) {
+            return false;
+        }
+
+        self.attributes.push((name, value));
+        true
+    }
+
+    // Substitui o valor de um atributo existente mantendo sua posição, ou
+    // adiciona o atributo no fim. Retorna o valor anterior.
+    pub fn set(&mut self, name: String, value: String) -> Option<String> {
+        match self.position(&name) {
+            Some(index) => Some(std::mem::replace(&mut self.attributes[index].1, value)),
+            None => {
+                self.attributes.push((name, value));
+                None
+            }
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<String> {
+        let index = self.position(name)?;
+
+        Some(self.attributes.remove(index).1)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.attributes
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.attributes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.attributes.is_empty()
+    }
+}
+
+impl FromIterator<(String, String)> for AttrMap {
+    fn from_iter<I: IntoIterator<Item = (String, String)>>(iter: I) -> AttrMap {
+        let mut attributes = AttrMap::new();
+
+        for (name, value) in iter {
+            attributes.add(name, value);
+        }
+
+        attributes
+    }
+}
 
 impl Element {
+    pub fn is_void(&self) -> bool {
+        VOID_ELEMENTS.contains(&self.tag_name.as_str())
+    }
+
+    pub fn outer_html(&self) -> String {
+        let mut output = String::new();
+        serialize::element(self, &mut output);
+
+        output
+    }
+
+    pub fn inner_html(&self) -> String {
+        let mut output = String::new();
+        serialize::children(&self.children, Some(self), &mut output);
+
+        output
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        self.attributes.get("id")
+    }
+
     pub fn classes(&self) -> HashSet<&str> {
         self.attributes
             .get("class")
-            .map(|class| class.split(' ').collect())
-            .unwrap_or_else(|| HashSet::new())
+            .map(|class| class.split_ascii_whitespace().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn descendant_elements(&self) -> DescendantElements<'_> {
+        DescendantElements {
+            stack: vec![self.children.iter()],
+        }
+    }
+
+    // Primeiro descendente, em ordem de documento, com o `id` informado
+    pub fn get_element_by_id(&self, id: &str) -> Option<&Element> {
+        self.descendant_elements()
+            .find(|element| element.id() == Some(id))
+    }
+
+    // Descendentes que têm todas as classes da lista separada por espaços
+    pub fn get_elements_by_class_name(&self, class_names: &str) -> Vec<&Element> {
+        let class_names: Vec<&str> = class_names.split_ascii_whitespace().collect();
+
+        if class_names.is_empty() {
+            return Vec::new();
+        }
+
+        self.descendant_elements()
+            .filter(|element| {
+                let classes = element.classes();
+                class_names.iter().all(|name| classes.contains(name))
+            })
+            .collect()
     }
 }
 
-impl Node {
-    pub fn markup(&self) -> String {
-        get_node_markup(self, 0)
+// O drop recursivo padrão estouraria a pilha em árvores muito profundas,
+// então os descendentes são desmontados com uma pilha explícita
+impl Drop for Element {
+    fn drop(&mut self) {
+        let mut pending = std::mem::take(&mut self.children);
+
+        while let Some(mut node) = pending.pop() {
+            if let Node::Element(element) = &mut node {
+                pending.append(&mut element.children);
+            }
+        }
     }
 }
 
-fn get_node_markup(node: &Node, current_depth: i32) -> String {
-    let ident = "    ".repeat(current_depth.try_into().unwrap());
+// Elementos descendentes em ordem de documento
+pub struct DescendantElements<'a> {
+    stack: Vec<std::slice::Iter<'a, Node>>,
+}
 
-    match node {
-        Node::Text(value) => ident + value,
-        Node::Element(Element {
-            tag_name,
-            attributes,
-            children,
-        }) => {
-            let attributes_markup = attributes
-                .iter()
-                .map(|(name, value)| format!("{}=\"{}\"", name, value))
-                .collect::<Vec<String>>()
-                .join(" ");
-            let attributes_markup = if attributes_markup.is_empty() {
-                "".to_owned()
-            } else {
-                " ".to_owned() + &attributes_markup
+impl<'a> Iterator for DescendantElements<'a> {
+    type Item = &'a Element;
+
+    fn next(&mut self) -> Option<&'a Element> {
+        loop {
+            let node = match self.stack.last_mut()?.next() {
+                Some(node) => node,
+                None => {
+                    self.stack.pop();
+                    continue;
+                }
             };
 
-            let content_markup = children
-                .iter()
-                .map(|node| get_node_markup(node, current_depth + 1))
-                .collect::<Vec<String>>()
-                .join("\n");
-
-            format!(
-                "{ident}<{name}{attrs}>\n{content}\n{ident}</{name}>",
-                name = tag_name,
-                attrs = attributes_markup,
-                content = content_markup,
-            )
+            self.stack.push(node.children().iter());
+
+            if let Node::Element(element) = node {
+                return Some(element);
+            }
+        }
+    }
+}
+
+impl Document {
+    // Elemento raiz (normalmente `html`)
+    pub fn document_element(&self) -> Option<&Element> {
+        self.children.iter().find_map(|node| match node {
+            Node::Element(element) => Some(element),
+            _ => None,
+        })
+    }
+}
+
+impl Node {
+    pub fn span(&self) -> Span {
+        match self {
+            Node::Document(document) => document.span,
+            Node::DocumentType(doctype) => doctype.span,
+            Node::Comment(comment) => comment.span,
+            Node::Text(text) => text.span,
+            Node::Element(element) => element.span,
+        }
+    }
+
+    pub fn descendant_elements(&self) -> DescendantElements<'_> {
+        DescendantElements {
+            stack: vec![self.children().iter()],
+        }
+    }
+
+    pub fn children(&self) -> &[Node] {
+        match self {
+            Node::Document(document) => &document.children,
+            Node::Element(element) => &element.children,
+            Node::DocumentType(_) | Node::Comment(_) | Node::Text(_) => &[],
+        }
+    }
+
+    // Serialização do nó com suas tags, como `outerHTML`. Para o documento,
+    // equivale a `inner_html`.
+    pub fn outer_html(&self) -> String {
+        let mut output = String::new();
+
+        match self {
+            Node::Document(document) => serialize::children(&document.children, None, &mut output),
+            node => serialize::node(node, None, &mut output),
         }
+
+        output
+    }
+
+    // Serialização apenas dos filhos, como `innerHTML`
+    pub fn inner_html(&self) -> String {
+        let mut output = String::new();
+
+        match self {
+            Node::Element(element) => {
+                serialize::children(&element.children, Some(element), &mut output)
+            }
+            node => serialize::children(node.children(), None, &mut output),
+        }
+
+        output
+    }
+}
+
+// Elementos que nunca têm conteúdo nem tag de fechamento
+pub const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "basefont", "bgsound", "br", "col", "embed", "frame", "hr", "img", "input",
+    "keygen", "link", "meta", "param", "source", "track", "wbr",
+];
+
+// Elementos cujo texto não é interpretado como marcação nem serializado
+// com escapes
+pub const RAW_TEXT_ELEMENTS: &[&str] = &[
+    "style",
+    "script",
+    "xmp",
+    "iframe",
+    "noembed",
+    "noframes",
+    "plaintext",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html::parse;
+
+    // Primeiro elemento com o nome informado, em ordem de documento
+    fn find<'a>(document: &'a Node, tag_name: &str) -> &'a Element {
+        document
+            .descendant_elements()
+            .find(|element| element.tag_name == tag_name)
+            .unwrap()
+    }
+
+    #[test]
+    fn duplicate_attributes_keep_the_first() {
+        let document = parse("<div id=a ID=b class=x id=c></div>".to_owned());
+        let div = find(&document, "div");
+
+        assert_eq!(div.attributes.get("id"), Some("a"));
+        assert_eq!(
+            div.attributes.iter().collect::<Vec<_>>(),
+            [("id", "a"), ("class", "x")]
+        );
+
+        let mut attributes: AttrMap = [("id", "a"), ("id", "b")]
+            .into_iter()
+            .map(|(name, value)| (name.to_owned(), value.to_owned()))
+            .collect();
+
+        assert_eq!(attributes.get("id"), Some("a"));
+        assert!(!attributes.add("Id".to_owned(), "c".to_owned()));
+        assert_eq!(attributes.len(), 1);
+    }
+
+    #[test]
+    fn attribute_names_ignore_ascii_case() {
+        let document = parse("<div id=a data-Value=1></div>".to_owned());
+        let attributes = &find(&document, "div").attributes;
+
+        assert_eq!(attributes.get("ID"), Some("a"));
+        assert_eq!(attributes.get("Id"), Some("a"));
+        assert_eq!(attributes.get("DATA-value"), Some("1"));
+        assert!(attributes.contains_key("ID"));
+
+        let mut attributes = attributes.clone();
+
+        // `set` mantém a posição e o nome original
+        assert_eq!(
+            attributes.set("ID".to_owned(), "b".to_owned()),
+            Some("a".to_owned())
+        );
+        assert_eq!(
+            attributes.iter().collect::<Vec<_>>(),
+            [("id", "b"), ("data-value", "1")]
+        );
+        assert_eq!(attributes.remove("DATA-VALUE"), Some("1".to_owned()));
+        assert_eq!(attributes.remove("data-value"), None);
+        assert_eq!(attributes.len(), 1);
+    }
+
+    #[test]
+    fn get_element_by_id() {
+        let document = parse(
+            "<div id=x><p id=dup>1</p></div><p id=dup>2</p><span ID=upper></span>".to_owned(),
+        );
+        let html = find(&document, "html");
+
+        // Com ids repetidos vale o primeiro em ordem de documento
+        let first = html.get_element_by_id("dup").unwrap();
+        assert_eq!(first.inner_html(), "1");
+
+        assert_eq!(html.get_element_by_id("x").unwrap().tag_name, "div");
+        assert!(html.get_element_by_id("ausente").is_none());
+        // O valor do id diferencia maiúsculas de minúsculas
+        assert!(html.get_element_by_id("X").is_none());
+        assert!(html.get_element_by_id("upper").is_some());
+        assert!(html.get_element_by_id("").is_none());
+
+        // A busca não inclui o próprio elemento
+        let div = find(&document, "div");
+        assert!(div.get_element_by_id("x").is_none());
+    }
+
+    #[test]
+    fn get_elements_by_class_name() {
+        let document = parse(
+            "<div id=1 class=\"a\tb\"><p id=2 class=\"b\na\"></p></div>\
+             <p id=3 class=\" a \x0C b c \"></p><p id=4 class=a></p><p id=5 class=ab></p>"
+                .to_owned(),
+        );
+        let html = find(&document, "html");
+        let ids = |class_names: &str| -> Vec<&str> {
+            html.get_elements_by_class_name(class_names)
+                .iter()
+                .filter_map(|element| element.id())
+                .collect()
+        };
+
+        // Qualquer espaço em branco ASCII separa as classes, e os resultados
+        // vêm em ordem de documento
+        assert_eq!(ids("b a"), ["1", "2", "3"]);
+        assert_eq!(ids("\ta\n"), ["1", "2", "3", "4"]);
+        assert_eq!(ids("c"), ["3"]);
+        assert!(ids(" \t").is_empty());
+        assert!(ids("A").is_empty());
     }
 }
@@ -0,0 +1,195 @@
+use super::{Element, Node, RAW_TEXT_ELEMENTS};
+
+// Serialização de fragmentos HTML
+// (https://html.spec.whatwg.org/multipage/parsing.html#serialising-html-fragments)
+
+// Trabalho pendente da serialização. Uma pilha explícita substitui a
+// recursão, para que árvores profundas não estourem a pilha de chamadas.
+enum Step<'a> {
+    Children(&'a [Node], Option<&'a Element>),
+    Node(&'a Node, Option<&'a Element>),
+    Element(&'a Element),
+    EndTag(&'a Element),
+}
+
+pub(super) fn children(children: &[Node], parent: Option<&Element>, output: &mut String) {
+    serialize(Step::Children(children, parent), output);
+}
+
+pub(super) fn node(node: &Node, parent: Option<&Element>, output: &mut String) {
+    serialize(Step::Node(node, parent), output);
+}
+
+pub(super) fn element(element: &Element, output: &mut String) {
+    serialize(Step::Element(element), output);
+}
+
+fn serialize(step: Step, output: &mut String) {
+    let mut stack = vec![step];
+
+    while let Some(step) = stack.pop() {
+        match step {
+            Step::Children(children, parent) => {
+                // O parser descarta a primeira quebra de linha de `pre`,
+                // `textarea` e `listing`, então ela é duplicada para que o
+                // texto sobreviva a um novo parsing
+                if let (Some(parent), Some(Node::Text(text))) = (parent, children.first()) {
+                    if matches!(parent.tag_name.as_str(), "pre" | "textarea" | "listing")
+                        && text.data.starts_with('\n')
+                    {
+                        output.push('\n');
+                    }
+                }
+
+                stack.extend(children.iter().rev().map(|child| Step::Node(child, parent)));
+            }
+            Step::Node(node, parent) => match node {
+                Node::Document(document) => stack.push(Step::Children(&document.children, None)),
+                Node::DocumentType(doctype) => {
+                    output.push_str("<!DOCTYPE ");
+                    output.push_str(&doctype.name);
+                    output.push('>');
+                }
+                Node::Comment(comment) => {
+                    output.push_str("<!--");
+                    output.push_str(&comment.data);
+                    output.push_str("-->");
+                }
+                Node::Text(text) => {
+                    let raw_text = parent.is_some_and(|parent| {
+                        RAW_TEXT_ELEMENTS.contains(&parent.tag_name.as_str())
+                    });
+
+                    if raw_text {
+                        output.push_str(&text.data);
+                    } else {
+                        output.push_str(&escape_text(&text.data));
+                    }
+                }
+                Node::Element(element) => stack.push(Step::Element(element)),
+            },
+            Step::Element(element) => {
+                output.push('<');
+                output.push_str(&element.tag_name);
+
+                for (name, value) in element.attributes.iter() {
+                    output.push(' ');
+                    output.push_str(name);
+                    output.push_str("=\"");
+                    output.push_str(&escape_attribute(value));
+                    output.push('"');
+                }
+
+                output.push('>');
+
+                if !element.is_void() {
+                    stack.push(Step::EndTag(element));
+                    stack.push(Step::Children(&element.children, Some(element)));
+                }
+            }
+            Step::EndTag(element) => {
+                output.push_str("</");
+                output.push_str(&element.tag_name);
+                output.push('>');
+            }
+        }
+    }
+}
+
+pub fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '\u{A0}' => escaped.push_str("&nbsp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+pub fn escape_attribute(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '\u{A0}' => escaped.push_str("&nbsp;"),
+            '"' => escaped.push_str("&quot;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::html::parse;
+
+    // Serializar e parsear de novo deve reproduzir a mesma árvore
+    fn assert_round_trip(input: &str) {
+        let first = parse(input.to_owned()).outer_html();
+        let second = parse(first.clone()).outer_html();
+
+        assert_eq!(first, second, "entrada: {input:?}");
+    }
+
+    #[test]
+    fn round_trip() {
+        for input in [
+            "<!DOCTYPE html><p class=\"a\" id=b>x &amp; y<!-- c --></p>",
+            "<p title='\"&amp;\"'>a&nbsp;b</p>",
+            "<ul><li>1<li>2</ul><br><img src=x>",
+        ] {
+            assert_round_trip(input);
+        }
+    }
+
+    #[test]
+    fn round_trip_foreign_content() {
+        for input in [
+            "<svg viewBox='0 0 1 1'><g><circle r=1 /></g></svg>",
+            "<svg><foreignObject><p>a</p></foreignObject></svg>",
+            "<svg><g></p>x",
+            "<math><mi>x</mi><annotation-xml encoding=text/html><div>y</div></annotation-xml></math>",
+            "<svg><![CDATA[a<b]]></svg>",
+        ] {
+            assert_round_trip(input);
+        }
+    }
+
+    #[test]
+    fn round_trip_tables() {
+        for input in [
+            "<table><td>x</table>",
+            "<table>a<tr><td>b</td></tr>c</table>",
+            "<table><caption>c</caption><colgroup><col></colgroup><thead><tr><th>h</table>",
+            "<template><td>x</td></template>",
+        ] {
+            assert_round_trip(input);
+        }
+    }
+
+    #[test]
+    fn round_trip_raw_text() {
+        for input in [
+            "<script>if (a < b && c) {}</script>",
+            "<style>p > a { content: '&amp;' }</style>",
+            "<textarea>\n\nx</textarea><pre>\ny</pre>",
+            "<title>a &lt; b</title>",
+            "<noscript><p>x</p></noscript>",
+        ] {
+            assert_round_trip(input);
+        }
+
+        let html = parse("<script>a < b</script>".to_owned()).outer_html();
+        assert!(html.contains("<script>a < b</script>"));
+    }
+}
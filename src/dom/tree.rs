@@ -0,0 +1,779 @@
+use super::{AttrMap, Comment, Document, DocumentType, Element, Node, QuirksMode, Text};
+use crate::diagnostics::Span;
+
+// DOM armazenado numa arena. Os nós são referenciados por `NodeId` e guardam
+// ligações para o pai e os irmãos, permitindo percorrer a árvore em qualquer
+// direção sem empréstimos aninhados. Alterações feitas pela API de mutação
+// ficam registradas em `changes`.
+
+// Identificador estável de um nó: continua válido enquanto a árvore existir
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeId(usize);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NodeData {
+    Document {
+        quirks_mode: QuirksMode,
+    },
+    DocumentType {
+        name: String,
+        public_id: String,
+        system_id: String,
+    },
+    Comment(String),
+    Text(String),
+    Element(ElementData),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ElementData {
+    pub tag_name: String,
+    pub attributes: AttrMap,
+}
+
+#[derive(Debug)]
+struct TreeNode {
+    data: NodeData,
+    span: Span,
+    parent: Option<NodeId>,
+    first_child: Option<NodeId>,
+    last_child: Option<NodeId>,
+    previous_sibling: Option<NodeId>,
+    next_sibling: Option<NodeId>,
+}
+
+// Alterações feitas pela API de mutação, na ordem em que aconteceram, para
+// que estilo e layout possam ser recalculados apenas onde for preciso
+#[derive(Debug, Clone, PartialEq)]
+pub enum Mutation {
+    Inserted { node: NodeId, parent: NodeId },
+    Removed { node: NodeId, parent: NodeId },
+    AttributeChanged { node: NodeId, name: String },
+    TextChanged { node: NodeId },
+}
+
+// Erros das operações de mutação, equivalentes às exceções do DOM
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MutationError {
+    // A inserção criaria um ciclo ou o pai não pode ter esse filho
+    HierarchyRequest,
+    // O nó de referência não é filho do pai informado
+    NotFound,
+    // A operação não se aplica a esse tipo de nó
+    InvalidNodeType,
+}
+
+#[derive(Debug)]
+pub struct Tree {
+    nodes: Vec<TreeNode>,
+    changes: Vec<Mutation>,
+}
+
+impl Tree {
+    // Converte uma árvore de `Node`; `node` passa a ser a raiz
+    pub fn from_node(node: &Node) -> Tree {
+        let mut tree = Tree {
+            nodes: Vec::new(),
+            changes: Vec::new(),
+        };
+        tree.insert_node(None, node);
+
+        tree
+    }
+
+    pub fn root(&self) -> NodeId {
+        NodeId(0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn data(&self, id: NodeId) -> &NodeData {
+        &self.nodes[id.0].data
+    }
+
+    pub fn span(&self, id: NodeId) -> Span {
+        self.nodes[id.0].span
+    }
+
+    pub fn element(&self, id: NodeId) -> Option<&ElementData> {
+        match self.data(id) {
+            NodeData::Element(element) => Some(element),
+            _ => None,
+        }
+    }
+
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id.0].parent
+    }
+
+    pub fn first_child(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id.0].first_child
+    }
+
+    pub fn last_child(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id.0].last_child
+    }
+
+    pub fn previous_sibling(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id.0].previous_sibling
+    }
+
+    pub fn next_sibling(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id.0].next_sibling
+    }
+
+    pub fn children(&self, id: NodeId) -> Children<'_> {
+        Children {
+            tree: self,
+            next: self.first_child(id),
+        }
+    }
+
+    // Do pai até a raiz, sem incluir o próprio nó
+    pub fn ancestors(&self, id: NodeId) -> Ancestors<'_> {
+        Ancestors {
+            tree: self,
+            next: self.parent(id),
+        }
+    }
+
+    // Descendentes em ordem de documento, sem incluir o próprio nó
+    pub fn descendants(&self, id: NodeId) -> Descendants<'_> {
+        Descendants {
+            tree: self,
+            root: id,
+            next: self.first_child(id),
+        }
+    }
+
+    // Converte a subárvore de `id` de volta para `Node`
+    pub fn to_node(&self, id: NodeId) -> Node {
+        let span = self.span(id);
+        let children = || self.children(id).map(|child| self.to_node(child)).collect();
+
+        match self.data(id) {
+            &NodeData::Document { quirks_mode } => Node::Document(Document {
+                children: children(),
+                quirks_mode,
+                span,
+            }),
+            NodeData::DocumentType {
+                name,
+                public_id,
+                system_id,
+            } => Node::DocumentType(DocumentType {
+                name: name.clone(),
+                public_id: public_id.clone(),
+                system_id: system_id.clone(),
+                span,
+            }),
+            NodeData::Comment(data) => Node::Comment(Comment {
+                data: data.clone(),
+                span,
+            }),
+            NodeData::Text(data) => Node::Text(Text {
+                data: data.clone(),
+                span,
+            }),
+            NodeData::Element(element) => Node::Element(Element {
+                tag_name: element.tag_name.clone(),
+                attributes: element.attributes.clone(),
+                children: children(),
+                span,
+            }),
+        }
+    }
+
+    // Mutação
+
+    // Os nós criados ficam soltos até serem inseridos na árvore
+    pub fn create_element(&mut self, tag_name: &str) -> NodeId {
+        self.create_node(
+            NodeData::Element(ElementData {
+                tag_name: tag_name.to_ascii_lowercase(),
+                attributes: AttrMap::new(),
+            }),
+            Span::default(),
+        )
+    }
+
+    pub fn create_text(&mut self, data: &str) -> NodeId {
+        self.create_node(NodeData::Text(data.to_owned()), Span::default())
+    }
+
+    pub fn create_comment(&mut self, data: &str) -> NodeId {
+        self.create_node(NodeData::Comment(data.to_owned()), Span::default())
+    }
+
+    pub fn append_child(&mut self, parent: NodeId, child: NodeId) -> Result<(), MutationError> {
+        self.insert_before(parent, child, None)
+    }
+
+    // Insere `child` antes de `reference`, ou no fim se `reference` for `None`.
+    // Se `child` já estiver na árvore, ele é movido.
+    pub fn insert_before(
+        &mut self,
+        parent: NodeId,
+        child: NodeId,
+        reference: Option<NodeId>,
+    ) -> Result<(), MutationError> {
+        if !self.can_have_children(parent)
+            || matches!(self.data(child), NodeData::Document { .. })
+            || child == parent
+            || self.ancestors(parent).any(|ancestor| ancestor == child)
+        {
+            return Err(MutationError::HierarchyRequest);
+        }
+
+        if reference.is_some_and(|reference| self.parent(reference) != Some(parent)) {
+            return Err(MutationError::NotFound);
+        }
+
+        // Inserir um nó antes dele mesmo não muda nada
+        if reference == Some(child) {
+            return Ok(());
+        }
+
+        if let Some(old_parent) = self.parent(child) {
+            self.detach(child);
+            self.changes.push(Mutation::Removed {
+                node: child,
+                parent: old_parent,
+            });
+        }
+
+        self.attach(parent, child, reference);
+        self.changes.push(Mutation::Inserted {
+            node: child,
+            parent,
+        });
+
+        Ok(())
+    }
+
+    // O nó removido continua válido e pode ser inserido novamente
+    pub fn remove_child(&mut self, parent: NodeId, child: NodeId) -> Result<(), MutationError> {
+        if self.parent(child) != Some(parent) {
+            return Err(MutationError::NotFound);
+        }
+
+        self.detach(child);
+        self.changes.push(Mutation::Removed {
+            node: child,
+            parent,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_attribute(
+        &mut self,
+        id: NodeId,
+        name: &str,
+        value: &str,
+    ) -> Result<(), MutationError> {
+        let element = self.element_mut(id)?;
+        let name = name.to_ascii_lowercase();
+
+        if element.attributes.get(&name) == Some(value) {
+            return Ok(());
+        }
+
+        element.attributes.set(name.clone(), value.to_owned());
+        self.changes
+            .push(Mutation::AttributeChanged { node: id, name });
+
+        Ok(())
+    }
+
+    pub fn remove_attribute(&mut self, id: NodeId, name: &str) -> Result<(), MutationError> {
+        let element = self.element_mut(id)?;
+        let name = name.to_ascii_lowercase();
+
+        if element.attributes.remove(&name).is_some() {
+            self.changes
+                .push(Mutation::AttributeChanged { node: id, name });
+        }
+
+        Ok(())
+    }
+
+    // Em textos e comentários, troca o conteúdo. Em elementos e documentos,
+    // substitui todos os filhos por um único texto, como `textContent`.
+    pub fn set_text(&mut self, id: NodeId, text: &str) -> Result<(), MutationError> {
+        match self.nodes[id.0].data {
+            NodeData::Text(ref mut data) | NodeData::Comment(ref mut data) => {
+                if data != text {
+                    *data = text.to_owned();
+                    self.changes.push(Mutation::TextChanged { node: id });
+                }
+            }
+            NodeData::DocumentType { .. } => return Err(MutationError::InvalidNodeType),
+            NodeData::Document { .. } | NodeData::Element(_) => {
+                while let Some(child) = self.first_child(id) {
+                    self.remove_child(id, child)?;
+                }
+
+                if !text.is_empty() {
+                    let child = self.create_text(text);
+                    self.append_child(id, child)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn changes(&self) -> &[Mutation] {
+        &self.changes
+    }
+
+    // Retorna e limpa o registro de alterações, ex: a cada frame
+    pub fn take_changes(&mut self) -> Vec<Mutation> {
+        std::mem::take(&mut self.changes)
+    }
+
+    fn element_mut(&mut self, id: NodeId) -> Result<&mut ElementData, MutationError> {
+        match self.nodes[id.0].data {
+            NodeData::Element(ref mut element) => Ok(element),
+            _ => Err(MutationError::InvalidNodeType),
+        }
+    }
+
+    fn can_have_children(&self, id: NodeId) -> bool {
+        matches!(
+            self.data(id),
+            NodeData::Document { .. } | NodeData::Element(_)
+        )
+    }
+
+    fn create_node(&mut self, data: NodeData, span: Span) -> NodeId {
+        let id = NodeId(self.nodes.len());
+
+        self.nodes.push(TreeNode {
+            data,
+            span,
+            parent: None,
+            first_child: None,
+            last_child: None,
+            previous_sibling: None,
+            next_sibling: None,
+        });
+
+        id
+    }
+
+    fn insert_node(&mut self, parent: Option<NodeId>, node: &Node) -> NodeId {
+        let data = match node {
+            Node::Document(document) => NodeData::Document {
+                quirks_mode: document.quirks_mode,
+            },
+            Node::DocumentType(doctype) => NodeData::DocumentType {
+                name: doctype.name.clone(),
+                public_id: doctype.public_id.clone(),
+                system_id: doctype.system_id.clone(),
+            },
+            Node::Comment(comment) => NodeData::Comment(comment.data.clone()),
+            Node::Text(text) => NodeData::Text(text.data.clone()),
+            Node::Element(element) => NodeData::Element(ElementData {
+                tag_name: element.tag_name.clone(),
+                attributes: element.attributes.clone(),
+            }),
+        };
+
+        let id = self.create_node(data, node.span());
+
+        if let Some(parent) = parent {
+            self.attach(parent, id, None);
+        }
+
+        for child in node.children() {
+            self.insert_node(Some(id), child);
+        }
+
+        id
+    }
+
+    // Liga um nó solto como filho de `parent`, antes de `reference`
+    fn attach(&mut self, parent: NodeId, child: NodeId, reference: Option<NodeId>) {
+        let previous = match reference {
+            Some(reference) => self.nodes[reference.0].previous_sibling.replace(child),
+            None => self.nodes[parent.0].last_child.replace(child),
+        };
+
+        match previous {
+            Some(previous) => self.nodes[previous.0].next_sibling = Some(child),
+            None => self.nodes[parent.0].first_child = Some(child),
+        }
+
+        let node = &mut self.nodes[child.0];
+        node.parent = Some(parent);
+        node.previous_sibling = previous;
+        node.next_sibling = reference;
+    }
+
+    fn detach(&mut self, id: NodeId) {
+        let node = &mut self.nodes[id.0];
+        let parent = node.parent.take();
+        let previous = node.previous_sibling.take();
+        let next = node.next_sibling.take();
+
+        let Some(parent) = parent else {
+            return;
+        };
+
+        match previous {
+            Some(previous) => self.nodes[previous.0].next_sibling = next,
+            None => self.nodes[parent.0].first_child = next,
+        }
+
+        match next {
+            Some(next) => self.nodes[next.0].previous_sibling = previous,
+            None => self.nodes[parent.0].last_child = previous,
+        }
+    }
+}
+
+pub struct Children<'a> {
+    tree: &'a Tree,
+    next: Option<NodeId>,
+}
+
+impl Iterator for Children<'_> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let current = self.next?;
+        self.next = self.tree.next_sibling(current);
+
+        Some(current)
+    }
+}
+
+pub struct Ancestors<'a> {
+    tree: &'a Tree,
+    next: Option<NodeId>,
+}
+
+impl Iterator for Ancestors<'_> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let current = self.next?;
+        self.next = self.tree.parent(current);
+
+        Some(current)
+    }
+}
+
+pub struct Descendants<'a> {
+    tree: &'a Tree,
+    root: NodeId,
+    next: Option<NodeId>,
+}
+
+impl Iterator for Descendants<'_> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let current = self.next?;
+
+        // Próximo nó em pré-ordem: o primeiro filho ou, subindo até a raiz,
+        // o próximo irmão do nó ou de um de seus ancestrais
+        self.next = self.tree.first_child(current).or_else(|| {
+            let mut node = current;
+
+            loop {
+                if node == self.root {
+                    return None;
+                }
+
+                if let Some(sibling) = self.tree.next_sibling(node) {
+                    return Some(sibling);
+                }
+
+                node = self.tree.parent(node)?;
+            }
+        });
+
+        Some(current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html::parse;
+
+    fn tree(html: &str) -> Tree {
+        Tree::from_node(&parse(html.to_owned()))
+    }
+
+    // Primeiro elemento com o nome informado, em ordem de documento
+    fn find(tree: &Tree, tag_name: &str) -> NodeId {
+        tree.descendants(tree.root())
+            .find(|&id| {
+                tree.element(id)
+                    .is_some_and(|element| element.tag_name == tag_name)
+            })
+            .unwrap()
+    }
+
+    fn names(tree: &Tree, ids: impl Iterator<Item = NodeId>) -> Vec<String> {
+        ids.map(|id| match tree.data(id) {
+            NodeData::Document { .. } => "#document".to_owned(),
+            NodeData::DocumentType { .. } => "#doctype".to_owned(),
+            NodeData::Comment(_) => "#comment".to_owned(),
+            NodeData::Text(data) => format!("#text {data}"),
+            NodeData::Element(element) => element.tag_name.clone(),
+        })
+        .collect()
+    }
+
+    #[test]
+    fn from_node() {
+        let html = "<!DOCTYPE html><!-- c --><p class=a>x<br>y</p>";
+        let node = parse(html.to_owned());
+        let tree = Tree::from_node(&node);
+
+        assert_eq!(tree.len(), 1 + node.descendant_elements().count() + 4);
+        assert_eq!(
+            tree.data(tree.root()),
+            &NodeData::Document {
+                quirks_mode: QuirksMode::NoQuirks
+            }
+        );
+        assert_eq!(
+            names(&tree, tree.descendants(tree.root())),
+            ["#doctype", "#comment", "html", "head", "body", "p", "#text x", "br", "#text y"]
+        );
+
+        let p = find(&tree, "p");
+        assert_eq!(tree.element(p).unwrap().attributes.get("class"), Some("a"));
+        assert_eq!(
+            tree.span(p),
+            node.descendant_elements()
+                .find(|e| e.tag_name == "p")
+                .unwrap()
+                .span
+        );
+        assert_eq!(tree.to_node(tree.root()).outer_html(), node.outer_html());
+    }
+
+    #[test]
+    fn navigation_at_the_root() {
+        let tree = tree("<!DOCTYPE html><p>x");
+        let root = tree.root();
+        let html = find(&tree, "html");
+
+        assert_eq!(tree.parent(root), None);
+        assert_eq!(tree.previous_sibling(root), None);
+        assert_eq!(tree.next_sibling(root), None);
+        assert_eq!(tree.ancestors(root).count(), 0);
+
+        assert_eq!(
+            names(&tree, tree.first_child(root).into_iter()),
+            ["#doctype"]
+        );
+        assert_eq!(tree.last_child(root), Some(html));
+        assert_eq!(tree.parent(html), Some(root));
+        assert_eq!(tree.previous_sibling(html), tree.first_child(root));
+        assert_eq!(tree.next_sibling(html), None);
+    }
+
+    #[test]
+    fn navigation_between_siblings() {
+        let tree = tree("<ul><li>a</li><li>b</li><li>c</li></ul>");
+        let ul = find(&tree, "ul");
+        let items: Vec<NodeId> = tree.children(ul).collect();
+
+        assert_eq!(names(&tree, items.iter().copied()), ["li", "li", "li"]);
+        assert_eq!(tree.first_child(ul), Some(items[0]));
+        assert_eq!(tree.last_child(ul), Some(items[2]));
+
+        // Para frente e para trás
+        assert_eq!(tree.previous_sibling(items[0]), None);
+        assert_eq!(tree.next_sibling(items[0]), Some(items[1]));
+        assert_eq!(tree.previous_sibling(items[1]), Some(items[0]));
+        assert_eq!(tree.next_sibling(items[1]), Some(items[2]));
+        assert_eq!(tree.previous_sibling(items[2]), Some(items[1]));
+        assert_eq!(tree.next_sibling(items[2]), None);
+
+        for &item in &items {
+            assert_eq!(tree.parent(item), Some(ul));
+        }
+    }
+
+    #[test]
+    fn navigation_at_the_leaves() {
+        let tree = tree("<div><p>a<br></p></div>");
+        let p = find(&tree, "p");
+        let text = tree.first_child(p).unwrap();
+        let br = tree.last_child(p).unwrap();
+
+        for leaf in [text, br] {
+            assert_eq!(tree.first_child(leaf), None);
+            assert_eq!(tree.last_child(leaf), None);
+            assert_eq!(tree.children(leaf).count(), 0);
+            assert_eq!(tree.descendants(leaf).count(), 0);
+        }
+
+        assert_eq!(tree.next_sibling(text), Some(br));
+        assert_eq!(tree.previous_sibling(br), Some(text));
+        assert_eq!(
+            names(&tree, tree.ancestors(br)),
+            ["p", "div", "body", "html", "#document"]
+        );
+
+        // `descendants` não sai da subárvore
+        assert_eq!(
+            names(&tree, tree.descendants(find(&tree, "div"))),
+            ["p", "#text a", "br"]
+        );
+    }
+
+    #[test]
+    fn insert_before_requires_a_child_reference() {
+        let mut tree = tree("<div><p></p></div><span></span>");
+        let div = find(&tree, "div");
+        let span = find(&tree, "span");
+        let child = tree.create_element("b");
+
+        assert_eq!(
+            tree.insert_before(div, child, Some(span)),
+            Err(MutationError::NotFound)
+        );
+        assert_eq!(tree.parent(child), None);
+        assert!(tree.changes().is_empty());
+
+        let p = find(&tree, "p");
+        assert_eq!(tree.insert_before(div, child, Some(p)), Ok(()));
+        assert_eq!(tree.first_child(div), Some(child));
+        assert_eq!(tree.next_sibling(child), Some(p));
+    }
+
+    #[test]
+    fn insert_before_rejects_cycles() {
+        let mut tree = tree("<div><p></p></div>");
+        let div = find(&tree, "div");
+        let p = find(&tree, "p");
+        let text = tree.create_text("x");
+
+        assert_eq!(
+            tree.append_child(p, div),
+            Err(MutationError::HierarchyRequest)
+        );
+        assert_eq!(
+            tree.append_child(div, div),
+            Err(MutationError::HierarchyRequest)
+        );
+        assert_eq!(
+            tree.append_child(text, p),
+            Err(MutationError::HierarchyRequest)
+        );
+        assert!(tree.changes().is_empty());
+    }
+
+    #[test]
+    fn remove_child_requires_a_child() {
+        let mut tree = tree("<div><p><b></b></p></div>");
+        let div = find(&tree, "div");
+        let b = find(&tree, "b");
+        let detached = tree.create_text("x");
+
+        // Descendentes que não são filhos diretos também são recusados
+        assert_eq!(tree.remove_child(div, b), Err(MutationError::NotFound));
+        assert_eq!(
+            tree.remove_child(div, detached),
+            Err(MutationError::NotFound)
+        );
+        assert!(tree.changes().is_empty());
+
+        let p = find(&tree, "p");
+        assert_eq!(tree.remove_child(div, p), Ok(()));
+        assert_eq!(tree.parent(p), None);
+        assert_eq!(tree.first_child(div), None);
+        // O nó removido mantém a sua subárvore
+        assert_eq!(tree.first_child(p), Some(b));
+    }
+
+    #[test]
+    fn set_text_on_an_element_replaces_its_children() {
+        let mut tree = tree("<p>a<b>b</b>c</p>");
+        let p = find(&tree, "p");
+        let old_children: Vec<NodeId> = tree.children(p).collect();
+
+        assert_eq!(tree.set_text(p, "novo"), Ok(()));
+        assert_eq!(names(&tree, tree.children(p)), ["#text novo"]);
+
+        let text = tree.first_child(p).unwrap();
+        let mut expected: Vec<Mutation> = old_children
+            .iter()
+            .map(|&node| Mutation::Removed { node, parent: p })
+            .collect();
+        expected.push(Mutation::Inserted {
+            node: text,
+            parent: p,
+        });
+        assert_eq!(tree.changes(), expected);
+
+        // Um texto vazio deixa o elemento sem filhos
+        assert_eq!(tree.set_text(p, ""), Ok(()));
+        assert_eq!(tree.first_child(p), None);
+
+        let mut tree = self::tree("<!DOCTYPE html>");
+        let doctype = tree.first_child(tree.root()).unwrap();
+
+        assert_eq!(
+            tree.set_text(doctype, "x"),
+            Err(MutationError::InvalidNodeType)
+        );
+    }
+
+    #[test]
+    fn take_changes_returns_records_in_order() {
+        let mut tree = tree("<div><p>a</p></div><span></span>");
+        let div = find(&tree, "div");
+        let p = find(&tree, "p");
+        let span = find(&tree, "span");
+        let text = tree.first_child(p).unwrap();
+
+        tree.set_attribute(div, "ID", "x").unwrap();
+        // Atribuir o mesmo valor não gera registro
+        tree.set_attribute(div, "id", "x").unwrap();
+        tree.append_child(span, p).unwrap();
+        tree.set_text(text, "b").unwrap();
+        tree.remove_attribute(div, "id").unwrap();
+
+        assert_eq!(
+            tree.take_changes(),
+            [
+                Mutation::AttributeChanged {
+                    node: div,
+                    name: "id".to_owned()
+                },
+                Mutation::Removed {
+                    node: p,
+                    parent: div
+                },
+                Mutation::Inserted {
+                    node: p,
+                    parent: span
+                },
+                Mutation::TextChanged { node: text },
+                Mutation::AttributeChanged {
+                    node: div,
+                    name: "id".to_owned()
+                },
+            ]
+        );
+        assert!(tree.changes().is_empty());
+        assert!(tree.take_changes().is_empty());
+    }
+}
@@ -0,0 +1,582 @@
+// Detecção da codificação de caracteres e decodificação incremental da entrada
+// (https://html.spec.whatwg.org/multipage/parsing.html#determining-the-character-encoding)
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Windows1252,
+}
+
+// Quantidade de bytes examinada em busca de um `<meta charset>`
+pub(super) const PRESCAN_LENGTH: usize = 1024;
+
+// Caracteres do windows-1252 no intervalo 0x80..=0x9F. Também são usados
+// para substituir referências numéricas de caracteres nesse intervalo.
+pub(super) const WINDOWS_1252_C1: [(u32, char); 27] = [
+    (0x80, '\u{20AC}'),
+    (0x82, '\u{201A}'),
+    (0x83, '\u{0192}'),
+    (0x84, '\u{201E}'),
+    (0x85, '\u{2026}'),
+    (0x86, '\u{2020}'),
+    (0x87, '\u{2021}'),
+    (0x88, '\u{02C6}'),
+    (0x89, '\u{2030}'),
+    (0x8A, '\u{0160}'),
+    (0x8B, '\u{2039}'),
+    (0x8C, '\u{0152}'),
+    (0x8E, '\u{017D}'),
+    (0x91, '\u{2018}'),
+    (0x92, '\u{2019}'),
+    (0x93, '\u{201C}'),
+    (0x94, '\u{201D}'),
+    (0x95, '\u{2022}'),
+    (0x96, '\u{2013}'),
+    (0x97, '\u{2014}'),
+    (0x98, '\u{02DC}'),
+    (0x99, '\u{2122}'),
+    (0x9A, '\u{0161}'),
+    (0x9B, '\u{203A}'),
+    (0x9C, '\u{0153}'),
+    (0x9E, '\u{017E}'),
+    (0x9F, '\u{0178}'),
+];
+
+const REPLACEMENT_CHARACTER: char = '\u{FFFD}';
+
+fn is_whitespace(byte: u8) -> bool {
+    matches!(byte, b'\t' | b'\n' | b'\x0C' | b'\r' | b' ')
+}
+
+impl Encoding {
+    // Rótulos do Encoding Standard para as codificações suportadas
+    // (https://encoding.spec.whatwg.org/#names-and-labels)
+    pub fn for_label(label: &str) -> Option<Encoding> {
+        let label = label
+            .trim_matches(|c: char| c.is_ascii() && is_whitespace(c as u8))
+            .to_ascii_lowercase();
+
+        match label.as_str() {
+            "unicode-1-1-utf-8" | "unicode11utf8" | "unicode20utf8" | "utf-8" | "utf8"
+            | "x-unicode20utf8" => Some(Encoding::Utf8),
+            "csunicode" | "iso-10646-ucs-2" | "ucs-2" | "unicode" | "unicodefeff" | "utf-16"
+            | "utf-16le" => Some(Encoding::Utf16Le),
+            "unicodefffe" | "utf-16be" => Some(Encoding::Utf16Be),
+            "ansi_x3.4-1968" | "ascii" | "cp1252" | "cp819" | "csisolatin1" | "ibm819"
+            | "iso-8859-1" | "iso-ir-100" | "iso8859-1" | "iso88591" | "iso_8859-1"
+            | "iso_8859-1:1987" | "l1" | "latin1" | "us-ascii" | "windows-1252" | "x-cp1252" => {
+                Some(Encoding::Windows1252)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf16Le => "UTF-16LE",
+            Encoding::Utf16Be => "UTF-16BE",
+            Encoding::Windows1252 => "windows-1252",
+        }
+    }
+}
+
+// Retorna a codificação indicada por um BOM e o tamanho do BOM
+pub(super) fn sniff_bom(bytes: &[u8]) -> Option<(Encoding, usize)> {
+    if bytes.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        Some((Encoding::Utf8, 3))
+    } else if bytes.starts_with(&[0xFE, 0xFF]) {
+        Some((Encoding::Utf16Be, 2))
+    } else if bytes.starts_with(&[0xFF, 0xFE]) {
+        Some((Encoding::Utf16Le, 2))
+    } else {
+        None
+    }
+}
+
+// Sem nenhuma indicação, a entrada é tratada como UTF-8 se for válida
+// e como windows-1252 caso contrário
+pub(super) fn guess(bytes: &[u8]) -> Encoding {
+    match std::str::from_utf8(bytes) {
+        Ok(_) => Encoding::Utf8,
+        // Sequência incompleta no fim do trecho examinado
+        Err(error) if error.error_len().is_none() => Encoding::Utf8,
+        Err(_) => Encoding::Windows1252,
+    }
+}
+
+// Procura por `<meta charset>` ou `<meta http-equiv="content-type">` no
+// início do documento, sem construir a árvore
+// (https://html.spec.whatwg.org/multipage/parsing.html#prescan-a-byte-stream-to-determine-its-encoding)
+pub(super) fn prescan(bytes: &[u8]) -> Option<Encoding> {
+    let mut prescanner = Prescanner { bytes, position: 0 };
+
+    prescanner.run()
+}
+
+struct Prescanner<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Prescanner<'_> {
+    fn run(&mut self) -> Option<Encoding> {
+        while self.position < self.bytes.len() {
+            if self.starts_with(b"<!--") {
+                // Os traços de `<!--` também podem fechar o comentário
+                match find(&self.bytes[self.position + 2..], b"-->") {
+                    Some(index) => self.position += 2 + index + 2,
+                    None => return None,
+                }
+            } else if self.starts_with_ignore_ascii_case(b"<meta")
+                && self
+                    .byte_at(5)
+                    .is_some_and(|byte| is_whitespace(byte) || byte == b'/')
+            {
+                self.position += 5;
+
+                if let Some(encoding) = self.meta() {
+                    return Some(encoding);
+                }
+            } else if self.starts_with(b"<") && self.tag_name_follows() {
+                self.position += 1;
+
+                while self
+                    .peek()
+                    .is_some_and(|byte| !is_whitespace(byte) && byte != b'>')
+                {
+                    self.position += 1;
+                }
+
+                while self.attribute().is_some() {}
+            } else if self.starts_with(b"<!") || self.starts_with(b"</") || self.starts_with(b"<?")
+            {
+                match self.bytes[self.position..]
+                    .iter()
+                    .position(|&byte| byte == b'>')
+                {
+                    Some(index) => self.position += index,
+                    None => return None,
+                }
+            }
+
+            self.position += 1;
+        }
+
+        None
+    }
+
+    // `<` ou `</` seguido de uma letra
+    fn tag_name_follows(&self) -> bool {
+        let offset = if self.byte_at(1) == Some(b'/') { 2 } else { 1 };
+
+        self.byte_at(offset)
+            .is_some_and(|byte| byte.is_ascii_alphabetic())
+    }
+
+    fn meta(&mut self) -> Option<Encoding> {
+        let mut names = Vec::new();
+        let mut got_pragma = false;
+        let mut need_pragma = None;
+        let mut charset = None;
+
+        while let Some((name, value)) = self.attribute() {
+            if names.contains(&name) {
+                continue;
+            }
+
+            match name.as_str() {
+                "http-equiv" if value == "content-type" => got_pragma = true,
+                "content" if charset.is_none() => {
+                    if let Some(encoding) =
+                        charset_from_content(&value).and_then(|label| Encoding::for_label(&label))
+                    {
+                        charset = Some(encoding);
+                        need_pragma = Some(true);
+                    }
+                }
+                "charset" => {
+                    charset = Encoding::for_label(&value);
+                    need_pragma = Some(false);
+                }
+                _ => {}
+            }
+
+            names.push(name);
+        }
+
+        match need_pragma {
+            None => None,
+            Some(true) if !got_pragma => None,
+            // Um documento que declara UTF-16 num `<meta>` já foi lido como
+            // ASCII, então só pode ser UTF-8
+            _ => charset.map(|encoding| match encoding {
+                Encoding::Utf16Le | Encoding::Utf16Be => Encoding::Utf8,
+                encoding => encoding,
+            }),
+        }
+    }
+
+    // Lê o próximo atributo, com nome e valor em minúsculas
+    fn attribute(&mut self) -> Option<(String, String)> {
+        while self
+            .peek()
+            .is_some_and(|byte| is_whitespace(byte) || byte == b'/')
+        {
+            self.position += 1;
+        }
+
+        let mut name = Vec::new();
+        let mut value = Vec::new();
+
+        loop {
+            match self.peek()? {
+                b'>' if name.is_empty() => return None,
+                b'=' if !name.is_empty() => {
+                    self.position += 1;
+                    break;
+                }
+                byte if is_whitespace(byte) => {
+                    self.skip_whitespace();
+
+                    if self.peek() != Some(b'=') {
+                        return Some((to_string(name), String::new()));
+                    }
+
+                    self.position += 1;
+                    break;
+                }
+                b'/' | b'>' => return Some((to_string(name), String::new())),
+                byte => {
+                    name.push(byte.to_ascii_lowercase());
+                    self.position += 1;
+                }
+            }
+        }
+
+        self.skip_whitespace();
+
+        match self.peek()? {
+            quote @ (b'"' | b'\'') => {
+                self.position += 1;
+
+                loop {
+                    let byte = self.peek()?;
+                    self.position += 1;
+
+                    if byte == quote {
+                        return Some((to_string(name), to_string(value)));
+                    }
+
+                    value.push(byte.to_ascii_lowercase());
+                }
+            }
+            b'>' => Some((to_string(name), String::new())),
+            _ => {
+                while let Some(byte) = self.peek() {
+                    if is_whitespace(byte) || byte == b'>' {
+                        break;
+                    }
+
+                    value.push(byte.to_ascii_lowercase());
+                    self.position += 1;
+                }
+
+                Some((to_string(name), to_string(value)))
+            }
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(is_whitespace) {
+            self.position += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.byte_at(0)
+    }
+
+    fn byte_at(&self, offset: usize) -> Option<u8> {
+        self.bytes.get(self.position + offset).copied()
+    }
+
+    fn starts_with(&self, prefix: &[u8]) -> bool {
+        self.bytes[self.position..].starts_with(prefix)
+    }
+
+    fn starts_with_ignore_ascii_case(&self, prefix: &[u8]) -> bool {
+        let rest = &self.bytes[self.position..];
+
+        rest.len() >= prefix.len() && rest[..prefix.len()].eq_ignore_ascii_case(prefix)
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn to_string(bytes: Vec<u8>) -> String {
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+// Extrai o `charset=...` do atributo `content` de um `<meta http-equiv>`
+// (https://html.spec.whatwg.org/multipage/urls-and-fetching.html#algorithm-for-extracting-a-character-encoding-from-a-meta-element)
+fn charset_from_content(content: &str) -> Option<String> {
+    let bytes = content.as_bytes();
+    let mut position = 0;
+
+    loop {
+        position += find(&bytes[position..], b"charset")? + "charset".len();
+
+        while bytes.get(position).copied().is_some_and(is_whitespace) {
+            position += 1;
+        }
+
+        if bytes.get(position) == Some(&b'=') {
+            position += 1;
+            break;
+        }
+    }
+
+    while bytes.get(position).copied().is_some_and(is_whitespace) {
+        position += 1;
+    }
+
+    let rest = &content[position..];
+
+    match rest.as_bytes().first()? {
+        &quote @ (b'"' | b'\'') => {
+            let end = rest[1..].find(quote as char)?;
+
+            Some(rest[1..end + 1].to_owned())
+        }
+        _ => {
+            let end = rest
+                .find(|c: char| c == ';' || (c.is_ascii() && is_whitespace(c as u8)))
+                .unwrap_or(rest.len());
+
+            Some(rest[..end].to_owned())
+        }
+    }
+}
+
+// Decodifica a entrada em trechos. Sequências de bytes incompletas no fim
+// de um trecho são guardadas até a chegada do próximo.
+#[derive(Debug)]
+pub(super) struct Decoder {
+    encoding: Encoding,
+    pending: Vec<u8>,
+}
+
+impl Decoder {
+    pub fn new(encoding: Encoding) -> Decoder {
+        Decoder {
+            encoding,
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    // `last` indica que não haverá mais entrada, então bytes pendentes
+    // viram U+FFFD
+    pub fn decode(&mut self, bytes: &[u8], last: bool) -> String {
+        let mut input = std::mem::take(&mut self.pending);
+        input.extend_from_slice(bytes);
+
+        let mut output = String::with_capacity(input.len());
+
+        let consumed = match self.encoding {
+            Encoding::Utf8 => decode_utf8(&input, last, &mut output),
+            Encoding::Utf16Le => decode_utf16(&input, last, u16::from_le_bytes, &mut output),
+            Encoding::Utf16Be => decode_utf16(&input, last, u16::from_be_bytes, &mut output),
+            Encoding::Windows1252 => {
+                output.extend(input.iter().map(|&byte| decode_windows_1252(byte)));
+                input.len()
+            }
+        };
+
+        self.pending = input[consumed..].to_vec();
+
+        output
+    }
+}
+
+// Retorna quantos bytes foram consumidos
+fn decode_utf8(mut input: &[u8], last: bool, output: &mut String) -> usize {
+    let length = input.len();
+
+    loop {
+        match std::str::from_utf8(input) {
+            Ok(valid) => {
+                output.push_str(valid);
+                return length;
+            }
+            Err(error) => {
+                let (valid, rest) = input.split_at(error.valid_up_to());
+                // O prefixo é válido, então nada é substituído aqui
+                output.push_str(&String::from_utf8_lossy(valid));
+
+                match error.error_len() {
+                    Some(invalid) => {
+                        output.push(REPLACEMENT_CHARACTER);
+                        input = &rest[invalid..];
+                    }
+                    None if last => {
+                        output.push(REPLACEMENT_CHARACTER);
+                        return length;
+                    }
+                    None => return length - rest.len(),
+                }
+            }
+        }
+    }
+}
+
+fn decode_utf16(input: &[u8], last: bool, unit: fn([u8; 2]) -> u16, output: &mut String) -> usize {
+    let units: Vec<u16> = input
+        .chunks_exact(2)
+        .map(|pair| unit([pair[0], pair[1]]))
+        .collect();
+    let mut index = 0;
+
+    while index < units.len() {
+        let current = units[index];
+
+        match current {
+            0xD800..=0xDBFF => match units.get(index + 1) {
+                Some(&low @ 0xDC00..=0xDFFF) => {
+                    let code = 0x10000 + ((current as u32 - 0xD800) << 10) + (low as u32 - 0xDC00);
+                    output.push(char::from_u32(code).unwrap_or(REPLACEMENT_CHARACTER));
+                    index += 2;
+                    continue;
+                }
+                // O surrogate baixo pode estar no próximo trecho
+                None if !last => return index * 2,
+                _ => output.push(REPLACEMENT_CHARACTER),
+            },
+            0xDC00..=0xDFFF => output.push(REPLACEMENT_CHARACTER),
+            _ => output.push(char::from_u32(current as u32).unwrap_or(REPLACEMENT_CHARACTER)),
+        }
+
+        index += 1;
+    }
+
+    if input.len() % 2 == 1 && last {
+        output.push(REPLACEMENT_CHARACTER);
+        return input.len();
+    }
+
+    units.len() * 2
+}
+
+fn decode_windows_1252(byte: u8) -> char {
+    WINDOWS_1252_C1
+        .iter()
+        .find(|&&(from, _)| from == byte as u32)
+        .map_or(byte as char, |&(_, to)| to)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bom_sniffing() {
+        assert_eq!(sniff_bom(b"\xEF\xBB\xBF<p>"), Some((Encoding::Utf8, 3)));
+        assert_eq!(sniff_bom(b"\xFF\xFE<\0p\0"), Some((Encoding::Utf16Le, 2)));
+        assert_eq!(sniff_bom(b"\xFE\xFF\0<\0p"), Some((Encoding::Utf16Be, 2)));
+        assert_eq!(sniff_bom(b"\xEF\xBB<p>"), None);
+        assert_eq!(sniff_bom(b"<p>"), None);
+    }
+
+    #[test]
+    fn prescan_meta_charset() {
+        assert_eq!(
+            prescan(b"<!DOCTYPE html><meta charset=\"windows-1252\">"),
+            Some(Encoding::Windows1252)
+        );
+        assert_eq!(
+            prescan(b"<META CHARSET='Latin1'><meta charset=utf-8>"),
+            Some(Encoding::Windows1252)
+        );
+        // Comentários e atributos de outras tags não contam
+        assert_eq!(
+            prescan(b"<!-- <meta charset=latin1> --><p title='<meta charset=latin1>'><meta charset=utf-8>"),
+            Some(Encoding::Utf8)
+        );
+        // UTF-16 num `<meta>` vira UTF-8
+        assert_eq!(prescan(b"<meta charset=utf-16>"), Some(Encoding::Utf8));
+        assert_eq!(prescan(b"<meta charset=klingon>"), None);
+        assert_eq!(prescan(b"<p>sem meta</p>"), None);
+    }
+
+    #[test]
+    fn prescan_http_equiv() {
+        assert_eq!(
+            prescan(
+                b"<meta http-equiv=\"Content-Type\" content=\"text/html; charset=ISO-8859-1\">"
+            ),
+            Some(Encoding::Windows1252)
+        );
+        assert_eq!(
+            prescan(b"<meta content='text/html; charset=\"utf-8\"' http-equiv=content-type>"),
+            Some(Encoding::Utf8)
+        );
+        // Sem `http-equiv`, o `content` é ignorado
+        assert_eq!(
+            prescan(b"<meta content=\"text/html; charset=iso-8859-1\">"),
+            None
+        );
+    }
+
+    #[test]
+    fn guess_falls_back_to_windows_1252() {
+        assert_eq!(guess("café".as_bytes()), Encoding::Utf8);
+        assert_eq!(guess(b"caf\xE9!"), Encoding::Windows1252);
+        // Sequência cortada no fim do trecho examinado
+        assert_eq!(guess(&"café".as_bytes()[..4]), Encoding::Utf8);
+    }
+
+    #[test]
+    fn decoder_keeps_incomplete_sequences() {
+        let text = "aé€😀";
+
+        for (encoding, bytes) in [
+            (Encoding::Utf8, text.as_bytes().to_vec()),
+            (
+                Encoding::Utf16Le,
+                text.encode_utf16().flat_map(u16::to_le_bytes).collect(),
+            ),
+            (
+                Encoding::Utf16Be,
+                text.encode_utf16().flat_map(u16::to_be_bytes).collect(),
+            ),
+        ] {
+            for split in 0..=bytes.len() {
+                let mut decoder = Decoder::new(encoding);
+                let mut decoded = decoder.decode(&bytes[..split], false);
+                decoded.push_str(&decoder.decode(&bytes[split..], true));
+
+                assert_eq!(decoded, text, "{encoding:?} dividido em {split}");
+            }
+        }
+    }
+
+    #[test]
+    fn decoder_replaces_invalid_bytes() {
+        let mut decoder = Decoder::new(Encoding::Utf8);
+        assert_eq!(decoder.decode(b"a\xFFb\xC3", false), "a\u{FFFD}b");
+        assert_eq!(decoder.decode(b"", true), "\u{FFFD}");
+
+        let mut decoder = Decoder::new(Encoding::Windows1252);
+        assert_eq!(decoder.decode(b"caf\xE9 \x80", true), "café €");
+    }
+}
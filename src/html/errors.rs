@@ -0,0 +1,104 @@
+// Descrições dos códigos de erro de parsing do HTML
+// (https://html.spec.whatwg.org/multipage/parsing.html#parse-errors)
+pub fn message(kind: &str) -> &'static str {
+    match kind {
+        "abrupt-closing-of-empty-comment" => "Comentário vazio fechado abruptamente",
+        "abrupt-doctype-public-identifier" => {
+            "Identificador público do DOCTYPE fechado abruptamente"
+        }
+        "abrupt-doctype-system-identifier" => {
+            "Identificador de sistema do DOCTYPE fechado abruptamente"
+        }
+        "absence-of-digits-in-numeric-character-reference" => {
+            "Referência numérica de caractere sem dígitos"
+        }
+        "bad-doctype" => "DOCTYPE não é o DOCTYPE do HTML",
+        "cdata-in-html-content" => "Seção CDATA fora de conteúdo SVG ou MathML",
+        "character-reference-outside-unicode-range" => {
+            "Referência de caractere fora do intervalo do Unicode"
+        }
+        "control-character-reference" => "Referência a um caractere de controle",
+        "duplicate-attribute" => "Atributo duplicado",
+        "end-tag-with-attributes" => "Tag de fechamento com atributos",
+        "end-tag-with-trailing-solidus" => "Tag de fechamento terminada com '/'",
+        "eof-before-tag-name" => "Fim do arquivo antes do nome da tag",
+        "eof-in-cdata" => "Fim do arquivo dentro de uma seção CDATA",
+        "eof-in-comment" => "Fim do arquivo dentro de um comentário",
+        "eof-in-doctype" => "Fim do arquivo dentro de um DOCTYPE",
+        "eof-in-script-html-comment-like-text" => {
+            "Fim do arquivo dentro de um comentário em um script"
+        }
+        "eof-in-tag" => "Fim do arquivo dentro de uma tag",
+        "eof-in-template" => "Fim do arquivo dentro de um template",
+        "eof-in-text" => "Fim do arquivo antes do fechamento de um elemento de texto",
+        "incorrectly-closed-comment" => "Comentário fechado com '--!>'",
+        "incorrectly-opened-comment" => "Comentário aberto incorretamente",
+        "invalid-character-sequence-after-doctype-name" => {
+            "Sequência inválida após o nome do DOCTYPE"
+        }
+        "invalid-first-character-of-tag-name" => "Caractere inválido no início do nome da tag",
+        "misnested-formatting-element" => "Elemento de formatação mal aninhado",
+        "missing-attribute-value" => "Valor de atributo ausente",
+        "missing-doctype" => "DOCTYPE ausente",
+        "missing-doctype-name" => "Nome do DOCTYPE ausente",
+        "missing-doctype-public-identifier" => "Identificador público do DOCTYPE ausente",
+        "missing-doctype-system-identifier" => "Identificador de sistema do DOCTYPE ausente",
+        "missing-end-tag-name" => "Nome da tag de fechamento ausente",
+        "missing-quote-before-doctype-public-identifier" => {
+            "Aspas ausentes antes do identificador público do DOCTYPE"
+        }
+        "missing-quote-before-doctype-system-identifier" => {
+            "Aspas ausentes antes do identificador de sistema do DOCTYPE"
+        }
+        "missing-semicolon-after-character-reference" => {
+            "Ponto e vírgula ausente após referência de caractere"
+        }
+        "missing-whitespace-after-doctype-public-keyword" => {
+            "Espaço ausente após PUBLIC no DOCTYPE"
+        }
+        "missing-whitespace-after-doctype-system-keyword" => {
+            "Espaço ausente após SYSTEM no DOCTYPE"
+        }
+        "missing-whitespace-before-doctype-name" => "Espaço ausente antes do nome do DOCTYPE",
+        "missing-whitespace-between-attributes" => "Espaço ausente entre atributos",
+        "missing-whitespace-between-doctype-public-and-system-identifiers" => {
+            "Espaço ausente entre os identificadores do DOCTYPE"
+        }
+        "nested-comment" => "Comentário aninhado",
+        "non-void-html-element-start-tag-with-trailing-solidus" => {
+            "Tag de abertura de elemento não-vazio terminada com '/'"
+        }
+        "noncharacter-character-reference" => "Referência a um não-caractere do Unicode",
+        "null-character-reference" => "Referência ao caractere nulo",
+        "surrogate-character-reference" => "Referência a um surrogate do UTF-16",
+        "unexpected-character-after-doctype-system-identifier" => {
+            "Caractere inesperado após o identificador de sistema do DOCTYPE"
+        }
+        "unexpected-character-in-attribute-name" => "Caractere inesperado no nome do atributo",
+        "unexpected-character-in-table" => "Texto inesperado dentro de uma tabela",
+        "unexpected-character-in-unquoted-attribute-value" => {
+            "Caractere inesperado em valor de atributo sem aspas"
+        }
+        "unexpected-doctype" => "DOCTYPE inesperado",
+        "unexpected-end-tag" => "Tag de fechamento inesperada",
+        "unexpected-equals-sign-before-attribute-name" => {
+            "'=' inesperado antes do nome do atributo"
+        }
+        "unexpected-html-element-in-foreign-content" => {
+            "Elemento HTML inesperado em conteúdo SVG ou MathML"
+        }
+        "unexpected-null-character" => "Caractere nulo inesperado",
+        "unexpected-question-mark-instead-of-tag-name" => "'?' inesperado no lugar do nome da tag",
+        "unexpected-solidus-in-tag" => "'/' inesperado dentro da tag",
+        "unexpected-start-tag" => "Tag de abertura inesperada",
+        "unexpected-token-after-body" => "Conteúdo inesperado após o body",
+        "unexpected-token-after-frameset" => "Conteúdo inesperado após o frameset",
+        "unexpected-token-in-column-group" => "Conteúdo inesperado dentro de um colgroup",
+        "unexpected-token-in-noscript" => "Conteúdo inesperado dentro de um noscript",
+        "unexpected-token-in-row" => "Conteúdo inesperado dentro de uma linha de tabela",
+        "unexpected-token-in-table" => "Conteúdo inesperado dentro de uma tabela",
+        "unexpected-token-in-table-body" => "Conteúdo inesperado dentro do corpo de uma tabela",
+        "unknown-named-character-reference" => "Referência de caractere nomeada desconhecida",
+        _ => "Erro de parsing",
+    }
+}
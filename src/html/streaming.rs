@@ -0,0 +1,230 @@
+use super::encoding::{self, Decoder, Encoding, PRESCAN_LENGTH};
+use super::{Tokenizer, TreeBuilder};
+use crate::diagnostics::ParseResult;
+use crate::dom::Node;
+
+// Parser que recebe o documento em trechos de bytes, por exemplo à medida
+// que são lidos de um arquivo ou socket. A árvore é construída conforme a
+// entrada chega, e `document` permite estilizar e fazer o layout do
+// começo do documento antes do restante.
+//
+// A codificação é decidida uma única vez, antes de o parsing começar: por
+// um BOM, pela codificação do transporte ou por um `<meta charset>` nos
+// primeiros 1024 bytes. Um `<meta>` posterior a isso é ignorado.
+pub struct StreamingParser {
+    tokenizer: Tokenizer,
+    builder: TreeBuilder,
+    transport_encoding: Option<Encoding>,
+    // Bytes guardados enquanto a codificação não foi decidida
+    buffer: Vec<u8>,
+    decoder: Option<Decoder>,
+}
+
+impl Default for StreamingParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StreamingParser {
+    pub fn new() -> StreamingParser {
+        StreamingParser {
+            tokenizer: Tokenizer::incremental(),
+            builder: TreeBuilder::new(),
+            transport_encoding: None,
+            buffer: Vec::new(),
+            decoder: None,
+        }
+    }
+
+    // Usa a codificação informada pelo transporte (ex: o header
+    // `Content-Type`), que só é substituída por um BOM
+    pub fn with_encoding(encoding: Encoding) -> StreamingParser {
+        StreamingParser {
+            transport_encoding: Some(encoding),
+            ..StreamingParser::new()
+        }
+    }
+
+    // `None` enquanto não houver bytes suficientes para decidir
+    pub fn encoding(&self) -> Option<Encoding> {
+        self.decoder.as_ref().map(Decoder::encoding)
+    }
+
+    pub fn feed(&mut self, bytes: &[u8]) {
+        match self.decoder {
+            Some(ref mut decoder) => {
+                let text = decoder.decode(bytes, false);
+                self.push_text(&text);
+            }
+            None => {
+                self.buffer.extend_from_slice(bytes);
+
+                // Com a codificação do transporte, basta esperar por um possível BOM
+                let needed = match self.transport_encoding {
+                    Some(_) => 3,
+                    None => PRESCAN_LENGTH,
+                };
+
+                if self.buffer.len() >= needed {
+                    self.start_decoding(false);
+                }
+            }
+        }
+    }
+
+    // Cópia da árvore construída até agora
+    pub fn document(&self) -> Node {
+        self.builder.document()
+    }
+
+    pub fn finish(mut self) -> ParseResult<Node> {
+        match self.decoder {
+            Some(ref mut decoder) => {
+                let text = decoder.decode(&[], true);
+                self.push_text(&text);
+            }
+            None => self.start_decoding(true),
+        }
+
+        self.tokenizer.finish();
+        self.process_tokens();
+
+        super::finish(self.tokenizer, self.builder)
+    }
+
+    fn start_decoding(&mut self, last: bool) {
+        let buffer = std::mem::take(&mut self.buffer);
+
+        let (encoding, bom_length) = encoding::sniff_bom(&buffer).unwrap_or_else(|| {
+            let encoding = self.transport_encoding.unwrap_or_else(|| {
+                let prescanned = &buffer[..buffer.len().min(PRESCAN_LENGTH)];
+
+                encoding::prescan(prescanned).unwrap_or_else(|| encoding::guess(prescanned))
+            });
+
+            (encoding, 0)
+        });
+
+        let mut decoder = Decoder::new(encoding);
+        let text = decoder.decode(&buffer[bom_length..], last);
+
+        self.decoder = Some(decoder);
+        self.push_text(&text);
+    }
+
+    fn push_text(&mut self, text: &str) {
+        self.tokenizer.feed(text);
+        self.process_tokens();
+    }
+
+    fn process_tokens(&mut self) {
+        while let Some(token) = self.tokenizer.next_token() {
+            self.builder.process_token(token, &mut self.tokenizer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html::parse_with_diagnostics;
+
+    fn parse_in_chunks(mut parser: StreamingParser, bytes: &[u8], size: usize) -> String {
+        for chunk in bytes.chunks(size) {
+            parser.feed(chunk);
+        }
+
+        parser.finish().value.outer_html()
+    }
+
+    fn body(html: &str) -> &str {
+        let start = html.find("<body>").unwrap() + "<body>".len();
+        let end = html.rfind("</body>").unwrap();
+
+        &html[start..end]
+    }
+
+    #[test]
+    fn matches_one_shot_parse() {
+        let input = "<!DOCTYPE html><title>Ação &amp; reação</title>\
+            <p class=a>café — 😀 &eacute;&#x20AC;<!-- comentário --></p>\
+            <table>x<tr><td>1<td>2</table><script>if (a < b) {}</script>\
+            <textarea>\nz</textarea><svg><circle r=1 /></svg>";
+        let expected = parse_with_diagnostics(input.to_owned());
+
+        for size in [1, 2, 3, 7, 64, 1024, input.len()] {
+            let mut parser = StreamingParser::new();
+
+            for chunk in input.as_bytes().chunks(size) {
+                parser.feed(chunk);
+            }
+
+            let result = parser.finish();
+
+            assert_eq!(
+                result.value.outer_html(),
+                expected.value.outer_html(),
+                "trechos de {size} bytes"
+            );
+            assert_eq!(
+                result.diagnostics, expected.diagnostics,
+                "trechos de {size} bytes"
+            );
+        }
+    }
+
+    #[test]
+    fn utf8_sequence_split_across_feeds() {
+        let bytes = "<p>😀é</p>".as_bytes();
+
+        for size in 1..=4 {
+            let html = parse_in_chunks(StreamingParser::new(), bytes, size);
+
+            assert_eq!(body(&html), "<p>😀é</p>", "trechos de {size} bytes");
+        }
+    }
+
+    #[test]
+    fn bom_decides_the_encoding() {
+        let mut utf16 = vec![0xFF, 0xFE];
+        utf16.extend("<p>é</p>".encode_utf16().flat_map(u16::to_le_bytes));
+
+        let html = parse_in_chunks(StreamingParser::new(), &utf16, 5);
+        assert_eq!(body(&html), "<p>é</p>");
+
+        // O BOM vence a codificação do transporte
+        let utf8 = b"\xEF\xBB\xBF<p>\xC3\xA9</p>";
+        let html = parse_in_chunks(
+            StreamingParser::with_encoding(Encoding::Windows1252),
+            utf8,
+            2,
+        );
+        assert_eq!(body(&html), "<p>é</p>");
+    }
+
+    #[test]
+    fn meta_charset_only_within_prescan_length() {
+        let meta = b"<meta charset=windows-1252><p>caf\xE9</p>";
+
+        let html = parse_in_chunks(StreamingParser::new(), meta, 16);
+        assert_eq!(body(&html), "<p>café</p>");
+
+        let mut late = b"<!--".to_vec();
+        late.resize(PRESCAN_LENGTH, b'-');
+        late.extend_from_slice(b"-><meta charset=windows-1252><p>caf\xE9</p>");
+
+        let html = parse_in_chunks(StreamingParser::new(), &late, 100);
+        assert_eq!(body(&html), "<p>caf\u{FFFD}</p>");
+    }
+
+    #[test]
+    fn unknown_encoding_guesses_windows_1252() {
+        let html = parse_in_chunks(StreamingParser::new(), b"<p>caf\xE9 \x80</p>", 3);
+        assert_eq!(body(&html), "<p>café €</p>");
+
+        let parser = StreamingParser::with_encoding(Encoding::Utf8);
+        let html = parse_in_chunks(parser, b"<p>caf\xE9</p>", 3);
+        assert_eq!(body(&html), "<p>caf\u{FFFD}</p>");
+    }
+}
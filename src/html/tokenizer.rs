@@ -0,0 +1,1864 @@
+use std::collections::VecDeque;
+use std::mem;
+
+use super::encoding::WINDOWS_1252_C1;
+use super::entities::NAMED_CHARACTER_REFERENCES;
+use super::errors;
+use crate::diagnostics::{Diagnostic, Position, Span};
+use crate::scanner::Scanner;
+
+// Tokenizador HTML seguindo a máquina de estados da especificação WHATWG
+// (https://html.spec.whatwg.org/multipage/parsing.html#tokenization)
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    Doctype(Doctype),
+    StartTag(Tag),
+    EndTag(Tag),
+    Comment(String),
+    Characters(String),
+    Eof,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Tag {
+    pub name: String,
+    pub self_closing: bool,
+    pub attributes: Vec<Attribute>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Attribute {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Doctype {
+    pub name: Option<String>,
+    pub public_id: Option<String>,
+    pub system_id: Option<String>,
+    pub force_quirks: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    Data,
+    Rcdata,
+    Rawtext,
+    ScriptData,
+    Plaintext,
+    TagOpen,
+    EndTagOpen,
+    TagName,
+    RcdataLessThanSign,
+    RcdataEndTagOpen,
+    RcdataEndTagName,
+    RawtextLessThanSign,
+    RawtextEndTagOpen,
+    RawtextEndTagName,
+    ScriptDataLessThanSign,
+    ScriptDataEndTagOpen,
+    ScriptDataEndTagName,
+    ScriptDataEscapeStart,
+    ScriptDataEscapeStartDash,
+    ScriptDataEscaped,
+    ScriptDataEscapedDash,
+    ScriptDataEscapedDashDash,
+    ScriptDataEscapedLessThanSign,
+    ScriptDataEscapedEndTagOpen,
+    ScriptDataEscapedEndTagName,
+    ScriptDataDoubleEscapeStart,
+    ScriptDataDoubleEscaped,
+    ScriptDataDoubleEscapedDash,
+    ScriptDataDoubleEscapedDashDash,
+    ScriptDataDoubleEscapedLessThanSign,
+    ScriptDataDoubleEscapeEnd,
+    BeforeAttributeName,
+    AttributeName,
+    AfterAttributeName,
+    BeforeAttributeValue,
+    AttributeValueDoubleQuoted,
+    AttributeValueSingleQuoted,
+    AttributeValueUnquoted,
+    AfterAttributeValueQuoted,
+    SelfClosingStartTag,
+    BogusComment,
+    MarkupDeclarationOpen,
+    CommentStart,
+    CommentStartDash,
+    Comment,
+    CommentLessThanSign,
+    CommentLessThanSignBang,
+    CommentLessThanSignBangDash,
+    CommentLessThanSignBangDashDash,
+    CommentEndDash,
+    CommentEnd,
+    CommentEndBang,
+    Doctype,
+    BeforeDoctypeName,
+    DoctypeName,
+    AfterDoctypeName,
+    AfterDoctypePublicKeyword,
+    BeforeDoctypePublicIdentifier,
+    DoctypePublicIdentifierDoubleQuoted,
+    DoctypePublicIdentifierSingleQuoted,
+    AfterDoctypePublicIdentifier,
+    BetweenDoctypePublicAndSystemIdentifiers,
+    AfterDoctypeSystemKeyword,
+    BeforeDoctypeSystemIdentifier,
+    DoctypeSystemIdentifierDoubleQuoted,
+    DoctypeSystemIdentifierSingleQuoted,
+    AfterDoctypeSystemIdentifier,
+    BogusDoctype,
+    CdataSection,
+    CdataSectionBracket,
+    CdataSectionEnd,
+    CharacterReference,
+    NamedCharacterReference,
+    AmbiguousAmpersand,
+    NumericCharacterReference,
+    HexadecimalCharacterReferenceStart,
+    DecimalCharacterReferenceStart,
+    HexadecimalCharacterReference,
+    DecimalCharacterReference,
+    NumericCharacterReferenceEnd,
+}
+
+pub struct Tokenizer {
+    scanner: Scanner,
+    previous_position: usize,
+    // Indica se toda a entrada já foi recebida. Enquanto for falso, chegar
+    // ao fim do buffer significa aguardar mais entrada em vez de EOF.
+    input_complete: bool,
+    state: State,
+    return_state: State,
+    character_reference_code: u32,
+    tokens: VecDeque<(Token, Span)>,
+    characters: String,
+    // Offsets usados para calcular o span de cada token: início da
+    // marcação atual (o `<`), fim do último token emitido e fim do
+    // último caractere acumulado em `characters`
+    token_start: usize,
+    last_token_end: usize,
+    characters_end: usize,
+    token_span: Span,
+    current_tag: Tag,
+    current_tag_is_end: bool,
+    current_attribute: Option<Attribute>,
+    current_comment: String,
+    current_doctype: Doctype,
+    temporary_buffer: String,
+    last_start_tag_name: Option<String>,
+    allow_cdata: bool,
+    eof_emitted: bool,
+    errors: Vec<Diagnostic>,
+}
+
+const REPLACEMENT_CHARACTER: char = '\u{FFFD}';
+
+fn is_whitespace(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\x0C' | ' ')
+}
+
+impl Tokenizer {
+    pub fn new(input: String) -> Tokenizer {
+        Tokenizer {
+            scanner: Scanner::new(input),
+            previous_position: 0,
+            input_complete: true,
+            state: State::Data,
+            return_state: State::Data,
+            character_reference_code: 0,
+            tokens: VecDeque::new(),
+            characters: String::new(),
+            token_start: 0,
+            last_token_end: 0,
+            characters_end: 0,
+            token_span: Span::default(),
+            current_tag: Tag::default(),
+            current_tag_is_end: false,
+            current_attribute: None,
+            current_comment: String::new(),
+            current_doctype: Doctype::default(),
+            temporary_buffer: String::new(),
+            last_start_tag_name: None,
+            allow_cdata: false,
+            eof_emitted: false,
+            errors: Vec::new(),
+        }
+    }
+
+    // Tokenizer sem entrada, que recebe o documento aos poucos por `feed`
+    // até que `finish` seja chamado
+    pub fn incremental() -> Tokenizer {
+        Tokenizer {
+            input_complete: false,
+            ..Tokenizer::new(String::new())
+        }
+    }
+
+    pub fn feed(&mut self, input: &str) {
+        debug_assert!(!self.input_complete);
+        self.scanner.push_str(input);
+    }
+
+    // Indica que não há mais entrada: o fim do buffer passa a ser EOF
+    pub fn finish(&mut self) {
+        self.input_complete = true;
+    }
+
+    // Usado pela construção da árvore para trocar o modelo de conteúdo
+    // (ex: RAWTEXT após um `<style>`)
+    pub fn set_state(&mut self, state: State) {
+        self.state = state;
+    }
+
+    // Seções CDATA só são permitidas em conteúdo estrangeiro (SVG/MathML)
+    pub fn set_allow_cdata(&mut self, allow: bool) {
+        self.allow_cdata = allow;
+    }
+
+    pub fn errors(&self) -> &[Diagnostic] {
+        &self.errors
+    }
+
+    // Posição atual na entrada, logo após o último caractere consumido
+    pub fn location(&self) -> Position {
+        self.scanner.location(self.scanner.position())
+    }
+
+    // Span do último token retornado por `next_token`
+    pub fn token_span(&self) -> Span {
+        self.token_span
+    }
+
+    // Retorna o próximo token, ou `None` se não houver entrada suficiente
+    // para produzi-lo ou se o token EOF já tiver sido emitido.
+    pub fn next_token(&mut self) -> Option<Token> {
+        loop {
+            if let Some((token, span)) = self.tokens.pop_front() {
+                self.token_span = span;
+                return Some(token);
+            }
+
+            if self.eof_emitted {
+                return None;
+            }
+
+            if !self.step() {
+                if self.characters.is_empty() {
+                    return None;
+                }
+
+                self.token_span = self.characters_span();
+                return Some(Token::Characters(mem::take(&mut self.characters)));
+            }
+        }
+    }
+
+    // Lê o próximo caractere da entrada, normalizando CR e CRLF para LF.
+    // `Some(None)` representa EOF e `None` que é preciso aguardar mais entrada.
+    fn next_char(&mut self) -> Option<Option<char>> {
+        self.previous_position = self.scanner.position();
+
+        match self.scanner.peek() {
+            None if self.input_complete => Some(None),
+            None => None,
+            Some('\r') => {
+                match self.scanner.rest().as_bytes().get(1) {
+                    Some(b'\n') => self.scanner.advance_by(2),
+                    None if !self.input_complete => return None,
+                    _ => self.scanner.advance_by(1),
+                }
+
+                Some(Some('\n'))
+            }
+            Some(_) => Some(self.scanner.consume_char()),
+        }
+    }
+
+    fn reconsume_in(&mut self, state: State) {
+        self.scanner.set_position(self.previous_position);
+        self.state = state;
+    }
+
+    // Verifica se a entrada restante começa com `s`, sem consumi-la.
+    // Retorna `None` se ainda não há entrada suficiente para decidir.
+    fn next_input_matches(&self, s: &str, ascii_case_insensitive: bool) -> Option<bool> {
+        let rest = self.scanner.rest().as_bytes();
+        let expected = s.as_bytes();
+        let length = rest.len().min(expected.len());
+
+        let matches = if ascii_case_insensitive {
+            rest[..length].eq_ignore_ascii_case(&expected[..length])
+        } else {
+            rest[..length] == expected[..length]
+        };
+
+        if !matches {
+            Some(false)
+        } else if length < expected.len() {
+            if self.input_complete {
+                Some(false)
+            } else {
+                None
+            }
+        } else {
+            Some(true)
+        }
+    }
+
+    fn parse_error(&mut self, kind: &'static str) {
+        self.errors.push(Diagnostic {
+            kind,
+            message: errors::message(kind).to_owned(),
+            span: self
+                .scanner
+                .span(self.previous_position, self.scanner.position()),
+        });
+    }
+
+    fn emit_char(&mut self, c: char) {
+        self.characters.push(c);
+        self.characters_end = self.scanner.position();
+    }
+
+    fn emit_str(&mut self, s: &str) {
+        self.characters.push_str(s);
+        self.characters_end = self.scanner.position();
+    }
+
+    // Texto sempre começa logo após o token anterior
+    fn characters_span(&mut self) -> Span {
+        let end = self.characters_end.max(self.last_token_end);
+        let span = self.scanner.span(self.last_token_end, end);
+        self.last_token_end = end;
+
+        span
+    }
+
+    // Marca o início de uma possível tag antes de trocar de estado
+    fn begin_markup(&mut self, state: State) {
+        self.token_start = self.previous_position;
+        self.state = state;
+    }
+
+    fn emit(&mut self, token: Token) {
+        if !self.characters.is_empty() {
+            let characters = mem::take(&mut self.characters);
+            let span = self.characters_span();
+            self.tokens.push_back((Token::Characters(characters), span));
+        }
+
+        if let Token::StartTag(ref tag) = token {
+            self.last_start_tag_name = Some(tag.name.clone());
+        }
+
+        let start = match token {
+            Token::Eof => self.scanner.position(),
+            _ => self.token_start.max(self.last_token_end),
+        };
+        let span = self.scanner.span(start, self.scanner.position());
+        self.last_token_end = self.scanner.position();
+
+        self.tokens.push_back((token, span));
+    }
+
+    fn emit_eof(&mut self) {
+        self.emit(Token::Eof);
+        self.eof_emitted = true;
+    }
+
+    fn create_tag(&mut self, is_end: bool) {
+        self.current_tag = Tag::default();
+        self.current_tag_is_end = is_end;
+        self.current_attribute = None;
+    }
+
+    fn start_attribute(&mut self, name: &str) {
+        self.finish_attribute();
+
+        self.current_attribute = Some(Attribute {
+            name: name.to_owned(),
+            value: String::new(),
+        });
+    }
+
+    // Atributos duplicados são descartados: vale a primeira ocorrência
+    fn finish_attribute(&mut self) {
+        if let Some(attribute) = self.current_attribute.take() {
+            if self
+                .current_tag
+                .attributes
+                .iter()
+                .any(|existing| existing.name == attribute.name)
+            {
+                self.parse_error("duplicate-attribute");
+            } else {
+                self.current_tag.attributes.push(attribute);
+            }
+        }
+    }
+
+    fn attribute_name(&mut self) -> &mut String {
+        &mut self
+            .current_attribute
+            .get_or_insert_with(Attribute::default)
+            .name
+    }
+
+    fn attribute_value(&mut self) -> &mut String {
+        &mut self
+            .current_attribute
+            .get_or_insert_with(Attribute::default)
+            .value
+    }
+
+    fn emit_current_tag(&mut self) {
+        self.finish_attribute();
+
+        let tag = mem::take(&mut self.current_tag);
+
+        if self.current_tag_is_end {
+            if !tag.attributes.is_empty() {
+                self.parse_error("end-tag-with-attributes");
+            }
+
+            if tag.self_closing {
+                self.parse_error("end-tag-with-trailing-solidus");
+            }
+
+            self.emit(Token::EndTag(tag));
+        } else {
+            self.emit(Token::StartTag(tag));
+        }
+    }
+
+    fn is_appropriate_end_tag(&self) -> bool {
+        self.current_tag_is_end
+            && self.last_start_tag_name.as_deref() == Some(self.current_tag.name.as_str())
+    }
+
+    fn emit_current_comment(&mut self) {
+        let comment = mem::take(&mut self.current_comment);
+        self.emit(Token::Comment(comment));
+    }
+
+    fn create_doctype(&mut self) {
+        self.current_doctype = Doctype::default();
+    }
+
+    fn doctype_name(&mut self) -> &mut String {
+        self.current_doctype.name.get_or_insert_with(String::new)
+    }
+
+    fn emit_current_doctype(&mut self) {
+        let doctype = mem::take(&mut self.current_doctype);
+        self.emit(Token::Doctype(doctype));
+    }
+
+    // EOF dentro de um DOCTYPE: o DOCTYPE é emitido em modo quirks
+    fn emit_doctype_at_eof(&mut self) {
+        self.parse_error("eof-in-doctype");
+        self.current_doctype.force_quirks = true;
+        self.emit_current_doctype();
+        self.emit_eof();
+    }
+
+    // Executa um passo da máquina de estados. Retorna falso quando é
+    // preciso aguardar mais entrada.
+    fn step(&mut self) -> bool {
+        match self.state {
+            State::MarkupDeclarationOpen => return self.markup_declaration_open(),
+            State::NamedCharacterReference => return self.named_character_reference(),
+            State::NumericCharacterReferenceEnd => {
+                self.numeric_character_reference_end();
+                return true;
+            }
+            State::AfterDoctypeName => {
+                for (keyword, state) in [
+                    ("public", State::AfterDoctypePublicKeyword),
+                    ("system", State::AfterDoctypeSystemKeyword),
+                ] {
+                    match self.next_input_matches(keyword, true) {
+                        None => return false,
+                        Some(true) => {
+                            self.scanner.advance_by(keyword.len());
+                            self.state = state;
+                            return true;
+                        }
+                        Some(false) => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        let c = match self.next_char() {
+            Some(c) => c,
+            None => return false,
+        };
+
+        match self.state {
+            State::Data => match c {
+                Some('&') => self.start_character_reference(State::Data),
+                Some('<') => self.begin_markup(State::TagOpen),
+                Some('\0') => {
+                    self.parse_error("unexpected-null-character");
+                    self.emit_char('\0');
+                }
+                Some(c) => self.emit_char(c),
+                None => self.emit_eof(),
+            },
+
+            State::Rcdata => match c {
+                Some('&') => self.start_character_reference(State::Rcdata),
+                Some('<') => self.begin_markup(State::RcdataLessThanSign),
+                Some('\0') => {
+                    self.parse_error("unexpected-null-character");
+                    self.emit_char(REPLACEMENT_CHARACTER);
+                }
+                Some(c) => self.emit_char(c),
+                None => self.emit_eof(),
+            },
+
+            State::Rawtext => match c {
+                Some('<') => self.begin_markup(State::RawtextLessThanSign),
+                Some('\0') => {
+                    self.parse_error("unexpected-null-character");
+                    self.emit_char(REPLACEMENT_CHARACTER);
+                }
+                Some(c) => self.emit_char(c),
+                None => self.emit_eof(),
+            },
+
+            State::ScriptData => match c {
+                Some('<') => self.begin_markup(State::ScriptDataLessThanSign),
+                Some('\0') => {
+                    self.parse_error("unexpected-null-character");
+                    self.emit_char(REPLACEMENT_CHARACTER);
+                }
+                Some(c) => self.emit_char(c),
+                None => self.emit_eof(),
+            },
+
+            State::Plaintext => match c {
+                Some('\0') => {
+                    self.parse_error("unexpected-null-character");
+                    self.emit_char(REPLACEMENT_CHARACTER);
+                }
+                Some(c) => self.emit_char(c),
+                None => self.emit_eof(),
+            },
+
+            State::TagOpen => match c {
+                Some('!') => self.state = State::MarkupDeclarationOpen,
+                Some('/') => self.state = State::EndTagOpen,
+                Some(c) if c.is_ascii_alphabetic() => {
+                    self.create_tag(false);
+                    self.reconsume_in(State::TagName);
+                }
+                Some('?') => {
+                    self.parse_error("unexpected-question-mark-instead-of-tag-name");
+                    self.current_comment.clear();
+                    self.reconsume_in(State::BogusComment);
+                }
+                Some(_) => {
+                    self.parse_error("invalid-first-character-of-tag-name");
+                    self.emit_char('<');
+                    self.reconsume_in(State::Data);
+                }
+                None => {
+                    self.parse_error("eof-before-tag-name");
+                    self.emit_char('<');
+                    self.emit_eof();
+                }
+            },
+
+            State::EndTagOpen => match c {
+                Some(c) if c.is_ascii_alphabetic() => {
+                    self.create_tag(true);
+                    self.reconsume_in(State::TagName);
+                }
+                Some('>') => {
+                    self.parse_error("missing-end-tag-name");
+                    self.state = State::Data;
+                }
+                Some(_) => {
+                    self.parse_error("invalid-first-character-of-tag-name");
+                    self.current_comment.clear();
+                    self.reconsume_in(State::BogusComment);
+                }
+                None => {
+                    self.parse_error("eof-before-tag-name");
+                    self.emit_str("</");
+                    self.emit_eof();
+                }
+            },
+
+            State::TagName => match c {
+                Some(c) if is_whitespace(c) => self.state = State::BeforeAttributeName,
+                Some('/') => self.state = State::SelfClosingStartTag,
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_current_tag();
+                }
+                Some('\0') => {
+                    self.parse_error("unexpected-null-character");
+                    self.current_tag.name.push(REPLACEMENT_CHARACTER);
+                }
+                Some(c) => self.current_tag.name.push(c.to_ascii_lowercase()),
+                None => {
+                    self.parse_error("eof-in-tag");
+                    self.emit_eof();
+                }
+            },
+
+            State::RcdataLessThanSign => {
+                self.text_less_than_sign(c, State::RcdataEndTagOpen, State::Rcdata)
+            }
+            State::RcdataEndTagOpen => {
+                self.text_end_tag_open(c, State::RcdataEndTagName, State::Rcdata)
+            }
+            State::RcdataEndTagName => self.text_end_tag_name(c, State::Rcdata),
+
+            State::RawtextLessThanSign => {
+                self.text_less_than_sign(c, State::RawtextEndTagOpen, State::Rawtext)
+            }
+            State::RawtextEndTagOpen => {
+                self.text_end_tag_open(c, State::RawtextEndTagName, State::Rawtext)
+            }
+            State::RawtextEndTagName => self.text_end_tag_name(c, State::Rawtext),
+
+            State::ScriptDataLessThanSign => match c {
+                Some('!') => {
+                    self.state = State::ScriptDataEscapeStart;
+                    self.emit_str("<!");
+                }
+                c => self.text_less_than_sign(c, State::ScriptDataEndTagOpen, State::ScriptData),
+            },
+            State::ScriptDataEndTagOpen => {
+                self.text_end_tag_open(c, State::ScriptDataEndTagName, State::ScriptData)
+            }
+            State::ScriptDataEndTagName => self.text_end_tag_name(c, State::ScriptData),
+
+            State::ScriptDataEscapeStart => match c {
+                Some('-') => {
+                    self.state = State::ScriptDataEscapeStartDash;
+                    self.emit_char('-');
+                }
+                _ => self.reconsume_in(State::ScriptData),
+            },
+
+            State::ScriptDataEscapeStartDash => match c {
+                Some('-') => {
+                    self.state = State::ScriptDataEscapedDashDash;
+                    self.emit_char('-');
+                }
+                _ => self.reconsume_in(State::ScriptData),
+            },
+
+            State::ScriptDataEscaped => match c {
+                Some('-') => {
+                    self.state = State::ScriptDataEscapedDash;
+                    self.emit_char('-');
+                }
+                Some('<') => self.begin_markup(State::ScriptDataEscapedLessThanSign),
+                Some('\0') => {
+                    self.parse_error("unexpected-null-character");
+                    self.emit_char(REPLACEMENT_CHARACTER);
+                }
+                Some(c) => self.emit_char(c),
+                None => {
+                    self.parse_error("eof-in-script-html-comment-like-text");
+                    self.emit_eof();
+                }
+            },
+
+            State::ScriptDataEscapedDash => match c {
+                Some('-') => {
+                    self.state = State::ScriptDataEscapedDashDash;
+                    self.emit_char('-');
+                }
+                Some('<') => self.begin_markup(State::ScriptDataEscapedLessThanSign),
+                Some('\0') => {
+                    self.parse_error("unexpected-null-character");
+                    self.state = State::ScriptDataEscaped;
+                    self.emit_char(REPLACEMENT_CHARACTER);
+                }
+                Some(c) => {
+                    self.state = State::ScriptDataEscaped;
+                    self.emit_char(c);
+                }
+                None => {
+                    self.parse_error("eof-in-script-html-comment-like-text");
+                    self.emit_eof();
+                }
+            },
+
+            State::ScriptDataEscapedDashDash => match c {
+                Some('-') => self.emit_char('-'),
+                Some('<') => self.begin_markup(State::ScriptDataEscapedLessThanSign),
+                Some('>') => {
+                    self.state = State::ScriptData;
+                    self.emit_char('>');
+                }
+                Some('\0') => {
+                    self.parse_error("unexpected-null-character");
+                    self.state = State::ScriptDataEscaped;
+                    self.emit_char(REPLACEMENT_CHARACTER);
+                }
+                Some(c) => {
+                    self.state = State::ScriptDataEscaped;
+                    self.emit_char(c);
+                }
+                None => {
+                    self.parse_error("eof-in-script-html-comment-like-text");
+                    self.emit_eof();
+                }
+            },
+
+            State::ScriptDataEscapedLessThanSign => match c {
+                Some('/') => {
+                    self.temporary_buffer.clear();
+                    self.state = State::ScriptDataEscapedEndTagOpen;
+                }
+                Some(c) if c.is_ascii_alphabetic() => {
+                    self.temporary_buffer.clear();
+                    self.emit_char('<');
+                    self.reconsume_in(State::ScriptDataDoubleEscapeStart);
+                }
+                _ => {
+                    self.emit_char('<');
+                    self.reconsume_in(State::ScriptDataEscaped);
+                }
+            },
+
+            State::ScriptDataEscapedEndTagOpen => self.text_end_tag_open(
+                c,
+                State::ScriptDataEscapedEndTagName,
+                State::ScriptDataEscaped,
+            ),
+            State::ScriptDataEscapedEndTagName => {
+                self.text_end_tag_name(c, State::ScriptDataEscaped)
+            }
+
+            State::ScriptDataDoubleEscapeStart => self.script_data_double_escape(
+                c,
+                State::ScriptDataDoubleEscaped,
+                State::ScriptDataEscaped,
+            ),
+
+            State::ScriptDataDoubleEscaped => match c {
+                Some('-') => {
+                    self.state = State::ScriptDataDoubleEscapedDash;
+                    self.emit_char('-');
+                }
+                Some('<') => {
+                    self.state = State::ScriptDataDoubleEscapedLessThanSign;
+                    self.emit_char('<');
+                }
+                Some('\0') => {
+                    self.parse_error("unexpected-null-character");
+                    self.emit_char(REPLACEMENT_CHARACTER);
+                }
+                Some(c) => self.emit_char(c),
+                None => {
+                    self.parse_error("eof-in-script-html-comment-like-text");
+                    self.emit_eof();
+                }
+            },
+
+            State::ScriptDataDoubleEscapedDash => match c {
+                Some('-') => {
+                    self.state = State::ScriptDataDoubleEscapedDashDash;
+                    self.emit_char('-');
+                }
+                Some('<') => {
+                    self.state = State::ScriptDataDoubleEscapedLessThanSign;
+                    self.emit_char('<');
+                }
+                Some('\0') => {
+                    self.parse_error("unexpected-null-character");
+                    self.state = State::ScriptDataDoubleEscaped;
+                    self.emit_char(REPLACEMENT_CHARACTER);
+                }
+                Some(c) => {
+                    self.state = State::ScriptDataDoubleEscaped;
+                    self.emit_char(c);
+                }
+                None => {
+                    self.parse_error("eof-in-script-html-comment-like-text");
+                    self.emit_eof();
+                }
+            },
+
+            State::ScriptDataDoubleEscapedDashDash => match c {
+                Some('-') => self.emit_char('-'),
+                Some('<') => {
+                    self.state = State::ScriptDataDoubleEscapedLessThanSign;
+                    self.emit_char('<');
+                }
+                Some('>') => {
+                    self.state = State::ScriptData;
+                    self.emit_char('>');
+                }
+                Some('\0') => {
+                    self.parse_error("unexpected-null-character");
+                    self.state = State::ScriptDataDoubleEscaped;
+                    self.emit_char(REPLACEMENT_CHARACTER);
+                }
+                Some(c) => {
+                    self.state = State::ScriptDataDoubleEscaped;
+                    self.emit_char(c);
+                }
+                None => {
+                    self.parse_error("eof-in-script-html-comment-like-text");
+                    self.emit_eof();
+                }
+            },
+
+            State::ScriptDataDoubleEscapedLessThanSign => match c {
+                Some('/') => {
+                    self.temporary_buffer.clear();
+                    self.state = State::ScriptDataDoubleEscapeEnd;
+                    self.emit_char('/');
+                }
+                _ => self.reconsume_in(State::ScriptDataDoubleEscaped),
+            },
+
+            State::ScriptDataDoubleEscapeEnd => self.script_data_double_escape(
+                c,
+                State::ScriptDataEscaped,
+                State::ScriptDataDoubleEscaped,
+            ),
+
+            State::BeforeAttributeName => match c {
+                Some(c) if is_whitespace(c) => {}
+                Some('/') | Some('>') | None => self.reconsume_in(State::AfterAttributeName),
+                Some('=') => {
+                    self.parse_error("unexpected-equals-sign-before-attribute-name");
+                    self.start_attribute("=");
+                    self.state = State::AttributeName;
+                }
+                Some(_) => {
+                    self.start_attribute("");
+                    self.reconsume_in(State::AttributeName);
+                }
+            },
+
+            State::AttributeName => match c {
+                Some(c) if is_whitespace(c) => self.reconsume_in(State::AfterAttributeName),
+                Some('/') | Some('>') | None => self.reconsume_in(State::AfterAttributeName),
+                Some('=') => self.state = State::BeforeAttributeValue,
+                Some('\0') => {
+                    self.parse_error("unexpected-null-character");
+                    self.attribute_name().push(REPLACEMENT_CHARACTER);
+                }
+                Some(c) => {
+                    if matches!(c, '"' | '\'' | '<') {
+                        self.parse_error("unexpected-character-in-attribute-name");
+                    }
+
+                    self.attribute_name().push(c.to_ascii_lowercase());
+                }
+            },
+
+            State::AfterAttributeName => match c {
+                Some(c) if is_whitespace(c) => {}
+                Some('/') => self.state = State::SelfClosingStartTag,
+                Some('=') => self.state = State::BeforeAttributeValue,
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_current_tag();
+                }
+                Some(_) => {
+                    self.start_attribute("");
+                    self.reconsume_in(State::AttributeName);
+                }
+                None => {
+                    self.parse_error("eof-in-tag");
+                    self.emit_eof();
+                }
+            },
+
+            State::BeforeAttributeValue => match c {
+                Some(c) if is_whitespace(c) => {}
+                Some('"') => self.state = State::AttributeValueDoubleQuoted,
+                Some('\'') => self.state = State::AttributeValueSingleQuoted,
+                Some('>') => {
+                    self.parse_error("missing-attribute-value");
+                    self.state = State::Data;
+                    self.emit_current_tag();
+                }
+                _ => self.reconsume_in(State::AttributeValueUnquoted),
+            },
+
+            State::AttributeValueDoubleQuoted => self.attribute_value_quoted(c, '"'),
+            State::AttributeValueSingleQuoted => self.attribute_value_quoted(c, '\''),
+
+            State::AttributeValueUnquoted => match c {
+                Some(c) if is_whitespace(c) => self.state = State::BeforeAttributeName,
+                Some('&') => self.start_character_reference(State::AttributeValueUnquoted),
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_current_tag();
+                }
+                Some('\0') => {
+                    self.parse_error("unexpected-null-character");
+                    self.attribute_value().push(REPLACEMENT_CHARACTER);
+                }
+                Some(c) => {
+                    if matches!(c, '"' | '\'' | '<' | '=' | '`') {
+                        self.parse_error("unexpected-character-in-unquoted-attribute-value");
+                    }
+
+                    self.attribute_value().push(c);
+                }
+                None => {
+                    self.parse_error("eof-in-tag");
+                    self.emit_eof();
+                }
+            },
+
+            State::AfterAttributeValueQuoted => match c {
+                Some(c) if is_whitespace(c) => self.state = State::BeforeAttributeName,
+                Some('/') => self.state = State::SelfClosingStartTag,
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_current_tag();
+                }
+                Some(_) => {
+                    self.parse_error("missing-whitespace-between-attributes");
+                    self.reconsume_in(State::BeforeAttributeName);
+                }
+                None => {
+                    self.parse_error("eof-in-tag");
+                    self.emit_eof();
+                }
+            },
+
+            State::SelfClosingStartTag => match c {
+                Some('>') => {
+                    self.current_tag.self_closing = true;
+                    self.state = State::Data;
+                    self.emit_current_tag();
+                }
+                Some(_) => {
+                    self.parse_error("unexpected-solidus-in-tag");
+                    self.reconsume_in(State::BeforeAttributeName);
+                }
+                None => {
+                    self.parse_error("eof-in-tag");
+                    self.emit_eof();
+                }
+            },
+
+            State::BogusComment => match c {
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_current_comment();
+                }
+                Some('\0') => {
+                    self.parse_error("unexpected-null-character");
+                    self.current_comment.push(REPLACEMENT_CHARACTER);
+                }
+                Some(c) => self.current_comment.push(c),
+                None => {
+                    self.emit_current_comment();
+                    self.emit_eof();
+                }
+            },
+
+            State::CommentStart => match c {
+                Some('-') => self.state = State::CommentStartDash,
+                Some('>') => {
+                    self.parse_error("abrupt-closing-of-empty-comment");
+                    self.state = State::Data;
+                    self.emit_current_comment();
+                }
+                _ => self.reconsume_in(State::Comment),
+            },
+
+            State::CommentStartDash => match c {
+                Some('-') => self.state = State::CommentEnd,
+                Some('>') => {
+                    self.parse_error("abrupt-closing-of-empty-comment");
+                    self.state = State::Data;
+                    self.emit_current_comment();
+                }
+                Some(_) => {
+                    self.current_comment.push('-');
+                    self.reconsume_in(State::Comment);
+                }
+                None => self.emit_comment_at_eof(),
+            },
+
+            State::Comment => match c {
+                Some('<') => {
+                    self.current_comment.push('<');
+                    self.state = State::CommentLessThanSign;
+                }
+                Some('-') => self.state = State::CommentEndDash,
+                Some('\0') => {
+                    self.parse_error("unexpected-null-character");
+                    self.current_comment.push(REPLACEMENT_CHARACTER);
+                }
+                Some(c) => self.current_comment.push(c),
+                None => self.emit_comment_at_eof(),
+            },
+
+            State::CommentLessThanSign => match c {
+                Some('!') => {
+                    self.current_comment.push('!');
+                    self.state = State::CommentLessThanSignBang;
+                }
+                Some('<') => self.current_comment.push('<'),
+                _ => self.reconsume_in(State::Comment),
+            },
+
+            State::CommentLessThanSignBang => match c {
+                Some('-') => self.state = State::CommentLessThanSignBangDash,
+                _ => self.reconsume_in(State::Comment),
+            },
+
+            State::CommentLessThanSignBangDash => match c {
+                Some('-') => self.state = State::CommentLessThanSignBangDashDash,
+                _ => self.reconsume_in(State::CommentEndDash),
+            },
+
+            State::CommentLessThanSignBangDashDash => match c {
+                Some('>') | None => self.reconsume_in(State::CommentEnd),
+                Some(_) => {
+                    self.parse_error("nested-comment");
+                    self.reconsume_in(State::CommentEnd);
+                }
+            },
+
+            State::CommentEndDash => match c {
+                Some('-') => self.state = State::CommentEnd,
+                Some(_) => {
+                    self.current_comment.push('-');
+                    self.reconsume_in(State::Comment);
+                }
+                None => self.emit_comment_at_eof(),
+            },
+
+            State::CommentEnd => match c {
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_current_comment();
+                }
+                Some('!') => self.state = State::CommentEndBang,
+                Some('-') => self.current_comment.push('-'),
+                Some(_) => {
+                    self.current_comment.push_str("--");
+                    self.reconsume_in(State::Comment);
+                }
+                None => self.emit_comment_at_eof(),
+            },
+
+            State::CommentEndBang => match c {
+                Some('-') => {
+                    self.current_comment.push_str("--!");
+                    self.state = State::CommentEndDash;
+                }
+                Some('>') => {
+                    self.parse_error("incorrectly-closed-comment");
+                    self.state = State::Data;
+                    self.emit_current_comment();
+                }
+                Some(_) => {
+                    self.current_comment.push_str("--!");
+                    self.reconsume_in(State::Comment);
+                }
+                None => self.emit_comment_at_eof(),
+            },
+
+            State::Doctype => match c {
+                Some(c) if is_whitespace(c) => self.state = State::BeforeDoctypeName,
+                Some('>') => self.reconsume_in(State::BeforeDoctypeName),
+                Some(_) => {
+                    self.parse_error("missing-whitespace-before-doctype-name");
+                    self.reconsume_in(State::BeforeDoctypeName);
+                }
+                None => {
+                    self.create_doctype();
+                    self.emit_doctype_at_eof();
+                }
+            },
+
+            State::BeforeDoctypeName => match c {
+                Some(c) if is_whitespace(c) => {}
+                Some('>') => {
+                    self.parse_error("missing-doctype-name");
+                    self.create_doctype();
+                    self.current_doctype.force_quirks = true;
+                    self.state = State::Data;
+                    self.emit_current_doctype();
+                }
+                Some(c) => {
+                    self.create_doctype();
+
+                    if c == '\0' {
+                        self.parse_error("unexpected-null-character");
+                        self.doctype_name().push(REPLACEMENT_CHARACTER);
+                    } else {
+                        self.doctype_name().push(c.to_ascii_lowercase());
+                    }
+
+                    self.state = State::DoctypeName;
+                }
+                None => {
+                    self.create_doctype();
+                    self.emit_doctype_at_eof();
+                }
+            },
+
+            State::DoctypeName => match c {
+                Some(c) if is_whitespace(c) => self.state = State::AfterDoctypeName,
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_current_doctype();
+                }
+                Some('\0') => {
+                    self.parse_error("unexpected-null-character");
+                    self.doctype_name().push(REPLACEMENT_CHARACTER);
+                }
+                Some(c) => self.doctype_name().push(c.to_ascii_lowercase()),
+                None => self.emit_doctype_at_eof(),
+            },
+
+            // As palavras-chave PUBLIC e SYSTEM já foram tratadas no início do passo
+            State::AfterDoctypeName => match c {
+                Some(c) if is_whitespace(c) => {}
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_current_doctype();
+                }
+                Some(_) => {
+                    self.parse_error("invalid-character-sequence-after-doctype-name");
+                    self.current_doctype.force_quirks = true;
+                    self.reconsume_in(State::BogusDoctype);
+                }
+                None => self.emit_doctype_at_eof(),
+            },
+
+            State::AfterDoctypePublicKeyword => self.after_doctype_keyword(
+                c,
+                true,
+                State::BeforeDoctypePublicIdentifier,
+                "missing-whitespace-after-doctype-public-keyword",
+            ),
+            State::BeforeDoctypePublicIdentifier => self.before_doctype_identifier(c, true),
+            State::DoctypePublicIdentifierDoubleQuoted => {
+                self.doctype_identifier_quoted(c, '"', true)
+            }
+            State::DoctypePublicIdentifierSingleQuoted => {
+                self.doctype_identifier_quoted(c, '\'', true)
+            }
+
+            State::AfterDoctypePublicIdentifier => match c {
+                Some(c) if is_whitespace(c) => {
+                    self.state = State::BetweenDoctypePublicAndSystemIdentifiers
+                }
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_current_doctype();
+                }
+                Some(quote @ ('"' | '\'')) => {
+                    self.parse_error(
+                        "missing-whitespace-between-doctype-public-and-system-identifiers",
+                    );
+                    self.start_doctype_identifier(quote, false);
+                }
+                Some(_) => {
+                    self.parse_error("missing-quote-before-doctype-system-identifier");
+                    self.current_doctype.force_quirks = true;
+                    self.reconsume_in(State::BogusDoctype);
+                }
+                None => self.emit_doctype_at_eof(),
+            },
+
+            State::BetweenDoctypePublicAndSystemIdentifiers => match c {
+                Some(c) if is_whitespace(c) => {}
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_current_doctype();
+                }
+                Some(quote @ ('"' | '\'')) => self.start_doctype_identifier(quote, false),
+                Some(_) => {
+                    self.parse_error("missing-quote-before-doctype-system-identifier");
+                    self.current_doctype.force_quirks = true;
+                    self.reconsume_in(State::BogusDoctype);
+                }
+                None => self.emit_doctype_at_eof(),
+            },
+
+            State::AfterDoctypeSystemKeyword => self.after_doctype_keyword(
+                c,
+                false,
+                State::BeforeDoctypeSystemIdentifier,
+                "missing-whitespace-after-doctype-system-keyword",
+            ),
+            State::BeforeDoctypeSystemIdentifier => self.before_doctype_identifier(c, false),
+            State::DoctypeSystemIdentifierDoubleQuoted => {
+                self.doctype_identifier_quoted(c, '"', false)
+            }
+            State::DoctypeSystemIdentifierSingleQuoted => {
+                self.doctype_identifier_quoted(c, '\'', false)
+            }
+
+            State::AfterDoctypeSystemIdentifier => match c {
+                Some(c) if is_whitespace(c) => {}
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_current_doctype();
+                }
+                Some(_) => {
+                    self.parse_error("unexpected-character-after-doctype-system-identifier");
+                    self.reconsume_in(State::BogusDoctype);
+                }
+                None => self.emit_doctype_at_eof(),
+            },
+
+            State::BogusDoctype => match c {
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_current_doctype();
+                }
+                Some('\0') => self.parse_error("unexpected-null-character"),
+                Some(_) => {}
+                None => {
+                    self.emit_current_doctype();
+                    self.emit_eof();
+                }
+            },
+
+            State::CdataSection => match c {
+                Some(']') => self.state = State::CdataSectionBracket,
+                Some(c) => self.emit_char(c),
+                None => {
+                    self.parse_error("eof-in-cdata");
+                    self.emit_eof();
+                }
+            },
+
+            State::CdataSectionBracket => match c {
+                Some(']') => self.state = State::CdataSectionEnd,
+                _ => {
+                    self.emit_char(']');
+                    self.reconsume_in(State::CdataSection);
+                }
+            },
+
+            State::CdataSectionEnd => match c {
+                Some(']') => self.emit_char(']'),
+                Some('>') => self.state = State::Data,
+                _ => {
+                    self.emit_str("]]");
+                    self.reconsume_in(State::CdataSection);
+                }
+            },
+
+            State::CharacterReference => match c {
+                Some(c) if c.is_ascii_alphanumeric() => {
+                    self.reconsume_in(State::NamedCharacterReference)
+                }
+                Some('#') => {
+                    self.temporary_buffer.push('#');
+                    self.state = State::NumericCharacterReference;
+                }
+                _ => {
+                    self.flush_character_reference();
+                    self.reconsume_in(self.return_state);
+                }
+            },
+
+            State::AmbiguousAmpersand => match c {
+                Some(c) if c.is_ascii_alphanumeric() => {
+                    if self.is_consuming_attribute_value() {
+                        self.attribute_value().push(c);
+                    } else {
+                        self.emit_char(c);
+                    }
+                }
+                Some(';') => {
+                    self.parse_error("unknown-named-character-reference");
+                    self.reconsume_in(self.return_state);
+                }
+                _ => self.reconsume_in(self.return_state),
+            },
+
+            State::NumericCharacterReference => {
+                self.character_reference_code = 0;
+
+                match c {
+                    Some(c @ ('x' | 'X')) => {
+                        self.temporary_buffer.push(c);
+                        self.state = State::HexadecimalCharacterReferenceStart;
+                    }
+                    _ => self.reconsume_in(State::DecimalCharacterReferenceStart),
+                }
+            }
+
+            State::HexadecimalCharacterReferenceStart => match c {
+                Some(c) if c.is_ascii_hexdigit() => {
+                    self.reconsume_in(State::HexadecimalCharacterReference)
+                }
+                _ => self.absence_of_digits(),
+            },
+
+            State::DecimalCharacterReferenceStart => match c {
+                Some(c) if c.is_ascii_digit() => {
+                    self.reconsume_in(State::DecimalCharacterReference)
+                }
+                _ => self.absence_of_digits(),
+            },
+
+            State::HexadecimalCharacterReference => self.numeric_character_reference_digit(c, 16),
+            State::DecimalCharacterReference => self.numeric_character_reference_digit(c, 10),
+
+            State::MarkupDeclarationOpen
+            | State::NamedCharacterReference
+            | State::NumericCharacterReferenceEnd => unreachable!(),
+        }
+
+        true
+    }
+
+    fn markup_declaration_open(&mut self) -> bool {
+        match self.next_input_matches("--", false) {
+            None => return false,
+            Some(true) => {
+                self.scanner.advance_by(2);
+                self.current_comment.clear();
+                self.state = State::CommentStart;
+                return true;
+            }
+            Some(false) => {}
+        }
+
+        match self.next_input_matches("doctype", true) {
+            None => return false,
+            Some(true) => {
+                self.scanner.advance_by("doctype".len());
+                self.state = State::Doctype;
+                return true;
+            }
+            Some(false) => {}
+        }
+
+        match self.next_input_matches("[CDATA[", false) {
+            None => return false,
+            Some(true) => {
+                self.scanner.advance_by("[CDATA[".len());
+
+                if self.allow_cdata {
+                    self.state = State::CdataSection;
+                } else {
+                    self.parse_error("cdata-in-html-content");
+                    self.current_comment = "[CDATA[".to_owned();
+                    self.state = State::BogusComment;
+                }
+
+                return true;
+            }
+            Some(false) => {}
+        }
+
+        self.parse_error("incorrectly-opened-comment");
+        self.current_comment.clear();
+        self.state = State::BogusComment;
+
+        true
+    }
+
+    fn emit_comment_at_eof(&mut self) {
+        self.parse_error("eof-in-comment");
+        self.emit_current_comment();
+        self.emit_eof();
+    }
+
+    // Estados "less-than sign" de RCDATA, RAWTEXT e script data
+    fn text_less_than_sign(&mut self, c: Option<char>, end_tag_open: State, text: State) {
+        if c == Some('/') {
+            self.temporary_buffer.clear();
+            self.state = end_tag_open;
+        } else {
+            self.emit_char('<');
+            self.reconsume_in(text);
+        }
+    }
+
+    fn text_end_tag_open(&mut self, c: Option<char>, end_tag_name: State, text: State) {
+        match c {
+            Some(c) if c.is_ascii_alphabetic() => {
+                self.create_tag(true);
+                self.reconsume_in(end_tag_name);
+            }
+            _ => {
+                self.emit_str("</");
+                self.reconsume_in(text);
+            }
+        }
+    }
+
+    // Dentro de RCDATA/RAWTEXT/script só a end tag do elemento aberto
+    // encerra o texto; qualquer outra coisa volta a ser texto.
+    fn text_end_tag_name(&mut self, c: Option<char>, text: State) {
+        match c {
+            Some(c) if is_whitespace(c) && self.is_appropriate_end_tag() => {
+                self.state = State::BeforeAttributeName;
+            }
+            Some('/') if self.is_appropriate_end_tag() => {
+                self.state = State::SelfClosingStartTag;
+            }
+            Some('>') if self.is_appropriate_end_tag() => {
+                self.state = State::Data;
+                self.emit_current_tag();
+            }
+            Some(c) if c.is_ascii_alphabetic() => {
+                self.current_tag.name.push(c.to_ascii_lowercase());
+                self.temporary_buffer.push(c);
+            }
+            _ => {
+                let buffer = mem::take(&mut self.temporary_buffer);
+                self.emit_str("</");
+                self.emit_str(&buffer);
+                self.reconsume_in(text);
+            }
+        }
+    }
+
+    fn script_data_double_escape(&mut self, c: Option<char>, if_script: State, otherwise: State) {
+        match c {
+            Some(c) if is_whitespace(c) || c == '/' || c == '>' => {
+                self.state = if self.temporary_buffer == "script" {
+                    if_script
+                } else {
+                    otherwise
+                };
+                self.emit_char(c);
+            }
+            Some(c) if c.is_ascii_alphabetic() => {
+                self.temporary_buffer.push(c.to_ascii_lowercase());
+                self.emit_char(c);
+            }
+            _ => self.reconsume_in(otherwise),
+        }
+    }
+
+    fn attribute_value_quoted(&mut self, c: Option<char>, quote: char) {
+        match c {
+            Some(c) if c == quote => self.state = State::AfterAttributeValueQuoted,
+            Some('&') => self.start_character_reference(self.state),
+            Some('\0') => {
+                self.parse_error("unexpected-null-character");
+                self.attribute_value().push(REPLACEMENT_CHARACTER);
+            }
+            Some(c) => self.attribute_value().push(c),
+            None => {
+                self.parse_error("eof-in-tag");
+                self.emit_eof();
+            }
+        }
+    }
+
+    fn start_doctype_identifier(&mut self, quote: char, public: bool) {
+        self.state = match (public, quote) {
+            (true, '"') => State::DoctypePublicIdentifierDoubleQuoted,
+            (true, _) => State::DoctypePublicIdentifierSingleQuoted,
+            (false, '"') => State::DoctypeSystemIdentifierDoubleQuoted,
+            (false, _) => State::DoctypeSystemIdentifierSingleQuoted,
+        };
+
+        if public {
+            self.current_doctype.public_id = Some(String::new());
+        } else {
+            self.current_doctype.system_id = Some(String::new());
+        }
+    }
+
+    fn after_doctype_keyword(
+        &mut self,
+        c: Option<char>,
+        public: bool,
+        before_identifier: State,
+        missing_whitespace_error: &'static str,
+    ) {
+        match c {
+            Some(c) if is_whitespace(c) => self.state = before_identifier,
+            Some(quote @ ('"' | '\'')) => {
+                self.parse_error(missing_whitespace_error);
+                self.start_doctype_identifier(quote, public);
+            }
+            c => self.before_doctype_identifier(c, public),
+        }
+    }
+
+    fn before_doctype_identifier(&mut self, c: Option<char>, public: bool) {
+        match c {
+            Some(c) if is_whitespace(c) => {}
+            Some(quote @ ('"' | '\'')) => self.start_doctype_identifier(quote, public),
+            Some('>') => {
+                self.parse_error(if public {
+                    "missing-doctype-public-identifier"
+                } else {
+                    "missing-doctype-system-identifier"
+                });
+                self.current_doctype.force_quirks = true;
+                self.state = State::Data;
+                self.emit_current_doctype();
+            }
+            Some(_) => {
+                self.parse_error(if public {
+                    "missing-quote-before-doctype-public-identifier"
+                } else {
+                    "missing-quote-before-doctype-system-identifier"
+                });
+                self.current_doctype.force_quirks = true;
+                self.reconsume_in(State::BogusDoctype);
+            }
+            None => self.emit_doctype_at_eof(),
+        }
+    }
+
+    fn doctype_identifier_quoted(&mut self, c: Option<char>, quote: char, public: bool) {
+        let identifier = if public {
+            &mut self.current_doctype.public_id
+        } else {
+            &mut self.current_doctype.system_id
+        };
+
+        match c {
+            Some(c) if c == quote => {
+                self.state = if public {
+                    State::AfterDoctypePublicIdentifier
+                } else {
+                    State::AfterDoctypeSystemIdentifier
+                };
+            }
+            Some('\0') => {
+                identifier
+                    .get_or_insert_with(String::new)
+                    .push(REPLACEMENT_CHARACTER);
+                self.parse_error("unexpected-null-character");
+            }
+            Some('>') => {
+                self.parse_error(if public {
+                    "abrupt-doctype-public-identifier"
+                } else {
+                    "abrupt-doctype-system-identifier"
+                });
+                self.current_doctype.force_quirks = true;
+                self.state = State::Data;
+                self.emit_current_doctype();
+            }
+            Some(c) => identifier.get_or_insert_with(String::new).push(c),
+            None => self.emit_doctype_at_eof(),
+        }
+    }
+
+    fn start_character_reference(&mut self, return_state: State) {
+        self.return_state = return_state;
+        self.temporary_buffer.clear();
+        self.temporary_buffer.push('&');
+        self.state = State::CharacterReference;
+    }
+
+    fn is_consuming_attribute_value(&self) -> bool {
+        matches!(
+            self.return_state,
+            State::AttributeValueDoubleQuoted
+                | State::AttributeValueSingleQuoted
+                | State::AttributeValueUnquoted
+        )
+    }
+
+    // Os caracteres consumidos vão para o valor do atributo ou para o texto,
+    // dependendo de onde a referência começou
+    fn flush_character_reference(&mut self) {
+        let buffer = mem::take(&mut self.temporary_buffer);
+
+        if self.is_consuming_attribute_value() {
+            self.attribute_value().push_str(&buffer);
+        } else {
+            self.emit_str(&buffer);
+        }
+    }
+
+    // Consome o maior nome da tabela de referências nomeadas que prefixa a entrada
+    fn named_character_reference(&mut self) -> bool {
+        let rest = self.scanner.rest();
+        let mut longest_match = None;
+        let mut exhausted_input = true;
+
+        for (index, c) in rest.char_indices() {
+            if !c.is_ascii_alphanumeric() && c != ';' {
+                exhausted_input = false;
+                break;
+            }
+
+            let candidate = &rest[..=index];
+            let position =
+                NAMED_CHARACTER_REFERENCES.partition_point(|&(name, _)| name < candidate);
+
+            match NAMED_CHARACTER_REFERENCES.get(position) {
+                Some(&(name, value)) if name.starts_with(candidate) => {
+                    if name == candidate {
+                        longest_match = Some((candidate.len(), value));
+                    }
+                }
+                _ => {
+                    exhausted_input = false;
+                    break;
+                }
+            }
+
+            if c == ';' {
+                exhausted_input = false;
+                break;
+            }
+        }
+
+        // Um nome mais longo ainda pode chegar com o próximo trecho da entrada
+        if exhausted_input && !self.input_complete {
+            return false;
+        }
+
+        let (length, value) = match longest_match {
+            Some(found) => found,
+            None => {
+                self.flush_character_reference();
+                self.state = State::AmbiguousAmpersand;
+                return true;
+            }
+        };
+
+        let name = &rest[..length];
+        let ends_with_semicolon = name.ends_with(';');
+        let next = rest[length..].chars().next();
+
+        self.temporary_buffer.push_str(name);
+        self.scanner.advance_by(length);
+
+        if self.is_consuming_attribute_value()
+            && !ends_with_semicolon
+            && next.is_some_and(|c| c == '=' || c.is_ascii_alphanumeric())
+        {
+            // Por razões históricas, `&amp=` em atributos não é decodificado
+            self.flush_character_reference();
+        } else {
+            if !ends_with_semicolon {
+                self.parse_error("missing-semicolon-after-character-reference");
+            }
+
+            self.temporary_buffer = value.to_owned();
+            self.flush_character_reference();
+        }
+
+        self.state = self.return_state;
+
+        true
+    }
+
+    fn absence_of_digits(&mut self) {
+        self.parse_error("absence-of-digits-in-numeric-character-reference");
+        self.flush_character_reference();
+        self.reconsume_in(self.return_state);
+    }
+
+    fn numeric_character_reference_digit(&mut self, c: Option<char>, radix: u32) {
+        match c.and_then(|c| c.to_digit(radix)) {
+            Some(digit) => {
+                // Satura acima do máximo do Unicode para evitar overflow
+                self.character_reference_code = self
+                    .character_reference_code
+                    .saturating_mul(radix)
+                    .saturating_add(digit)
+                    .min(0x110000);
+            }
+            None if c == Some(';') => self.state = State::NumericCharacterReferenceEnd,
+            None => {
+                self.parse_error("missing-semicolon-after-character-reference");
+                self.reconsume_in(State::NumericCharacterReferenceEnd);
+            }
+        }
+    }
+
+    fn numeric_character_reference_end(&mut self) {
+        let code = self.character_reference_code;
+
+        let c = match code {
+            0 => {
+                self.parse_error("null-character-reference");
+                REPLACEMENT_CHARACTER
+            }
+            0x110000.. => {
+                self.parse_error("character-reference-outside-unicode-range");
+                REPLACEMENT_CHARACTER
+            }
+            0xD800..=0xDFFF => {
+                self.parse_error("surrogate-character-reference");
+                REPLACEMENT_CHARACTER
+            }
+            _ => {
+                if (0xFDD0..=0xFDEF).contains(&code) || code & 0xFFFE == 0xFFFE {
+                    self.parse_error("noncharacter-character-reference");
+                }
+
+                if code == 0x0D
+                    || ((code <= 0x1F || (0x7F..=0x9F).contains(&code))
+                        && !matches!(code, 0x09 | 0x0A | 0x0C | 0x20))
+                {
+                    self.parse_error("control-character-reference");
+                }
+
+                WINDOWS_1252_C1
+                    .iter()
+                    .find(|&&(from, _)| from == code)
+                    .map(|&(_, to)| to)
+                    .or_else(|| char::from_u32(code))
+                    .unwrap_or(REPLACEMENT_CHARACTER)
+            }
+        };
+
+        self.temporary_buffer.clear();
+        self.temporary_buffer.push(c);
+        self.flush_character_reference();
+        self.state = self.return_state;
+    }
+}
+
+impl Iterator for Tokenizer {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        self.next_token()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(input: &str) -> Vec<Token> {
+        Tokenizer::new(input.to_owned()).collect()
+    }
+
+    fn tag(name: &str, attributes: &[(&str, &str)]) -> Tag {
+        Tag {
+            name: name.to_owned(),
+            self_closing: false,
+            attributes: attributes
+                .iter()
+                .map(|&(name, value)| Attribute {
+                    name: name.to_owned(),
+                    value: value.to_owned(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn tags_and_attributes() {
+        assert_eq!(
+            tokens("<A href=x Class='a b' id=\"c\">t</a>"),
+            vec![
+                Token::StartTag(tag("a", &[("href", "x"), ("class", "a b"), ("id", "c")])),
+                Token::Characters("t".to_owned()),
+                Token::EndTag(tag("a", &[])),
+                Token::Eof,
+            ]
+        );
+
+        let mut tokenizer = Tokenizer::new("<p a=1 a=2>".to_owned());
+        assert_eq!(
+            tokenizer.next_token(),
+            Some(Token::StartTag(tag("p", &[("a", "1")])))
+        );
+        assert_eq!(tokenizer.errors()[0].kind, "duplicate-attribute");
+    }
+
+    #[test]
+    fn character_references() {
+        assert_eq!(
+            tokens("&amp;&lt;&#65;&#x42;&notit; &bogus;"),
+            vec![Token::Characters("&<AB¬it; &bogus;".to_owned()), Token::Eof]
+        );
+        assert_eq!(
+            tokens("<a title='&amp;&notit'>"),
+            vec![
+                Token::StartTag(tag("a", &[("title", "&&notit")])),
+                Token::Eof
+            ]
+        );
+    }
+
+    #[test]
+    fn comments_and_doctype() {
+        assert_eq!(
+            tokens("<!DOCTYPE html><!-- c --><?x>"),
+            vec![
+                Token::Doctype(Doctype {
+                    name: Some("html".to_owned()),
+                    ..Doctype::default()
+                }),
+                Token::Comment(" c ".to_owned()),
+                Token::Comment("?x".to_owned()),
+                Token::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn text_states() {
+        let mut tokenizer = Tokenizer::new("a<b>&amp;</style>".to_owned());
+        tokenizer.set_state(State::Rawtext);
+        tokenizer.last_start_tag_name = Some("style".to_owned());
+
+        assert_eq!(
+            tokenizer.next_token(),
+            Some(Token::Characters("a<b>&amp;".to_owned()))
+        );
+        assert_eq!(
+            tokenizer.next_token(),
+            Some(Token::EndTag(tag("style", &[])))
+        );
+    }
+
+    #[test]
+    fn spans_and_newlines() {
+        let mut tokenizer = Tokenizer::new("a\r\n<p>".to_owned());
+
+        assert_eq!(
+            tokenizer.next_token(),
+            Some(Token::Characters("a\n".to_owned()))
+        );
+        assert_eq!(tokenizer.next_token(), Some(Token::StartTag(tag("p", &[]))));
+
+        let span = tokenizer.token_span();
+        assert_eq!((span.start.line, span.start.column), (2, 1));
+        assert_eq!((span.end.offset, span.end.column), (6, 4));
+    }
+
+    #[test]
+    fn incremental_input() {
+        let mut tokenizer = Tokenizer::incremental();
+        tokenizer.feed("<di");
+        assert_eq!(tokenizer.next_token(), None);
+
+        tokenizer.feed("v>x");
+        tokenizer.finish();
+        assert_eq!(
+            tokenizer.collect::<Vec<_>>(),
+            vec![
+                Token::StartTag(tag("div", &[])),
+                Token::Characters("x".to_owned()),
+                Token::Eof
+            ]
+        );
+    }
+}
@@ -0,0 +1,3273 @@
+use std::mem;
+
+use super::errors;
+use super::tokenizer::{Attribute, Doctype, State, Tag, Token, Tokenizer};
+use crate::diagnostics::{Diagnostic, Position, Span};
+use crate::dom::{Comment, Document, DocumentType, Element, Node, QuirksMode, Text, VOID_ELEMENTS};
+
+// Construção da árvore seguindo os modos de inserção da especificação WHATWG
+// (https://html.spec.whatwg.org/multipage/parsing.html#tree-construction)
+//
+// Diferente da especificação, o conteúdo de `<template>` não vai para um
+// DocumentFragment separado: fica como filhos do próprio elemento.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InsertionMode {
+    Initial,
+    BeforeHtml,
+    BeforeHead,
+    InHead,
+    InHeadNoscript,
+    AfterHead,
+    InBody,
+    Text,
+    InTable,
+    InTableText,
+    InCaption,
+    InColumnGroup,
+    InTableBody,
+    InRow,
+    InCell,
+    InSelect,
+    InSelectInTable,
+    InTemplate,
+    AfterBody,
+    InFrameset,
+    AfterFrameset,
+    AfterAfterBody,
+    AfterAfterFrameset,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Namespace {
+    Html,
+    Svg,
+    MathMl,
+}
+
+#[derive(Debug)]
+enum NodeData {
+    Document,
+    Doctype(Doctype),
+    Comment(String),
+    Text(String),
+    Element { tag: Tag, namespace: Namespace },
+}
+
+#[derive(Debug)]
+struct TreeNode {
+    data: NodeData,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    // Distância até o documento, mantida por `insert_at`
+    depth: usize,
+    // Elementos vão da tag de abertura até a de fechamento (ou até onde
+    // foram fechados implicitamente); textos cobrem todos os seus trechos
+    span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FormattingEntry {
+    Marker,
+    Element(usize),
+}
+
+const DOCUMENT: usize = 0;
+
+// Profundidade máxima da árvore. Nós mais profundos são inseridos como
+// irmãos, para que as etapas recursivas (estilo, layout, pintura) não
+// estourem a pilha com entradas muito aninhadas.
+const MAX_TREE_DEPTH: usize = 512;
+
+const SPECIAL_ELEMENTS: &[&str] = &[
+    "address",
+    "applet",
+    "area",
+    "article",
+    "aside",
+    "base",
+    "basefont",
+    "bgsound",
+    "blockquote",
+    "body",
+    "br",
+    "button",
+    "caption",
+    "center",
+    "col",
+    "colgroup",
+    "dd",
+    "details",
+    "dir",
+    "div",
+    "dl",
+    "dt",
+    "embed",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "frame",
+    "frameset",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "head",
+    "header",
+    "hgroup",
+    "hr",
+    "html",
+    "iframe",
+    "img",
+    "input",
+    "keygen",
+    "li",
+    "link",
+    "listing",
+    "main",
+    "marquee",
+    "menu",
+    "meta",
+    "nav",
+    "noembed",
+    "noframes",
+    "noscript",
+    "object",
+    "ol",
+    "p",
+    "param",
+    "plaintext",
+    "pre",
+    "script",
+    "search",
+    "section",
+    "select",
+    "source",
+    "style",
+    "summary",
+    "table",
+    "tbody",
+    "td",
+    "template",
+    "textarea",
+    "tfoot",
+    "th",
+    "thead",
+    "title",
+    "tr",
+    "track",
+    "ul",
+    "wbr",
+    "xmp",
+];
+
+const FORMATTING_ELEMENTS: &[&str] = &[
+    "a", "b", "big", "code", "em", "font", "i", "nobr", "s", "small", "strike", "strong", "tt", "u",
+];
+
+const HEADINGS: &[&str] = &["h1", "h2", "h3", "h4", "h5", "h6"];
+
+const IMPLIED_END_TAGS: &[&str] = &[
+    "dd", "dt", "li", "optgroup", "option", "p", "rb", "rp", "rt", "rtc",
+];
+
+const THOROUGHLY_IMPLIED_END_TAGS: &[&str] = &[
+    "caption", "colgroup", "dd", "dt", "li", "optgroup", "option", "p", "rb", "rp", "rt", "rtc",
+    "tbody", "td", "tfoot", "th", "thead", "tr",
+];
+
+const DEFAULT_SCOPE: &[&str] = &[
+    "applet", "caption", "html", "table", "td", "th", "marquee", "object", "template",
+];
+
+const TABLE_SCOPE: &[&str] = &["html", "table", "template"];
+
+// Nomes de elementos e atributos SVG e MathML que têm maiúsculas. O tokenizer
+// converte todos os nomes para minúsculas, então eles são ajustados ao
+// inserir elementos estrangeiros
+// (https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-inforeign)
+const SVG_TAG_NAMES: &[(&str, &str)] = &[
+    ("altglyph", "altGlyph"),
+    ("altglyphdef", "altGlyphDef"),
+    ("altglyphitem", "altGlyphItem"),
+    ("animatecolor", "animateColor"),
+    ("animatemotion", "animateMotion"),
+    ("animatetransform", "animateTransform"),
+    ("clippath", "clipPath"),
+    ("feblend", "feBlend"),
+    ("fecolormatrix", "feColorMatrix"),
+    ("fecomponenttransfer", "feComponentTransfer"),
+    ("fecomposite", "feComposite"),
+    ("feconvolvematrix", "feConvolveMatrix"),
+    ("fediffuselighting", "feDiffuseLighting"),
+    ("fedisplacementmap", "feDisplacementMap"),
+    ("fedistantlight", "feDistantLight"),
+    ("fedropshadow", "feDropShadow"),
+    ("feflood", "feFlood"),
+    ("fefunca", "feFuncA"),
+    ("fefuncb", "feFuncB"),
+    ("fefuncg", "feFuncG"),
+    ("fefuncr", "feFuncR"),
+    ("fegaussianblur", "feGaussianBlur"),
+    ("feimage", "feImage"),
+    ("femerge", "feMerge"),
+    ("femergenode", "feMergeNode"),
+    ("femorphology", "feMorphology"),
+    ("feoffset", "feOffset"),
+    ("fepointlight", "fePointLight"),
+    ("fespecularlighting", "feSpecularLighting"),
+    ("fespotlight", "feSpotLight"),
+    ("fetile", "feTile"),
+    ("feturbulence", "feTurbulence"),
+    ("foreignobject", "foreignObject"),
+    ("glyphref", "glyphRef"),
+    ("lineargradient", "linearGradient"),
+    ("radialgradient", "radialGradient"),
+    ("textpath", "textPath"),
+];
+
+const SVG_ATTRIBUTE_NAMES: &[(&str, &str)] = &[
+    ("attributename", "attributeName"),
+    ("attributetype", "attributeType"),
+    ("basefrequency", "baseFrequency"),
+    ("baseprofile", "baseProfile"),
+    ("calcmode", "calcMode"),
+    ("clippathunits", "clipPathUnits"),
+    ("diffuseconstant", "diffuseConstant"),
+    ("edgemode", "edgeMode"),
+    ("filterunits", "filterUnits"),
+    ("glyphref", "glyphRef"),
+    ("gradienttransform", "gradientTransform"),
+    ("gradientunits", "gradientUnits"),
+    ("kernelmatrix", "kernelMatrix"),
+    ("kernelunitlength", "kernelUnitLength"),
+    ("keypoints", "keyPoints"),
+    ("keysplines", "keySplines"),
+    ("keytimes", "keyTimes"),
+    ("lengthadjust", "lengthAdjust"),
+    ("limitingconeangle", "limitingConeAngle"),
+    ("markerheight", "markerHeight"),
+    ("markerunits", "markerUnits"),
+    ("markerwidth", "markerWidth"),
+    ("maskcontentunits", "maskContentUnits"),
+    ("maskunits", "maskUnits"),
+    ("numoctaves", "numOctaves"),
+    ("pathlength", "pathLength"),
+    ("patterncontentunits", "patternContentUnits"),
+    ("patterntransform", "patternTransform"),
+    ("patternunits", "patternUnits"),
+    ("pointsatx", "pointsAtX"),
+    ("pointsaty", "pointsAtY"),
+    ("pointsatz", "pointsAtZ"),
+    ("preservealpha", "preserveAlpha"),
+    ("preserveaspectratio", "preserveAspectRatio"),
+    ("primitiveunits", "primitiveUnits"),
+    ("refx", "refX"),
+    ("refy", "refY"),
+    ("repeatcount", "repeatCount"),
+    ("repeatdur", "repeatDur"),
+    ("requiredextensions", "requiredExtensions"),
+    ("requiredfeatures", "requiredFeatures"),
+    ("specularconstant", "specularConstant"),
+    ("specularexponent", "specularExponent"),
+    ("spreadmethod", "spreadMethod"),
+    ("startoffset", "startOffset"),
+    ("stddeviation", "stdDeviation"),
+    ("stitchtiles", "stitchTiles"),
+    ("surfacescale", "surfaceScale"),
+    ("systemlanguage", "systemLanguage"),
+    ("tablevalues", "tableValues"),
+    ("targetx", "targetX"),
+    ("targety", "targetY"),
+    ("textlength", "textLength"),
+    ("viewbox", "viewBox"),
+    ("viewtarget", "viewTarget"),
+    ("xchannelselector", "xChannelSelector"),
+    ("ychannelselector", "yChannelSelector"),
+    ("zoomandpan", "zoomAndPan"),
+];
+
+const MATHML_ATTRIBUTE_NAMES: &[(&str, &str)] = &[("definitionurl", "definitionURL")];
+
+fn adjusted_name(names: &[(&str, &'static str)], name: &str) -> Option<&'static str> {
+    names
+        .iter()
+        .find(|&&(lowercase, _)| lowercase == name)
+        .map(|&(_, adjusted)| adjusted)
+}
+
+fn adjust_foreign_tag(tag: &mut Tag, namespace: Namespace) {
+    let attribute_names = match namespace {
+        Namespace::Html => return,
+        Namespace::Svg => SVG_ATTRIBUTE_NAMES,
+        Namespace::MathMl => MATHML_ATTRIBUTE_NAMES,
+    };
+
+    if namespace == Namespace::Svg {
+        if let Some(name) = adjusted_name(SVG_TAG_NAMES, &tag.name) {
+            tag.name = name.to_owned();
+        }
+    }
+
+    for attribute in &mut tag.attributes {
+        if let Some(name) = adjusted_name(attribute_names, &attribute.name) {
+            attribute.name = name.to_owned();
+        }
+    }
+}
+
+fn is_whitespace(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\x0C' | '\r' | ' ')
+}
+
+fn split_leading_whitespace(text: &str) -> (&str, &str) {
+    let index = text.find(|c| !is_whitespace(c)).unwrap_or(text.len());
+
+    text.split_at(index)
+}
+
+// Prefixos de identificadores públicos que colocam o documento em modo quirks
+// (https://html.spec.whatwg.org/multipage/parsing.html#the-initial-insertion-mode)
+const QUIRKS_PUBLIC_ID_PREFIXES: &[&str] = &[
+    "+//silmaril//dtd html pro v0r11 19970101//",
+    "-//as//dtd html 3.0 aswedit + extensions//",
+    "-//advasoft ltd//dtd html 3.0 aswedit + extensions//",
+    "-//ietf//dtd html 2.0 level 1//",
+    "-//ietf//dtd html 2.0 level 2//",
+    "-//ietf//dtd html 2.0 strict level 1//",
+    "-//ietf//dtd html 2.0 strict level 2//",
+    "-//ietf//dtd html 2.0 strict//",
+    "-//ietf//dtd html 2.0//",
+    "-//ietf//dtd html 2.1e//",
+    "-//ietf//dtd html 3.0//",
+    "-//ietf//dtd html 3.2 final//",
+    "-//ietf//dtd html 3.2//",
+    "-//ietf//dtd html 3//",
+    "-//ietf//dtd html level 0//",
+    "-//ietf//dtd html level 1//",
+    "-//ietf//dtd html level 2//",
+    "-//ietf//dtd html level 3//",
+    "-//ietf//dtd html strict level 0//",
+    "-//ietf//dtd html strict level 1//",
+    "-//ietf//dtd html strict level 2//",
+    "-//ietf//dtd html strict level 3//",
+    "-//ietf//dtd html strict//",
+    "-//ietf//dtd html//",
+    "-//metrius//dtd metrius presentational//",
+    "-//microsoft//dtd internet explorer 2.0 html strict//",
+    "-//microsoft//dtd internet explorer 2.0 html//",
+    "-//microsoft//dtd internet explorer 2.0 tables//",
+    "-//microsoft//dtd internet explorer 3.0 html strict//",
+    "-//microsoft//dtd internet explorer 3.0 html//",
+    "-//microsoft//dtd internet explorer 3.0 tables//",
+    "-//netscape comm. corp.//dtd html//",
+    "-//netscape comm. corp.//dtd strict html//",
+    "-//o'reilly and associates//dtd html 2.0//",
+    "-//o'reilly and associates//dtd html extended 1.0//",
+    "-//o'reilly and associates//dtd html extended relaxed 1.0//",
+    "-//sq//dtd html 2.0 hotmetal + extensions//",
+    "-//softquad software//dtd hotmetal pro 6.0::19990601::extensions to html 4.0//",
+    "-//softquad//dtd hotmetal pro 4.0::19971010::extensions to html 4.0//",
+    "-//spyglass//dtd html 2.0 extended//",
+    "-//sun microsystems corp.//dtd hotjava html//",
+    "-//sun microsystems corp.//dtd hotjava strict html//",
+    "-//w3c//dtd html 3 1995-03-24//",
+    "-//w3c//dtd html 3.2 draft//",
+    "-//w3c//dtd html 3.2 final//",
+    "-//w3c//dtd html 3.2//",
+    "-//w3c//dtd html 3.2s draft//",
+    "-//w3c//dtd html 4.0 frameset//",
+    "-//w3c//dtd html 4.0 transitional//",
+    "-//w3c//dtd html experimental 19960712//",
+    "-//w3c//dtd html experimental 970421//",
+    "-//w3c//dtd w3 html//",
+    "-//w3o//dtd w3 html 3.0//",
+    "-//webtechs//dtd mozilla html 2.0//",
+    "-//webtechs//dtd mozilla html//",
+];
+
+const QUIRKS_PUBLIC_IDS: &[&str] = &[
+    "-//w3o//dtd w3 html strict 3.0//en//",
+    "-/w3c/dtd html 4.0 transitional/en",
+    "html",
+];
+
+const QUIRKS_SYSTEM_ID: &str = "http://www.ibm.com/data/dtd/v11/ibmxhtml1-transitional.dtd";
+
+// Em modo quirks sem identificador de sistema, ou limited-quirks com ele
+const HTML_401_PUBLIC_ID_PREFIXES: &[&str] = &[
+    "-//w3c//dtd html 4.01 frameset//",
+    "-//w3c//dtd html 4.01 transitional//",
+];
+
+const LIMITED_QUIRKS_PUBLIC_ID_PREFIXES: &[&str] = &[
+    "-//w3c//dtd xhtml 1.0 frameset//",
+    "-//w3c//dtd xhtml 1.0 transitional//",
+];
+
+fn quirks_mode(doctype: &Doctype) -> QuirksMode {
+    let public_id = doctype
+        .public_id
+        .as_deref()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    let system_id = doctype.system_id.as_deref().map(str::to_ascii_lowercase);
+    let public_id_starts_with =
+        |prefixes: &[&str]| prefixes.iter().any(|prefix| public_id.starts_with(prefix));
+
+    if doctype.force_quirks
+        || doctype.name.as_deref() != Some("html")
+        || QUIRKS_PUBLIC_IDS.contains(&public_id.as_str())
+        || system_id.as_deref() == Some(QUIRKS_SYSTEM_ID)
+        || public_id_starts_with(QUIRKS_PUBLIC_ID_PREFIXES)
+        || (system_id.is_none() && public_id_starts_with(HTML_401_PUBLIC_ID_PREFIXES))
+    {
+        QuirksMode::Quirks
+    } else if public_id_starts_with(LIMITED_QUIRKS_PUBLIC_ID_PREFIXES)
+        || (system_id.is_some() && public_id_starts_with(HTML_401_PUBLIC_ID_PREFIXES))
+    {
+        QuirksMode::LimitedQuirks
+    } else {
+        QuirksMode::NoQuirks
+    }
+}
+
+fn tag_named(name: &str) -> Tag {
+    Tag {
+        name: name.to_owned(),
+        ..Default::default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Scope {
+    Default,
+    ListItem,
+    Button,
+    Table,
+    Select,
+}
+
+pub struct TreeBuilder {
+    nodes: Vec<TreeNode>,
+    mode: InsertionMode,
+    original_mode: InsertionMode,
+    // Pilha de modos de inserção de `<template>`
+    template_modes: Vec<InsertionMode>,
+    open_elements: Vec<usize>,
+    active_formatting: Vec<FormattingEntry>,
+    head: Option<usize>,
+    form: Option<usize>,
+    frameset_ok: bool,
+    quirks_mode: QuirksMode,
+    foster_parenting: bool,
+    ignore_next_line_feed: bool,
+    pending_table_text: String,
+    stopped: bool,
+    // Span do token sendo processado
+    span: Span,
+    // Nomes da tag sendo processada, para distinguir elementos criados
+    // por ela dos implícitos e decidir onde termina o span dos elementos
+    // retirados da pilha
+    start_tag_name: Option<String>,
+    end_tag_name: Option<String>,
+    errors: Vec<Diagnostic>,
+}
+
+impl Default for TreeBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TreeBuilder {
+    pub fn new() -> TreeBuilder {
+        // Início da entrada, com linha e coluna a partir de 1 como no tokenizer
+        let start = Position {
+            offset: 0,
+            line: 1,
+            column: 1,
+        };
+        let span = Span { start, end: start };
+
+        TreeBuilder {
+            nodes: vec![TreeNode {
+                data: NodeData::Document,
+                parent: None,
+                children: Vec::new(),
+                depth: 0,
+                span,
+            }],
+            mode: InsertionMode::Initial,
+            original_mode: InsertionMode::Initial,
+            template_modes: Vec::new(),
+            open_elements: Vec::new(),
+            active_formatting: Vec::new(),
+            head: None,
+            form: None,
+            frameset_ok: true,
+            quirks_mode: QuirksMode::NoQuirks,
+            foster_parenting: false,
+            ignore_next_line_feed: false,
+            pending_table_text: String::new(),
+            stopped: false,
+            span,
+            start_tag_name: None,
+            end_tag_name: None,
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn errors(&self) -> &[Diagnostic] {
+        &self.errors
+    }
+
+    pub fn process_token(&mut self, token: Token, tokenizer: &mut Tokenizer) {
+        if self.stopped {
+            return;
+        }
+
+        self.span = tokenizer.token_span();
+        (self.start_tag_name, self.end_tag_name) = match token {
+            Token::StartTag(ref tag) => (Some(tag.name.clone()), None),
+            Token::EndTag(ref tag) => (None, Some(tag.name.clone())),
+            _ => (None, None),
+        };
+
+        let token = match token {
+            Token::Characters(text) if mem::take(&mut self.ignore_next_line_feed) => {
+                match text.strip_prefix('\n') {
+                    Some("") => return,
+                    Some(rest) => Token::Characters(rest.to_owned()),
+                    None => Token::Characters(text),
+                }
+            }
+            token => {
+                self.ignore_next_line_feed = false;
+                token
+            }
+        };
+
+        if self.should_process_as_foreign_content(&token) {
+            self.process_foreign_content(token, tokenizer);
+        } else {
+            // Em HTML, `/>` só é permitido em elementos void (e em `svg`/`math`,
+            // que são tratados como conteúdo estrangeiro)
+            let unacknowledged_self_closing = matches!(
+                token,
+                Token::StartTag(ref tag) if tag.self_closing
+                    && !VOID_ELEMENTS.contains(&tag.name.as_str())
+                    && tag.name != "svg"
+                    && tag.name != "math"
+            );
+
+            self.process_in_mode(self.mode, token, tokenizer);
+
+            if unacknowledged_self_closing {
+                self.parse_error("non-void-html-element-start-tag-with-trailing-solidus");
+            }
+        }
+
+        tokenizer.set_allow_cdata(
+            self.adjusted_current_node()
+                .is_some_and(|node| self.namespace(node) != Namespace::Html),
+        );
+    }
+
+    // Converte a árvore construída para `dom::Node`, retornando o documento
+    pub fn into_document(self) -> Node {
+        self.document()
+    }
+
+    // Como `into_document`, mas sem consumir o builder, para obter a árvore
+    // parcial durante o parsing incremental
+    pub fn document(&self) -> Node {
+        // Construção iterativa: cada entrada da pilha guarda um nó, o índice
+        // do próximo filho e os filhos já convertidos
+        let mut stack = vec![(DOCUMENT, 0, Vec::new())];
+
+        loop {
+            let depth = stack.len();
+            let (id, next, built) = stack.last_mut().unwrap();
+
+            let Some(&child) = self.nodes[*id].children.get(*next) else {
+                let (id, _, children) = stack.pop().unwrap();
+                let node = self.build_node(id, children);
+
+                match stack.last_mut() {
+                    Some((_, _, built)) => built.push(node),
+                    None => return node,
+                }
+
+                continue;
+            };
+
+            *next += 1;
+
+            if depth == MAX_TREE_DEPTH {
+                // Nós movidos pelo adoption agency podem ficar abaixo do
+                // limite junto com a sua subárvore. Eles entram achatados,
+                // em ordem de documento, como irmãos.
+                let mut pending = vec![child];
+
+                while let Some(node) = pending.pop() {
+                    built.push(self.build_node(node, Vec::new()));
+                    pending.extend(self.nodes[node].children.iter().rev());
+                }
+            } else if self.nodes[child].children.is_empty() {
+                built.push(self.build_node(child, Vec::new()));
+            } else {
+                stack.push((child, 0, Vec::new()));
+            }
+        }
+    }
+
+    fn build_node(&self, id: usize, children: Vec<Node>) -> Node {
+        let span = self.nodes[id].span;
+
+        match &self.nodes[id].data {
+            NodeData::Document => Node::Document(Document {
+                children,
+                quirks_mode: self.quirks_mode,
+                // O documento cobre toda a entrada, que termina no token EOF
+                span: Span {
+                    start: span.start,
+                    end: self.span.end,
+                },
+            }),
+            NodeData::Doctype(doctype) => Node::DocumentType(DocumentType {
+                name: doctype.name.clone().unwrap_or_default(),
+                public_id: doctype.public_id.clone().unwrap_or_default(),
+                system_id: doctype.system_id.clone().unwrap_or_default(),
+                span,
+            }),
+            NodeData::Comment(data) => Node::Comment(Comment {
+                data: data.clone(),
+                span,
+            }),
+            NodeData::Text(text) => Node::Text(Text {
+                data: text.clone(),
+                span,
+            }),
+            NodeData::Element { tag, .. } => {
+                let attributes = tag
+                    .attributes
+                    .iter()
+                    .map(|attribute| (attribute.name.clone(), attribute.value.clone()))
+                    .collect();
+
+                Node::Element(Element {
+                    tag_name: tag.name.clone(),
+                    attributes,
+                    children,
+                    span,
+                })
+            }
+        }
+    }
+
+    fn parse_error(&mut self, kind: &'static str) {
+        self.errors.push(Diagnostic {
+            kind,
+            message: errors::message(kind).to_owned(),
+            span: self.span,
+        });
+    }
+
+    // Acesso aos nós
+
+    fn tag(&self, id: usize) -> Option<&Tag> {
+        match &self.nodes[id].data {
+            NodeData::Element { tag, .. } => Some(tag),
+            _ => None,
+        }
+    }
+
+    fn name(&self, id: usize) -> &str {
+        self.tag(id).map_or("", |tag| tag.name.as_str())
+    }
+
+    fn namespace(&self, id: usize) -> Namespace {
+        match self.nodes[id].data {
+            NodeData::Element { namespace, .. } => namespace,
+            _ => Namespace::Html,
+        }
+    }
+
+    fn is_html(&self, id: usize, name: &str) -> bool {
+        self.namespace(id) == Namespace::Html && self.name(id) == name
+    }
+
+    fn is_html_one_of(&self, id: usize, names: &[&str]) -> bool {
+        self.namespace(id) == Namespace::Html && names.contains(&self.name(id))
+    }
+
+    fn is_special(&self, id: usize) -> bool {
+        match self.namespace(id) {
+            Namespace::Html => SPECIAL_ELEMENTS.contains(&self.name(id)),
+            Namespace::MathMl => matches!(
+                self.name(id),
+                "mi" | "mo" | "mn" | "ms" | "mtext" | "annotation-xml"
+            ),
+            Namespace::Svg => matches!(self.name(id), "foreignObject" | "desc" | "title"),
+        }
+    }
+
+    fn is_mathml_text_integration_point(&self, id: usize) -> bool {
+        self.namespace(id) == Namespace::MathMl
+            && matches!(self.name(id), "mi" | "mo" | "mn" | "ms" | "mtext")
+    }
+
+    fn is_html_integration_point(&self, id: usize) -> bool {
+        match self.namespace(id) {
+            Namespace::MathMl if self.name(id) == "annotation-xml" => self
+                .tag(id)
+                .and_then(|tag| {
+                    tag.attributes
+                        .iter()
+                        .find(|attribute| attribute.name == "encoding")
+                })
+                .is_some_and(|encoding| {
+                    encoding.value.eq_ignore_ascii_case("text/html")
+                        || encoding.value.eq_ignore_ascii_case("application/xhtml+xml")
+                }),
+            Namespace::Svg => matches!(self.name(id), "foreignObject" | "desc" | "title"),
+            _ => false,
+        }
+    }
+
+    fn current_node(&self) -> Option<usize> {
+        self.open_elements.last().copied()
+    }
+
+    fn adjusted_current_node(&self) -> Option<usize> {
+        self.current_node()
+    }
+
+    fn current_is(&self, name: &str) -> bool {
+        self.current_node()
+            .is_some_and(|node| self.is_html(node, name))
+    }
+
+    fn current_is_one_of(&self, names: &[&str]) -> bool {
+        self.current_node()
+            .is_some_and(|node| self.is_html_one_of(node, names))
+    }
+
+    // Manipulação da árvore
+
+    fn create_node(&mut self, data: NodeData) -> usize {
+        self.nodes.push(TreeNode {
+            data,
+            parent: None,
+            children: Vec::new(),
+            depth: 0,
+            span: self.span,
+        });
+
+        self.nodes.len() - 1
+    }
+
+    fn detach(&mut self, id: usize) {
+        if let Some(parent) = self.nodes[id].parent.take() {
+            self.nodes[parent].children.retain(|&child| child != id);
+        }
+    }
+
+    // Toda inserção passa por aqui, inclusive as do adoption agency, para
+    // que a profundidade máxima valha para a árvore inteira
+    fn insert_at(&mut self, place: (usize, Option<usize>), id: usize) {
+        let (parent, before) = self.capped_insertion_place(place);
+
+        self.detach(id);
+        self.nodes[id].parent = Some(parent);
+
+        let children = &mut self.nodes[parent].children;
+        let position = before
+            .and_then(|before| children.iter().position(|&child| child == before))
+            .unwrap_or(children.len());
+
+        children.insert(position, id);
+
+        // Nós movidos levam a subárvore junto, então a profundidade é
+        // atualizada em todos os descendentes
+        let mut pending = vec![(id, self.nodes[parent].depth + 1)];
+
+        while let Some((node, depth)) = pending.pop() {
+            if self.nodes[node].depth == depth {
+                continue;
+            }
+
+            self.nodes[node].depth = depth;
+            pending.extend(
+                self.nodes[node]
+                    .children
+                    .iter()
+                    .map(|&child| (child, depth + 1)),
+            );
+        }
+    }
+
+    // Filhos de um nó que já está na profundidade máxima vão para o fim do
+    // ancestral no nível anterior, ficando como irmãos
+    fn capped_insertion_place(
+        &self,
+        (parent, before): (usize, Option<usize>),
+    ) -> (usize, Option<usize>) {
+        if self.nodes[parent].depth < MAX_TREE_DEPTH {
+            return (parent, before);
+        }
+
+        let mut ancestor = parent;
+
+        while self.nodes[ancestor].depth >= MAX_TREE_DEPTH {
+            ancestor = self.nodes[ancestor].parent.unwrap_or(DOCUMENT);
+        }
+
+        (ancestor, None)
+    }
+
+    fn append_child(&mut self, parent: usize, id: usize) {
+        self.insert_at((parent, None), id);
+    }
+
+    fn appropriate_insertion_place(
+        &self,
+        override_target: Option<usize>,
+    ) -> (usize, Option<usize>) {
+        let target = override_target
+            .or_else(|| self.current_node())
+            .unwrap_or(DOCUMENT);
+
+        if self.foster_parenting
+            && self.is_html_one_of(target, &["table", "tbody", "tfoot", "thead", "tr"])
+        {
+            let last_table = self
+                .open_elements
+                .iter()
+                .rposition(|&element| self.is_html(element, "table"));
+
+            return match last_table {
+                None => (self.open_elements[0], None),
+                Some(index) => {
+                    let table = self.open_elements[index];
+
+                    match self.nodes[table].parent {
+                        Some(parent) => (parent, Some(table)),
+                        None => (self.open_elements[index - 1], None),
+                    }
+                }
+            };
+        }
+
+        (target, None)
+    }
+
+    fn create_element(&mut self, tag: Tag, namespace: Namespace) -> usize {
+        // Elementos implícitos (ex: `tbody`) ou recriados não têm marcação
+        // própria: seu span começa vazio no início do token atual
+        let implied = !self
+            .start_tag_name
+            .as_ref()
+            .is_some_and(|name| name.eq_ignore_ascii_case(&tag.name));
+
+        let element = self.create_node(NodeData::Element { tag, namespace });
+
+        if implied {
+            self.nodes[element].span.end = self.span.start;
+        }
+
+        element
+    }
+
+    fn insert_foreign_element(&mut self, mut tag: Tag, namespace: Namespace) -> usize {
+        adjust_foreign_tag(&mut tag, namespace);
+
+        // A pilha de elementos abertos também é limitada: no limite, abrir um
+        // elemento fecha o nó atual. Assim as buscas por escopo e as demais
+        // varreduras da pilha não crescem com a profundidade da entrada.
+        if self.open_elements.len() >= MAX_TREE_DEPTH {
+            let closed = self.pop();
+
+            // Um template fechado aqui também sai da pilha de modos, que
+            // precisa continuar em par com os templates abertos
+            if closed.is_some_and(|node| self.is_html(node, "template")) {
+                self.clear_active_formatting_to_last_marker();
+                self.template_modes.pop();
+            }
+        }
+
+        let place = self.appropriate_insertion_place(None);
+        let element = self.create_element(tag, namespace);
+
+        self.insert_at(place, element);
+        self.open_elements.push(element);
+
+        element
+    }
+
+    fn insert_element(&mut self, tag: Tag) -> usize {
+        self.insert_foreign_element(tag, Namespace::Html)
+    }
+
+    fn insert_characters(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+
+        let (parent, before) = self.capped_insertion_place(self.appropriate_insertion_place(None));
+
+        if parent == DOCUMENT {
+            return;
+        }
+
+        let children = &self.nodes[parent].children;
+        let previous = match before {
+            Some(before) => children
+                .iter()
+                .position(|&child| child == before)
+                .and_then(|position| position.checked_sub(1))
+                .map(|position| children[position]),
+            None => children.last().copied(),
+        };
+
+        if let Some(previous) = previous {
+            let previous = &mut self.nodes[previous];
+
+            if let NodeData::Text(ref mut existing) = previous.data {
+                existing.push_str(text);
+                previous.span.end = previous.span.end.max(self.span.end);
+                return;
+            }
+        }
+
+        let node = self.create_node(NodeData::Text(text.to_owned()));
+        self.insert_at((parent, before), node);
+    }
+
+    fn insert_comment(&mut self, text: String, parent: Option<usize>) {
+        let place = match parent {
+            Some(parent) => (parent, None),
+            None => self.appropriate_insertion_place(None),
+        };
+        let node = self.create_node(NodeData::Comment(text));
+
+        self.insert_at(place, node);
+    }
+
+    // Registra o fim do span de um elemento retirado da pilha. Se foi a
+    // sua própria tag de fechamento que o fechou, ela faz parte do span.
+    fn close(&mut self, id: usize) {
+        let end = match self.end_tag_name {
+            Some(ref name) if self.name(id).eq_ignore_ascii_case(name) => self.span.end,
+            _ => self.span.start,
+        };
+
+        let span = &mut self.nodes[id].span;
+        span.end = span.end.max(end);
+    }
+
+    fn pop(&mut self) -> Option<usize> {
+        let node = self.open_elements.pop()?;
+        self.close(node);
+
+        Some(node)
+    }
+
+    fn truncate_open_elements(&mut self, length: usize) {
+        while self.open_elements.len() > length {
+            self.pop();
+        }
+    }
+
+    fn pop_until(&mut self, name: &str) {
+        while let Some(node) = self.pop() {
+            if self.is_html(node, name) {
+                break;
+            }
+        }
+    }
+
+    fn pop_until_one_of(&mut self, names: &[&str]) {
+        while let Some(node) = self.pop() {
+            if self.is_html_one_of(node, names) {
+                break;
+            }
+        }
+    }
+
+    fn remove_from_open_elements(&mut self, id: usize) {
+        self.close(id);
+        self.open_elements.retain(|&element| element != id);
+    }
+
+    // Escopos
+
+    fn is_scope_boundary(&self, id: usize, scope: Scope) -> bool {
+        match scope {
+            Scope::Select => !self.is_html_one_of(id, &["optgroup", "option"]),
+            Scope::Table => self.is_html_one_of(id, TABLE_SCOPE),
+            _ => {
+                let in_default = match self.namespace(id) {
+                    Namespace::Html => DEFAULT_SCOPE.contains(&self.name(id)),
+                    Namespace::MathMl => matches!(
+                        self.name(id),
+                        "mi" | "mo" | "mn" | "ms" | "mtext" | "annotation-xml"
+                    ),
+                    Namespace::Svg => matches!(self.name(id), "foreignObject" | "desc" | "title"),
+                };
+
+                in_default
+                    || (scope == Scope::ListItem && self.is_html_one_of(id, &["ol", "ul"]))
+                    || (scope == Scope::Button && self.is_html(id, "button"))
+            }
+        }
+    }
+
+    fn has_in_scope_matching<F: Fn(&Self, usize) -> bool>(&self, scope: Scope, target: F) -> bool {
+        for &node in self.open_elements.iter().rev() {
+            if target(self, node) {
+                return true;
+            }
+
+            if self.is_scope_boundary(node, scope) {
+                return false;
+            }
+        }
+
+        false
+    }
+
+    fn has_in_scope(&self, name: &str, scope: Scope) -> bool {
+        self.has_in_scope_matching(scope, |builder, node| builder.is_html(node, name))
+    }
+
+    fn has_one_of_in_scope(&self, names: &[&str], scope: Scope) -> bool {
+        self.has_in_scope_matching(scope, |builder, node| builder.is_html_one_of(node, names))
+    }
+
+    fn has_node_in_scope(&self, id: usize, scope: Scope) -> bool {
+        self.has_in_scope_matching(scope, |_, node| node == id)
+    }
+
+    // End tags implícitas
+
+    fn generate_implied_end_tags(&mut self, except: Option<&str>) {
+        while let Some(node) = self.current_node() {
+            if !self.is_html_one_of(node, IMPLIED_END_TAGS) || Some(self.name(node)) == except {
+                break;
+            }
+
+            self.pop();
+        }
+    }
+
+    fn generate_all_implied_end_tags_thoroughly(&mut self) {
+        while self.current_is_one_of(THOROUGHLY_IMPLIED_END_TAGS) {
+            self.pop();
+        }
+    }
+
+    fn close_p_element(&mut self) {
+        self.generate_implied_end_tags(Some("p"));
+
+        if !self.current_is("p") {
+            self.parse_error("unexpected-end-tag");
+        }
+
+        self.pop_until("p");
+    }
+
+    fn close_p_if_in_button_scope(&mut self) {
+        if self.has_in_scope("p", Scope::Button) {
+            self.close_p_element();
+        }
+    }
+
+    // Lista de elementos de formatação ativos
+
+    fn is_in_active_formatting(&self, id: usize) -> bool {
+        self.active_formatting
+            .contains(&FormattingEntry::Element(id))
+    }
+
+    fn push_active_formatting(&mut self, id: usize) {
+        // "Arca de Noé": no máximo três elementos iguais após o último marcador
+        let mut equal = Vec::new();
+
+        for (index, entry) in self.active_formatting.iter().enumerate().rev() {
+            match *entry {
+                FormattingEntry::Marker => break,
+                FormattingEntry::Element(other) => {
+                    if self.same_element_token(id, other) {
+                        equal.push(index);
+                    }
+                }
+            }
+        }
+
+        if equal.len() >= 3 {
+            self.active_formatting.remove(*equal.last().unwrap());
+        }
+
+        self.active_formatting.push(FormattingEntry::Element(id));
+    }
+
+    fn same_element_token(&self, a: usize, b: usize) -> bool {
+        match (self.tag(a), self.tag(b)) {
+            (Some(a_tag), Some(b_tag)) => {
+                a_tag.name == b_tag.name
+                    && self.namespace(a) == self.namespace(b)
+                    && a_tag.attributes.len() == b_tag.attributes.len()
+                    && a_tag
+                        .attributes
+                        .iter()
+                        .all(|attribute| b_tag.attributes.contains(attribute))
+            }
+            _ => false,
+        }
+    }
+
+    fn reconstruct_active_formatting_elements(&mut self) {
+        let is_open = |builder: &Self, entry: FormattingEntry| match entry {
+            FormattingEntry::Marker => true,
+            FormattingEntry::Element(id) => builder.open_elements.contains(&id),
+        };
+
+        match self.active_formatting.last() {
+            None => return,
+            Some(&entry) if is_open(self, entry) => return,
+            _ => {}
+        }
+
+        let mut index = self.active_formatting.len() - 1;
+
+        while index > 0 && !is_open(self, self.active_formatting[index - 1]) {
+            index -= 1;
+        }
+
+        for entry in index..self.active_formatting.len() {
+            if let FormattingEntry::Element(id) = self.active_formatting[entry] {
+                let tag = self.tag(id).cloned().unwrap_or_default();
+                let element = self.insert_element(tag);
+
+                self.active_formatting[entry] = FormattingEntry::Element(element);
+            }
+        }
+    }
+
+    fn clear_active_formatting_to_last_marker(&mut self) {
+        while let Some(entry) = self.active_formatting.pop() {
+            if entry == FormattingEntry::Marker {
+                break;
+            }
+        }
+    }
+
+    fn last_formatting_element_named(&self, name: &str) -> Option<usize> {
+        for entry in self.active_formatting.iter().rev() {
+            match *entry {
+                FormattingEntry::Marker => return None,
+                FormattingEntry::Element(id) if self.is_html(id, name) => return Some(id),
+                _ => {}
+            }
+        }
+
+        None
+    }
+
+    // Algoritmo "adoption agency" para elementos de formatação mal aninhados.
+    // Retorna falso quando a end tag deve ser tratada como "any other end tag".
+    fn adoption_agency(&mut self, subject: &str) -> bool {
+        if let Some(current) = self.current_node() {
+            if self.is_html(current, subject) && !self.is_in_active_formatting(current) {
+                self.pop();
+                return true;
+            }
+        }
+
+        for _ in 0..8 {
+            let formatting_element = match self.last_formatting_element_named(subject) {
+                Some(element) => element,
+                None => return false,
+            };
+
+            let stack_index = match self
+                .open_elements
+                .iter()
+                .position(|&e| e == formatting_element)
+            {
+                Some(index) => index,
+                None => {
+                    self.parse_error("misnested-formatting-element");
+                    self.active_formatting
+                        .retain(|&entry| entry != FormattingEntry::Element(formatting_element));
+                    return true;
+                }
+            };
+
+            if !self.has_node_in_scope(formatting_element, Scope::Default) {
+                self.parse_error("misnested-formatting-element");
+                return true;
+            }
+
+            if self.current_node() != Some(formatting_element) {
+                self.parse_error("misnested-formatting-element");
+            }
+
+            let furthest_block = self.open_elements[stack_index + 1..]
+                .iter()
+                .position(|&element| self.is_special(element))
+                .map(|position| stack_index + 1 + position);
+
+            let furthest_block_index = match furthest_block {
+                Some(index) => index,
+                None => {
+                    self.truncate_open_elements(stack_index);
+                    self.active_formatting
+                        .retain(|&entry| entry != FormattingEntry::Element(formatting_element));
+                    return true;
+                }
+            };
+
+            let furthest_block = self.open_elements[furthest_block_index];
+            let common_ancestor = self.open_elements[stack_index - 1];
+
+            let mut bookmark = self
+                .active_formatting
+                .iter()
+                .position(|&entry| entry == FormattingEntry::Element(formatting_element))
+                .unwrap();
+
+            let mut node_index = furthest_block_index;
+            let mut last_node = furthest_block;
+            let mut inner_loop_counter = 0;
+
+            loop {
+                inner_loop_counter += 1;
+                node_index -= 1;
+
+                let node = self.open_elements[node_index];
+
+                if node == formatting_element {
+                    break;
+                }
+
+                let formatting_position = self
+                    .active_formatting
+                    .iter()
+                    .position(|&entry| entry == FormattingEntry::Element(node));
+
+                if inner_loop_counter > 3 {
+                    if let Some(position) = formatting_position {
+                        self.active_formatting.remove(position);
+
+                        if position < bookmark {
+                            bookmark -= 1;
+                        }
+
+                        self.close(node);
+                        self.open_elements.remove(node_index);
+                        continue;
+                    }
+                }
+
+                let formatting_position = match formatting_position {
+                    Some(position) => position,
+                    None => {
+                        self.close(node);
+                        self.open_elements.remove(node_index);
+                        continue;
+                    }
+                };
+
+                let tag = self.tag(node).cloned().unwrap_or_default();
+                let new_element = self.create_element(tag, Namespace::Html);
+
+                self.active_formatting[formatting_position] = FormattingEntry::Element(new_element);
+                self.open_elements[node_index] = new_element;
+
+                if last_node == furthest_block {
+                    bookmark = formatting_position + 1;
+                }
+
+                self.append_child(new_element, last_node);
+                last_node = new_element;
+            }
+
+            let place = self.appropriate_insertion_place(Some(common_ancestor));
+            self.insert_at(place, last_node);
+
+            let tag = self.tag(formatting_element).cloned().unwrap_or_default();
+            let new_element = self.create_element(tag, Namespace::Html);
+
+            let children = mem::take(&mut self.nodes[furthest_block].children);
+            self.append_child(furthest_block, new_element);
+
+            for child in children {
+                self.nodes[child].parent = None;
+                self.append_child(new_element, child);
+            }
+
+            let old_position = self
+                .active_formatting
+                .iter()
+                .position(|&entry| entry == FormattingEntry::Element(formatting_element))
+                .unwrap();
+
+            self.active_formatting.remove(old_position);
+
+            if old_position < bookmark {
+                bookmark -= 1;
+            }
+
+            self.active_formatting.insert(
+                bookmark.min(self.active_formatting.len()),
+                FormattingEntry::Element(new_element),
+            );
+
+            self.remove_from_open_elements(formatting_element);
+
+            let furthest_block_index = self
+                .open_elements
+                .iter()
+                .position(|&element| element == furthest_block)
+                .unwrap();
+
+            self.open_elements
+                .insert(furthest_block_index + 1, new_element);
+        }
+
+        true
+    }
+
+    fn any_other_end_tag(&mut self, name: &str) {
+        for index in (0..self.open_elements.len()).rev() {
+            let node = self.open_elements[index];
+
+            if self.is_html(node, name) {
+                self.generate_implied_end_tags(Some(name));
+
+                if self.current_node() != Some(node) {
+                    self.parse_error("unexpected-end-tag");
+                }
+
+                self.truncate_open_elements(index);
+                return;
+            }
+
+            if self.is_special(node) {
+                self.parse_error("unexpected-end-tag");
+                return;
+            }
+        }
+    }
+
+    fn reset_insertion_mode(&mut self) {
+        for (index, &node) in self.open_elements.iter().enumerate().rev() {
+            let last = index == 0;
+
+            self.mode = match self.name(node) {
+                "select" => {
+                    let in_table = self.open_elements[..index]
+                        .iter()
+                        .any(|&ancestor| self.is_html(ancestor, "table"));
+
+                    if in_table {
+                        InsertionMode::InSelectInTable
+                    } else {
+                        InsertionMode::InSelect
+                    }
+                }
+                "td" | "th" if !last => InsertionMode::InCell,
+                "tr" => InsertionMode::InRow,
+                "tbody" | "thead" | "tfoot" => InsertionMode::InTableBody,
+                "caption" => InsertionMode::InCaption,
+                "colgroup" => InsertionMode::InColumnGroup,
+                "table" => InsertionMode::InTable,
+                "template" => *self.template_modes.last().unwrap_or(&InsertionMode::InBody),
+                "head" if !last => InsertionMode::InHead,
+                "body" => InsertionMode::InBody,
+                "frameset" => InsertionMode::InFrameset,
+                "html" if self.head.is_none() => InsertionMode::BeforeHead,
+                "html" => InsertionMode::AfterHead,
+                _ if last => InsertionMode::InBody,
+                _ => continue,
+            };
+
+            return;
+        }
+
+        self.mode = InsertionMode::InBody;
+    }
+
+    // Algoritmos genéricos para elementos de texto bruto (RAWTEXT) e RCDATA
+    fn parse_text_element(&mut self, tag: Tag, state: State, tokenizer: &mut Tokenizer) {
+        self.insert_element(tag);
+        tokenizer.set_state(state);
+        self.original_mode = self.mode;
+        self.mode = InsertionMode::Text;
+    }
+
+    fn stop_parsing(&mut self) {
+        self.truncate_open_elements(0);
+        self.stopped = true;
+    }
+
+    fn should_process_as_foreign_content(&self, token: &Token) -> bool {
+        let node = match self.adjusted_current_node() {
+            Some(node) => node,
+            None => return false,
+        };
+
+        if self.namespace(node) == Namespace::Html {
+            return false;
+        }
+
+        match token {
+            Token::StartTag(tag)
+                if self.is_mathml_text_integration_point(node)
+                    && tag.name != "mglyph"
+                    && tag.name != "malignmark" =>
+            {
+                false
+            }
+            Token::Characters(_) if self.is_mathml_text_integration_point(node) => false,
+            Token::StartTag(tag)
+                if tag.name == "svg"
+                    && self.namespace(node) == Namespace::MathMl
+                    && self.name(node) == "annotation-xml" =>
+            {
+                false
+            }
+            Token::StartTag(_) | Token::Characters(_) if self.is_html_integration_point(node) => {
+                false
+            }
+            Token::Eof => false,
+            _ => true,
+        }
+    }
+
+    fn process_in_mode(&mut self, mode: InsertionMode, token: Token, tokenizer: &mut Tokenizer) {
+        match mode {
+            InsertionMode::Initial => self.initial(token, tokenizer),
+            InsertionMode::BeforeHtml => self.before_html(token, tokenizer),
+            InsertionMode::BeforeHead => self.before_head(token, tokenizer),
+            InsertionMode::InHead => self.in_head(token, tokenizer),
+            InsertionMode::InHeadNoscript => self.in_head_noscript(token, tokenizer),
+            InsertionMode::AfterHead => self.after_head(token, tokenizer),
+            InsertionMode::InBody => self.in_body(token, tokenizer),
+            InsertionMode::Text => self.text(token, tokenizer),
+            InsertionMode::InTable => self.in_table(token, tokenizer),
+            InsertionMode::InTableText => self.in_table_text(token, tokenizer),
+            InsertionMode::InCaption => self.in_caption(token, tokenizer),
+            InsertionMode::InColumnGroup => self.in_column_group(token, tokenizer),
+            InsertionMode::InTableBody => self.in_table_body(token, tokenizer),
+            InsertionMode::InRow => self.in_row(token, tokenizer),
+            InsertionMode::InCell => self.in_cell(token, tokenizer),
+            InsertionMode::InSelect => self.in_select(token, tokenizer),
+            InsertionMode::InSelectInTable => self.in_select_in_table(token, tokenizer),
+            InsertionMode::InTemplate => self.in_template(token, tokenizer),
+            InsertionMode::AfterBody => self.after_body(token, tokenizer),
+            InsertionMode::InFrameset => self.in_frameset(token, tokenizer),
+            InsertionMode::AfterFrameset => self.after_frameset(token, tokenizer),
+            InsertionMode::AfterAfterBody => self.after_after_body(token, tokenizer),
+            InsertionMode::AfterAfterFrameset => self.after_after_frameset(token, tokenizer),
+        }
+    }
+
+    fn reprocess(&mut self, mode: InsertionMode, token: Token, tokenizer: &mut Tokenizer) {
+        self.mode = mode;
+        self.process_in_mode(mode, token, tokenizer);
+    }
+
+    fn initial(&mut self, token: Token, tokenizer: &mut Tokenizer) {
+        let token = match token {
+            Token::Characters(text) => match split_leading_whitespace(&text).1 {
+                "" => return,
+                rest => Token::Characters(rest.to_owned()),
+            },
+            Token::Comment(text) => return self.insert_comment(text, Some(DOCUMENT)),
+            Token::Doctype(doctype) => {
+                if doctype.name.as_deref() != Some("html")
+                    || doctype.public_id.is_some()
+                    || doctype
+                        .system_id
+                        .as_deref()
+                        .is_some_and(|id| id != "about:legacy-compat")
+                {
+                    self.parse_error("bad-doctype");
+                }
+
+                self.quirks_mode = quirks_mode(&doctype);
+
+                let node = self.create_node(NodeData::Doctype(doctype));
+                self.append_child(DOCUMENT, node);
+                self.mode = InsertionMode::BeforeHtml;
+                return;
+            }
+            token => token,
+        };
+
+        self.parse_error("missing-doctype");
+        self.quirks_mode = QuirksMode::Quirks;
+        self.reprocess(InsertionMode::BeforeHtml, token, tokenizer);
+    }
+
+    fn before_html(&mut self, token: Token, tokenizer: &mut Tokenizer) {
+        let token = match token {
+            Token::Doctype(_) => return self.parse_error("unexpected-doctype"),
+            Token::Comment(text) => return self.insert_comment(text, Some(DOCUMENT)),
+            Token::Characters(text) => match split_leading_whitespace(&text).1 {
+                "" => return,
+                rest => Token::Characters(rest.to_owned()),
+            },
+            Token::StartTag(tag) if tag.name == "html" => {
+                let element = self.create_element(tag, Namespace::Html);
+                self.append_child(DOCUMENT, element);
+                self.open_elements.push(element);
+                self.mode = InsertionMode::BeforeHead;
+                return;
+            }
+            Token::EndTag(tag) if !matches!(tag.name.as_str(), "head" | "body" | "html" | "br") => {
+                return self.parse_error("unexpected-end-tag");
+            }
+            token => token,
+        };
+
+        let element = self.create_element(tag_named("html"), Namespace::Html);
+        self.append_child(DOCUMENT, element);
+        self.open_elements.push(element);
+        self.reprocess(InsertionMode::BeforeHead, token, tokenizer);
+    }
+
+    fn before_head(&mut self, token: Token, tokenizer: &mut Tokenizer) {
+        let token = match token {
+            Token::Characters(text) => match split_leading_whitespace(&text).1 {
+                "" => return,
+                rest => Token::Characters(rest.to_owned()),
+            },
+            Token::Comment(text) => return self.insert_comment(text, None),
+            Token::Doctype(_) => return self.parse_error("unexpected-doctype"),
+            Token::StartTag(ref tag) if tag.name == "html" => {
+                return self.in_body(token, tokenizer)
+            }
+            Token::StartTag(tag) if tag.name == "head" => {
+                self.head = Some(self.insert_element(tag));
+                self.mode = InsertionMode::InHead;
+                return;
+            }
+            Token::EndTag(tag) if !matches!(tag.name.as_str(), "head" | "body" | "html" | "br") => {
+                return self.parse_error("unexpected-end-tag");
+            }
+            token => token,
+        };
+
+        self.head = Some(self.insert_element(tag_named("head")));
+        self.reprocess(InsertionMode::InHead, token, tokenizer);
+    }
+
+    fn in_head(&mut self, token: Token, tokenizer: &mut Tokenizer) {
+        let token = match token {
+            Token::Characters(text) => {
+                let (whitespace, rest) = split_leading_whitespace(&text);
+                self.insert_characters(whitespace);
+
+                if rest.is_empty() {
+                    return;
+                }
+
+                Token::Characters(rest.to_owned())
+            }
+            Token::Comment(text) => return self.insert_comment(text, None),
+            Token::Doctype(_) => return self.parse_error("unexpected-doctype"),
+            Token::StartTag(tag) => match tag.name.as_str() {
+                "html" => return self.in_body(Token::StartTag(tag), tokenizer),
+                "base" | "basefont" | "bgsound" | "link" | "meta" => {
+                    self.insert_element(tag);
+                    self.pop();
+                    return;
+                }
+                "title" => return self.parse_text_element(tag, State::Rcdata, tokenizer),
+                "noframes" | "style" => {
+                    return self.parse_text_element(tag, State::Rawtext, tokenizer)
+                }
+                "noscript" => {
+                    self.insert_element(tag);
+                    self.mode = InsertionMode::InHeadNoscript;
+                    return;
+                }
+                "script" => return self.parse_text_element(tag, State::ScriptData, tokenizer),
+                // O conteúdo de `<template>` é tratado como conteúdo normal do elemento
+                "template" => {
+                    self.insert_element(tag);
+                    self.active_formatting.push(FormattingEntry::Marker);
+                    self.frameset_ok = false;
+                    self.mode = InsertionMode::InTemplate;
+                    self.template_modes.push(InsertionMode::InTemplate);
+                    return;
+                }
+                "head" => return self.parse_error("unexpected-start-tag"),
+                _ => Token::StartTag(tag),
+            },
+            Token::EndTag(tag) => match tag.name.as_str() {
+                "head" => {
+                    self.pop();
+                    self.mode = InsertionMode::AfterHead;
+                    return;
+                }
+                "template" => {
+                    if !self
+                        .open_elements
+                        .iter()
+                        .any(|&node| self.is_html(node, "template"))
+                    {
+                        return self.parse_error("unexpected-end-tag");
+                    }
+
+                    self.generate_all_implied_end_tags_thoroughly();
+
+                    if !self.current_is("template") {
+                        self.parse_error("unexpected-end-tag");
+                    }
+
+                    self.pop_until("template");
+                    self.clear_active_formatting_to_last_marker();
+                    self.template_modes.pop();
+                    self.reset_insertion_mode();
+                    return;
+                }
+                "body" | "html" | "br" => Token::EndTag(tag),
+                _ => return self.parse_error("unexpected-end-tag"),
+            },
+            token => token,
+        };
+
+        self.pop();
+        self.reprocess(InsertionMode::AfterHead, token, tokenizer);
+    }
+
+    fn in_head_noscript(&mut self, token: Token, tokenizer: &mut Tokenizer) {
+        let token = match token {
+            Token::Doctype(_) => return self.parse_error("unexpected-doctype"),
+            Token::StartTag(ref tag) if tag.name == "html" => {
+                return self.in_body(token, tokenizer)
+            }
+            Token::EndTag(ref tag) if tag.name == "noscript" => {
+                self.pop();
+                self.mode = InsertionMode::InHead;
+                return;
+            }
+            Token::Characters(text) => {
+                let (whitespace, rest) = split_leading_whitespace(&text);
+                self.insert_characters(whitespace);
+
+                if rest.is_empty() {
+                    return;
+                }
+
+                Token::Characters(rest.to_owned())
+            }
+            Token::Comment(_) => return self.in_head(token, tokenizer),
+            Token::StartTag(ref tag)
+                if matches!(
+                    tag.name.as_str(),
+                    "basefont" | "bgsound" | "link" | "meta" | "noframes" | "style"
+                ) =>
+            {
+                return self.in_head(token, tokenizer);
+            }
+            Token::StartTag(ref tag) if matches!(tag.name.as_str(), "head" | "noscript") => {
+                return self.parse_error("unexpected-start-tag");
+            }
+            Token::EndTag(ref tag) if tag.name != "br" => {
+                return self.parse_error("unexpected-end-tag")
+            }
+            token => token,
+        };
+
+        self.parse_error("unexpected-token-in-noscript");
+        self.pop();
+        self.reprocess(InsertionMode::InHead, token, tokenizer);
+    }
+
+    fn after_head(&mut self, token: Token, tokenizer: &mut Tokenizer) {
+        let token = match token {
+            Token::Characters(text) => {
+                let (whitespace, rest) = split_leading_whitespace(&text);
+                self.insert_characters(whitespace);
+
+                if rest.is_empty() {
+                    return;
+                }
+
+                Token::Characters(rest.to_owned())
+            }
+            Token::Comment(text) => return self.insert_comment(text, None),
+            Token::Doctype(_) => return self.parse_error("unexpected-doctype"),
+            Token::StartTag(tag) => match tag.name.as_str() {
+                "html" => return self.in_body(Token::StartTag(tag), tokenizer),
+                "body" => {
+                    self.insert_element(tag);
+                    self.frameset_ok = false;
+                    self.mode = InsertionMode::InBody;
+                    return;
+                }
+                "frameset" => {
+                    self.insert_element(tag);
+                    self.mode = InsertionMode::InFrameset;
+                    return;
+                }
+                "base" | "basefont" | "bgsound" | "link" | "meta" | "noframes" | "script"
+                | "style" | "template" | "title" => {
+                    self.parse_error("unexpected-start-tag");
+
+                    if let Some(head) = self.head {
+                        self.open_elements.push(head);
+                        self.in_head(Token::StartTag(tag), tokenizer);
+                        self.remove_from_open_elements(head);
+                    }
+
+                    return;
+                }
+                "head" => return self.parse_error("unexpected-start-tag"),
+                _ => Token::StartTag(tag),
+            },
+            Token::EndTag(tag) if !matches!(tag.name.as_str(), "body" | "html" | "br") => {
+                return self.parse_error("unexpected-end-tag");
+            }
+            token => token,
+        };
+
+        self.insert_element(tag_named("body"));
+        self.reprocess(InsertionMode::InBody, token, tokenizer);
+    }
+
+    fn in_body(&mut self, token: Token, tokenizer: &mut Tokenizer) {
+        match token {
+            Token::Characters(text) => {
+                let text = if text.contains('\0') {
+                    self.parse_error("unexpected-null-character");
+                    text.replace('\0', "")
+                } else {
+                    text
+                };
+
+                if text.is_empty() {
+                    return;
+                }
+
+                self.reconstruct_active_formatting_elements();
+                self.insert_characters(&text);
+
+                if !text.chars().all(is_whitespace) {
+                    self.frameset_ok = false;
+                }
+            }
+            Token::Comment(text) => self.insert_comment(text, None),
+            Token::Doctype(_) => self.parse_error("unexpected-doctype"),
+            Token::StartTag(tag) => self.in_body_start_tag(tag, tokenizer),
+            Token::EndTag(tag) => self.in_body_end_tag(tag, tokenizer),
+            Token::Eof if !self.template_modes.is_empty() => {
+                self.in_template(Token::Eof, tokenizer)
+            }
+            Token::Eof => self.stop_parsing(),
+        }
+    }
+
+    fn merge_missing_attributes(&mut self, id: usize, attributes: Vec<Attribute>) {
+        if let NodeData::Element { ref mut tag, .. } = self.nodes[id].data {
+            for attribute in attributes {
+                if !tag
+                    .attributes
+                    .iter()
+                    .any(|existing| existing.name == attribute.name)
+                {
+                    tag.attributes.push(attribute);
+                }
+            }
+        }
+    }
+
+    fn in_body_start_tag(&mut self, tag: Tag, tokenizer: &mut Tokenizer) {
+        match tag.name.as_str() {
+            "html" => {
+                self.parse_error("unexpected-start-tag");
+                let html = self.open_elements[0];
+                self.merge_missing_attributes(html, tag.attributes);
+            }
+            "base" | "basefont" | "bgsound" | "link" | "meta" | "noframes" | "script" | "style"
+            | "template" | "title" => self.in_head(Token::StartTag(tag), tokenizer),
+            "body" => {
+                self.parse_error("unexpected-start-tag");
+
+                match self.open_elements.get(1) {
+                    Some(&body) if self.is_html(body, "body") => {
+                        self.frameset_ok = false;
+                        self.merge_missing_attributes(body, tag.attributes);
+                    }
+                    _ => {}
+                }
+            }
+            "frameset" => {
+                self.parse_error("unexpected-start-tag");
+
+                match self.open_elements.get(1) {
+                    Some(&body) if self.is_html(body, "body") && self.frameset_ok => {
+                        self.detach(body);
+                        self.truncate_open_elements(1);
+                        self.insert_element(tag);
+                        self.mode = InsertionMode::InFrameset;
+                    }
+                    _ => {}
+                }
+            }
+            "address" | "article" | "aside" | "blockquote" | "center" | "details" | "dialog"
+            | "dir" | "div" | "dl" | "fieldset" | "figcaption" | "figure" | "footer" | "header"
+            | "hgroup" | "main" | "menu" | "nav" | "ol" | "p" | "search" | "section"
+            | "summary" | "ul" => {
+                self.close_p_if_in_button_scope();
+                self.insert_element(tag);
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.close_p_if_in_button_scope();
+
+                if self.current_is_one_of(HEADINGS) {
+                    self.parse_error("unexpected-start-tag");
+                    self.pop();
+                }
+
+                self.insert_element(tag);
+            }
+            "pre" | "listing" => {
+                self.close_p_if_in_button_scope();
+                self.insert_element(tag);
+                self.ignore_next_line_feed = true;
+                self.frameset_ok = false;
+            }
+            "form" => {
+                if self.form.is_some() {
+                    self.parse_error("unexpected-start-tag");
+                    return;
+                }
+
+                self.close_p_if_in_button_scope();
+                self.form = Some(self.insert_element(tag));
+            }
+            "li" | "dd" | "dt" => {
+                self.frameset_ok = false;
+
+                let closes: &[&str] = if tag.name == "li" {
+                    &["li"]
+                } else {
+                    &["dd", "dt"]
+                };
+
+                for index in (0..self.open_elements.len()).rev() {
+                    let node = self.open_elements[index];
+
+                    if self.is_html_one_of(node, closes) {
+                        let name = self.name(node).to_owned();
+                        self.generate_implied_end_tags(Some(&name));
+
+                        if !self.current_is(&name) {
+                            self.parse_error("unexpected-start-tag");
+                        }
+
+                        self.pop_until(&name);
+                        break;
+                    }
+
+                    if self.is_special(node) && !self.is_html_one_of(node, &["address", "div", "p"])
+                    {
+                        break;
+                    }
+                }
+
+                self.close_p_if_in_button_scope();
+                self.insert_element(tag);
+            }
+            "plaintext" => {
+                self.close_p_if_in_button_scope();
+                self.insert_element(tag);
+                tokenizer.set_state(State::Plaintext);
+            }
+            "button" => {
+                if self.has_in_scope("button", Scope::Default) {
+                    self.parse_error("unexpected-start-tag");
+                    self.generate_implied_end_tags(None);
+                    self.pop_until("button");
+                }
+
+                self.reconstruct_active_formatting_elements();
+                self.insert_element(tag);
+                self.frameset_ok = false;
+            }
+            "a" => {
+                if let Some(existing) = self.last_formatting_element_named("a") {
+                    self.parse_error("unexpected-start-tag");
+
+                    if !self.adoption_agency("a") {
+                        self.any_other_end_tag("a");
+                    }
+
+                    self.active_formatting
+                        .retain(|&entry| entry != FormattingEntry::Element(existing));
+                    self.remove_from_open_elements(existing);
+                }
+
+                self.reconstruct_active_formatting_elements();
+                let element = self.insert_element(tag);
+                self.push_active_formatting(element);
+            }
+            "b" | "big" | "code" | "em" | "font" | "i" | "s" | "small" | "strike" | "strong"
+            | "tt" | "u" => {
+                self.reconstruct_active_formatting_elements();
+                let element = self.insert_element(tag);
+                self.push_active_formatting(element);
+            }
+            "nobr" => {
+                self.reconstruct_active_formatting_elements();
+
+                if self.has_in_scope("nobr", Scope::Default) {
+                    self.parse_error("unexpected-start-tag");
+
+                    if !self.adoption_agency("nobr") {
+                        self.any_other_end_tag("nobr");
+                    }
+
+                    self.reconstruct_active_formatting_elements();
+                }
+
+                let element = self.insert_element(tag);
+                self.push_active_formatting(element);
+            }
+            "applet" | "marquee" | "object" => {
+                self.reconstruct_active_formatting_elements();
+                self.insert_element(tag);
+                self.active_formatting.push(FormattingEntry::Marker);
+                self.frameset_ok = false;
+            }
+            "table" => {
+                if self.quirks_mode != QuirksMode::Quirks {
+                    self.close_p_if_in_button_scope();
+                }
+
+                self.insert_element(tag);
+                self.frameset_ok = false;
+                self.mode = InsertionMode::InTable;
+            }
+            "area" | "br" | "embed" | "img" | "keygen" | "wbr" => {
+                self.reconstruct_active_formatting_elements();
+                self.insert_element(tag);
+                self.pop();
+                self.frameset_ok = false;
+            }
+            "input" => {
+                let hidden = tag.attributes.iter().any(|attribute| {
+                    attribute.name == "type" && attribute.value.eq_ignore_ascii_case("hidden")
+                });
+
+                self.reconstruct_active_formatting_elements();
+                self.insert_element(tag);
+                self.pop();
+
+                if !hidden {
+                    self.frameset_ok = false;
+                }
+            }
+            "param" | "source" | "track" => {
+                self.insert_element(tag);
+                self.pop();
+            }
+            "hr" => {
+                self.close_p_if_in_button_scope();
+                self.insert_element(tag);
+                self.pop();
+                self.frameset_ok = false;
+            }
+            "image" => {
+                self.parse_error("unexpected-start-tag");
+                self.in_body_start_tag(
+                    Tag {
+                        name: "img".to_owned(),
+                        ..tag
+                    },
+                    tokenizer,
+                );
+            }
+            "textarea" => {
+                self.ignore_next_line_feed = true;
+                self.frameset_ok = false;
+                self.parse_text_element(tag, State::Rcdata, tokenizer);
+            }
+            "xmp" => {
+                self.close_p_if_in_button_scope();
+                self.reconstruct_active_formatting_elements();
+                self.frameset_ok = false;
+                self.parse_text_element(tag, State::Rawtext, tokenizer);
+            }
+            "iframe" => {
+                self.frameset_ok = false;
+                self.parse_text_element(tag, State::Rawtext, tokenizer);
+            }
+            "noembed" => self.parse_text_element(tag, State::Rawtext, tokenizer),
+            "select" => {
+                self.reconstruct_active_formatting_elements();
+                self.insert_element(tag);
+                self.frameset_ok = false;
+                self.mode = match self.mode {
+                    InsertionMode::InTable
+                    | InsertionMode::InCaption
+                    | InsertionMode::InTableBody
+                    | InsertionMode::InRow
+                    | InsertionMode::InCell => InsertionMode::InSelectInTable,
+                    _ => InsertionMode::InSelect,
+                };
+            }
+            "optgroup" | "option" => {
+                if self.current_is("option") {
+                    self.pop();
+                }
+
+                self.reconstruct_active_formatting_elements();
+                self.insert_element(tag);
+            }
+            "rb" | "rtc" => {
+                if self.has_in_scope("ruby", Scope::Default) {
+                    self.generate_implied_end_tags(None);
+
+                    if !self.current_is("ruby") {
+                        self.parse_error("unexpected-start-tag");
+                    }
+                }
+
+                self.insert_element(tag);
+            }
+            "rp" | "rt" => {
+                if self.has_in_scope("ruby", Scope::Default) {
+                    self.generate_implied_end_tags(Some("rtc"));
+
+                    if !self.current_is_one_of(&["rtc", "ruby"]) {
+                        self.parse_error("unexpected-start-tag");
+                    }
+                }
+
+                self.insert_element(tag);
+            }
+            "math" | "svg" => {
+                let namespace = if tag.name == "math" {
+                    Namespace::MathMl
+                } else {
+                    Namespace::Svg
+                };
+                let self_closing = tag.self_closing;
+
+                self.reconstruct_active_formatting_elements();
+                self.insert_foreign_element(tag, namespace);
+
+                if self_closing {
+                    self.pop();
+                }
+            }
+            "caption" | "col" | "colgroup" | "frame" | "head" | "tbody" | "td" | "tfoot" | "th"
+            | "thead" | "tr" => self.parse_error("unexpected-start-tag"),
+            _ => {
+                self.reconstruct_active_formatting_elements();
+                self.insert_element(tag);
+            }
+        }
+    }
+
+    fn in_body_end_tag(&mut self, tag: Tag, tokenizer: &mut Tokenizer) {
+        match tag.name.as_str() {
+            "body" | "html" => {
+                if !self.has_in_scope("body", Scope::Default) {
+                    self.parse_error("unexpected-end-tag");
+                    return;
+                }
+
+                self.mode = InsertionMode::AfterBody;
+
+                if tag.name == "html" {
+                    self.after_body(Token::EndTag(tag), tokenizer);
+                }
+            }
+            "address" | "article" | "aside" | "blockquote" | "button" | "center" | "details"
+            | "dialog" | "dir" | "div" | "dl" | "fieldset" | "figcaption" | "figure" | "footer"
+            | "header" | "hgroup" | "listing" | "main" | "menu" | "nav" | "ol" | "pre"
+            | "search" | "section" | "summary" | "ul" => {
+                if !self.has_in_scope(&tag.name, Scope::Default) {
+                    self.parse_error("unexpected-end-tag");
+                    return;
+                }
+
+                self.generate_implied_end_tags(None);
+
+                if !self.current_is(&tag.name) {
+                    self.parse_error("unexpected-end-tag");
+                }
+
+                self.pop_until(&tag.name);
+            }
+            "form" => {
+                let node = self.form.take();
+
+                match node {
+                    Some(node) if self.has_node_in_scope(node, Scope::Default) => {
+                        self.generate_implied_end_tags(None);
+
+                        if self.current_node() != Some(node) {
+                            self.parse_error("unexpected-end-tag");
+                        }
+
+                        self.remove_from_open_elements(node);
+                    }
+                    _ => self.parse_error("unexpected-end-tag"),
+                }
+            }
+            "p" => {
+                if !self.has_in_scope("p", Scope::Button) {
+                    self.parse_error("unexpected-end-tag");
+                    self.insert_element(tag_named("p"));
+                }
+
+                self.close_p_element();
+            }
+            "li" | "dd" | "dt" => {
+                let scope = if tag.name == "li" {
+                    Scope::ListItem
+                } else {
+                    Scope::Default
+                };
+
+                if !self.has_in_scope(&tag.name, scope) {
+                    self.parse_error("unexpected-end-tag");
+                    return;
+                }
+
+                self.generate_implied_end_tags(Some(&tag.name));
+
+                if !self.current_is(&tag.name) {
+                    self.parse_error("unexpected-end-tag");
+                }
+
+                self.pop_until(&tag.name);
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                if !self.has_one_of_in_scope(HEADINGS, Scope::Default) {
+                    self.parse_error("unexpected-end-tag");
+                    return;
+                }
+
+                self.generate_implied_end_tags(None);
+
+                if !self.current_is(&tag.name) {
+                    self.parse_error("unexpected-end-tag");
+                }
+
+                self.pop_until_one_of(HEADINGS);
+            }
+            name if FORMATTING_ELEMENTS.contains(&name) => {
+                if !self.adoption_agency(name) {
+                    self.any_other_end_tag(name);
+                }
+            }
+            "applet" | "marquee" | "object" => {
+                if !self.has_in_scope(&tag.name, Scope::Default) {
+                    self.parse_error("unexpected-end-tag");
+                    return;
+                }
+
+                self.generate_implied_end_tags(None);
+
+                if !self.current_is(&tag.name) {
+                    self.parse_error("unexpected-end-tag");
+                }
+
+                self.pop_until(&tag.name);
+                self.clear_active_formatting_to_last_marker();
+            }
+            "br" => {
+                self.parse_error("unexpected-end-tag");
+                self.in_body_start_tag(tag_named("br"), tokenizer);
+            }
+            "template" => self.in_head(Token::EndTag(tag), tokenizer),
+            name => self.any_other_end_tag(name),
+        }
+    }
+
+    fn text(&mut self, token: Token, tokenizer: &mut Tokenizer) {
+        match token {
+            Token::Characters(text) => self.insert_characters(&text),
+            Token::Eof => {
+                self.parse_error("eof-in-text");
+                self.pop();
+                self.reprocess(self.original_mode, token, tokenizer);
+            }
+            _ => {
+                self.pop();
+                self.mode = self.original_mode;
+            }
+        }
+    }
+
+    fn clear_stack_back_to(&mut self, names: &[&str]) {
+        while let Some(node) = self.current_node() {
+            if self.is_html_one_of(node, names) {
+                break;
+            }
+
+            self.pop();
+        }
+    }
+
+    fn clear_stack_back_to_table_context(&mut self) {
+        self.clear_stack_back_to(&["table", "template", "html"]);
+    }
+
+    fn clear_stack_back_to_table_body_context(&mut self) {
+        self.clear_stack_back_to(&["tbody", "tfoot", "thead", "template", "html"]);
+    }
+
+    fn clear_stack_back_to_table_row_context(&mut self) {
+        self.clear_stack_back_to(&["tr", "template", "html"]);
+    }
+
+    fn in_table(&mut self, token: Token, tokenizer: &mut Tokenizer) {
+        let token = match token {
+            Token::Characters(_)
+                if self
+                    .current_is_one_of(&["table", "tbody", "template", "tfoot", "thead", "tr"]) =>
+            {
+                self.pending_table_text.clear();
+                self.original_mode = self.mode;
+                return self.reprocess(InsertionMode::InTableText, token, tokenizer);
+            }
+            Token::Comment(text) => return self.insert_comment(text, None),
+            Token::Doctype(_) => return self.parse_error("unexpected-doctype"),
+            Token::StartTag(tag) => match tag.name.as_str() {
+                "caption" => {
+                    self.clear_stack_back_to_table_context();
+                    self.active_formatting.push(FormattingEntry::Marker);
+                    self.insert_element(tag);
+                    self.mode = InsertionMode::InCaption;
+                    return;
+                }
+                "colgroup" => {
+                    self.clear_stack_back_to_table_context();
+                    self.insert_element(tag);
+                    self.mode = InsertionMode::InColumnGroup;
+                    return;
+                }
+                "col" => {
+                    self.clear_stack_back_to_table_context();
+                    self.insert_element(tag_named("colgroup"));
+                    return self.reprocess(
+                        InsertionMode::InColumnGroup,
+                        Token::StartTag(tag),
+                        tokenizer,
+                    );
+                }
+                "tbody" | "tfoot" | "thead" => {
+                    self.clear_stack_back_to_table_context();
+                    self.insert_element(tag);
+                    self.mode = InsertionMode::InTableBody;
+                    return;
+                }
+                "td" | "th" | "tr" => {
+                    self.clear_stack_back_to_table_context();
+                    self.insert_element(tag_named("tbody"));
+                    return self.reprocess(
+                        InsertionMode::InTableBody,
+                        Token::StartTag(tag),
+                        tokenizer,
+                    );
+                }
+                "table" => {
+                    self.parse_error("unexpected-start-tag");
+
+                    if self.has_in_scope("table", Scope::Table) {
+                        self.pop_until("table");
+                        self.reset_insertion_mode();
+                        self.process_in_mode(self.mode, Token::StartTag(tag), tokenizer);
+                    }
+
+                    return;
+                }
+                "style" | "script" | "template" => {
+                    return self.in_head(Token::StartTag(tag), tokenizer)
+                }
+                "input"
+                    if tag.attributes.iter().any(|attribute| {
+                        attribute.name == "type" && attribute.value.eq_ignore_ascii_case("hidden")
+                    }) =>
+                {
+                    self.parse_error("unexpected-start-tag");
+                    self.insert_element(tag);
+                    self.pop();
+                    return;
+                }
+                "form" => {
+                    self.parse_error("unexpected-start-tag");
+
+                    if self.form.is_none() {
+                        self.form = Some(self.insert_element(tag));
+                        self.pop();
+                    }
+
+                    return;
+                }
+                _ => Token::StartTag(tag),
+            },
+            Token::EndTag(tag) => match tag.name.as_str() {
+                "table" => {
+                    if !self.has_in_scope("table", Scope::Table) {
+                        return self.parse_error("unexpected-end-tag");
+                    }
+
+                    self.pop_until("table");
+                    self.reset_insertion_mode();
+                    return;
+                }
+                "body" | "caption" | "col" | "colgroup" | "html" | "tbody" | "td" | "tfoot"
+                | "th" | "thead" | "tr" => return self.parse_error("unexpected-end-tag"),
+                "template" => return self.in_head(Token::EndTag(tag), tokenizer),
+                _ => Token::EndTag(tag),
+            },
+            Token::Eof => return self.in_body(token, tokenizer),
+            token => token,
+        };
+
+        // Conteúdo inesperado dentro de tabelas é movido para antes da tabela
+        self.parse_error("unexpected-token-in-table");
+        self.foster_parenting = true;
+        self.in_body(token, tokenizer);
+        self.foster_parenting = false;
+    }
+
+    fn in_table_text(&mut self, token: Token, tokenizer: &mut Tokenizer) {
+        if let Token::Characters(text) = token {
+            if text.contains('\0') {
+                self.parse_error("unexpected-null-character");
+            }
+
+            self.pending_table_text
+                .extend(text.chars().filter(|&c| c != '\0'));
+            return;
+        }
+
+        let pending = mem::take(&mut self.pending_table_text);
+
+        if pending.chars().any(|c| !is_whitespace(c)) {
+            self.parse_error("unexpected-character-in-table");
+            self.foster_parenting = true;
+            self.in_body(Token::Characters(pending), tokenizer);
+            self.foster_parenting = false;
+        } else {
+            self.insert_characters(&pending);
+        }
+
+        self.reprocess(self.original_mode, token, tokenizer);
+    }
+
+    fn close_caption(&mut self) -> bool {
+        if !self.has_in_scope("caption", Scope::Table) {
+            self.parse_error("unexpected-end-tag");
+            return false;
+        }
+
+        self.generate_implied_end_tags(None);
+
+        if !self.current_is("caption") {
+            self.parse_error("unexpected-end-tag");
+        }
+
+        self.pop_until("caption");
+        self.clear_active_formatting_to_last_marker();
+        self.mode = InsertionMode::InTable;
+
+        true
+    }
+
+    fn in_caption(&mut self, token: Token, tokenizer: &mut Tokenizer) {
+        match token {
+            Token::EndTag(ref tag) if tag.name == "caption" => {
+                self.close_caption();
+            }
+            Token::StartTag(ref tag)
+                if matches!(
+                    tag.name.as_str(),
+                    "caption"
+                        | "col"
+                        | "colgroup"
+                        | "tbody"
+                        | "td"
+                        | "tfoot"
+                        | "th"
+                        | "thead"
+                        | "tr"
+                ) =>
+            {
+                if self.close_caption() {
+                    self.reprocess(InsertionMode::InTable, token, tokenizer);
+                }
+            }
+            Token::EndTag(ref tag) if tag.name == "table" => {
+                if self.close_caption() {
+                    self.reprocess(InsertionMode::InTable, token, tokenizer);
+                }
+            }
+            Token::EndTag(ref tag)
+                if matches!(
+                    tag.name.as_str(),
+                    "body"
+                        | "col"
+                        | "colgroup"
+                        | "html"
+                        | "tbody"
+                        | "td"
+                        | "tfoot"
+                        | "th"
+                        | "thead"
+                        | "tr"
+                ) =>
+            {
+                self.parse_error("unexpected-end-tag");
+            }
+            token => self.in_body(token, tokenizer),
+        }
+    }
+
+    fn in_column_group(&mut self, token: Token, tokenizer: &mut Tokenizer) {
+        let token = match token {
+            Token::Characters(text) => {
+                let (whitespace, rest) = split_leading_whitespace(&text);
+                self.insert_characters(whitespace);
+
+                if rest.is_empty() {
+                    return;
+                }
+
+                Token::Characters(rest.to_owned())
+            }
+            Token::Comment(text) => return self.insert_comment(text, None),
+            Token::Doctype(_) => return self.parse_error("unexpected-doctype"),
+            Token::StartTag(ref tag) if tag.name == "html" => {
+                return self.in_body(token, tokenizer)
+            }
+            Token::StartTag(tag) if tag.name == "col" => {
+                self.insert_element(tag);
+                self.pop();
+                return;
+            }
+            Token::EndTag(ref tag) if tag.name == "colgroup" => {
+                if !self.current_is("colgroup") {
+                    return self.parse_error("unexpected-end-tag");
+                }
+
+                self.pop();
+                self.mode = InsertionMode::InTable;
+                return;
+            }
+            Token::EndTag(ref tag) if tag.name == "col" => {
+                return self.parse_error("unexpected-end-tag")
+            }
+            Token::StartTag(ref tag) if tag.name == "template" => {
+                return self.in_head(token, tokenizer)
+            }
+            Token::EndTag(ref tag) if tag.name == "template" => {
+                return self.in_head(token, tokenizer)
+            }
+            Token::Eof => return self.in_body(token, tokenizer),
+            token => token,
+        };
+
+        if !self.current_is("colgroup") {
+            return self.parse_error("unexpected-token-in-column-group");
+        }
+
+        self.pop();
+        self.reprocess(InsertionMode::InTable, token, tokenizer);
+    }
+
+    fn in_table_body(&mut self, token: Token, tokenizer: &mut Tokenizer) {
+        match token {
+            Token::StartTag(tag) if tag.name == "tr" => {
+                self.clear_stack_back_to_table_body_context();
+                self.insert_element(tag);
+                self.mode = InsertionMode::InRow;
+            }
+            Token::StartTag(ref tag) if matches!(tag.name.as_str(), "th" | "td") => {
+                self.parse_error("unexpected-start-tag");
+                self.clear_stack_back_to_table_body_context();
+                self.insert_element(tag_named("tr"));
+                self.reprocess(InsertionMode::InRow, token, tokenizer);
+            }
+            Token::EndTag(ref tag) if matches!(tag.name.as_str(), "tbody" | "tfoot" | "thead") => {
+                if !self.has_in_scope(&tag.name, Scope::Table) {
+                    return self.parse_error("unexpected-end-tag");
+                }
+
+                self.clear_stack_back_to_table_body_context();
+                self.pop();
+                self.mode = InsertionMode::InTable;
+            }
+            Token::StartTag(ref tag)
+                if matches!(
+                    tag.name.as_str(),
+                    "caption" | "col" | "colgroup" | "tbody" | "tfoot" | "thead"
+                ) =>
+            {
+                self.close_table_body(token, tokenizer)
+            }
+            Token::EndTag(ref tag) if tag.name == "table" => {
+                self.close_table_body(token, tokenizer)
+            }
+            Token::EndTag(ref tag)
+                if matches!(
+                    tag.name.as_str(),
+                    "body" | "caption" | "col" | "colgroup" | "html" | "td" | "th" | "tr"
+                ) =>
+            {
+                self.parse_error("unexpected-end-tag");
+            }
+            token => self.in_table(token, tokenizer),
+        }
+    }
+
+    fn close_table_body(&mut self, token: Token, tokenizer: &mut Tokenizer) {
+        if !self.has_one_of_in_scope(&["tbody", "thead", "tfoot"], Scope::Table) {
+            return self.parse_error("unexpected-token-in-table-body");
+        }
+
+        self.clear_stack_back_to_table_body_context();
+        self.pop();
+        self.reprocess(InsertionMode::InTable, token, tokenizer);
+    }
+
+    fn close_row(&mut self) -> bool {
+        if !self.has_in_scope("tr", Scope::Table) {
+            self.parse_error("unexpected-token-in-row");
+            return false;
+        }
+
+        self.clear_stack_back_to_table_row_context();
+        self.pop();
+        self.mode = InsertionMode::InTableBody;
+
+        true
+    }
+
+    fn in_row(&mut self, token: Token, tokenizer: &mut Tokenizer) {
+        match token {
+            Token::StartTag(tag) if matches!(tag.name.as_str(), "th" | "td") => {
+                self.clear_stack_back_to_table_row_context();
+                self.insert_element(tag);
+                self.mode = InsertionMode::InCell;
+                self.active_formatting.push(FormattingEntry::Marker);
+            }
+            Token::EndTag(ref tag) if tag.name == "tr" => {
+                self.close_row();
+            }
+            Token::StartTag(ref tag)
+                if matches!(
+                    tag.name.as_str(),
+                    "caption" | "col" | "colgroup" | "tbody" | "tfoot" | "thead" | "tr"
+                ) =>
+            {
+                if self.close_row() {
+                    self.reprocess(InsertionMode::InTableBody, token, tokenizer);
+                }
+            }
+            Token::EndTag(ref tag) if tag.name == "table" => {
+                if self.close_row() {
+                    self.reprocess(InsertionMode::InTableBody, token, tokenizer);
+                }
+            }
+            Token::EndTag(ref tag) if matches!(tag.name.as_str(), "tbody" | "tfoot" | "thead") => {
+                if !self.has_in_scope(&tag.name, Scope::Table) {
+                    return self.parse_error("unexpected-end-tag");
+                }
+
+                if self.close_row() {
+                    self.reprocess(InsertionMode::InTableBody, token, tokenizer);
+                }
+            }
+            Token::EndTag(ref tag)
+                if matches!(
+                    tag.name.as_str(),
+                    "body" | "caption" | "col" | "colgroup" | "html" | "td" | "th"
+                ) =>
+            {
+                self.parse_error("unexpected-end-tag");
+            }
+            token => self.in_table(token, tokenizer),
+        }
+    }
+
+    fn close_cell(&mut self) {
+        self.generate_implied_end_tags(None);
+
+        if !self.current_is_one_of(&["td", "th"]) {
+            self.parse_error("unexpected-end-tag");
+        }
+
+        self.pop_until_one_of(&["td", "th"]);
+        self.clear_active_formatting_to_last_marker();
+        self.mode = InsertionMode::InRow;
+    }
+
+    fn in_cell(&mut self, token: Token, tokenizer: &mut Tokenizer) {
+        match token {
+            Token::EndTag(ref tag) if matches!(tag.name.as_str(), "td" | "th") => {
+                if !self.has_in_scope(&tag.name, Scope::Table) {
+                    return self.parse_error("unexpected-end-tag");
+                }
+
+                self.generate_implied_end_tags(None);
+
+                if !self.current_is(&tag.name) {
+                    self.parse_error("unexpected-end-tag");
+                }
+
+                self.pop_until(&tag.name);
+                self.clear_active_formatting_to_last_marker();
+                self.mode = InsertionMode::InRow;
+            }
+            Token::StartTag(ref tag)
+                if matches!(
+                    tag.name.as_str(),
+                    "caption"
+                        | "col"
+                        | "colgroup"
+                        | "tbody"
+                        | "td"
+                        | "tfoot"
+                        | "th"
+                        | "thead"
+                        | "tr"
+                ) =>
+            {
+                if !self.has_one_of_in_scope(&["td", "th"], Scope::Table) {
+                    return self.parse_error("unexpected-start-tag");
+                }
+
+                self.close_cell();
+                self.reprocess(InsertionMode::InRow, token, tokenizer);
+            }
+            Token::EndTag(ref tag)
+                if matches!(
+                    tag.name.as_str(),
+                    "body" | "caption" | "col" | "colgroup" | "html"
+                ) =>
+            {
+                self.parse_error("unexpected-end-tag");
+            }
+            Token::EndTag(ref tag)
+                if matches!(
+                    tag.name.as_str(),
+                    "table" | "tbody" | "tfoot" | "thead" | "tr"
+                ) =>
+            {
+                if !self.has_in_scope(&tag.name, Scope::Table) {
+                    return self.parse_error("unexpected-end-tag");
+                }
+
+                self.close_cell();
+                self.reprocess(InsertionMode::InRow, token, tokenizer);
+            }
+            token => self.in_body(token, tokenizer),
+        }
+    }
+
+    fn close_select(&mut self) -> bool {
+        if !self.has_in_scope("select", Scope::Select) {
+            return false;
+        }
+
+        self.pop_until("select");
+        self.reset_insertion_mode();
+
+        true
+    }
+
+    fn in_select(&mut self, token: Token, tokenizer: &mut Tokenizer) {
+        match token {
+            Token::Characters(text) => {
+                if text.contains('\0') {
+                    self.parse_error("unexpected-null-character");
+                }
+
+                self.insert_characters(&text.replace('\0', ""));
+            }
+            Token::Comment(text) => self.insert_comment(text, None),
+            Token::Doctype(_) => self.parse_error("unexpected-doctype"),
+            Token::StartTag(tag) => match tag.name.as_str() {
+                "html" => self.in_body(Token::StartTag(tag), tokenizer),
+                "option" => {
+                    if self.current_is("option") {
+                        self.pop();
+                    }
+
+                    self.insert_element(tag);
+                }
+                "optgroup" | "hr" => {
+                    if self.current_is("option") {
+                        self.pop();
+                    }
+
+                    if self.current_is("optgroup") {
+                        self.pop();
+                    }
+
+                    let void = tag.name == "hr";
+                    self.insert_element(tag);
+
+                    if void {
+                        self.pop();
+                    }
+                }
+                "select" => {
+                    self.parse_error("unexpected-start-tag");
+                    self.close_select();
+                }
+                "input" | "keygen" | "textarea" => {
+                    self.parse_error("unexpected-start-tag");
+
+                    if self.close_select() {
+                        self.process_in_mode(self.mode, Token::StartTag(tag), tokenizer);
+                    }
+                }
+                "script" | "template" => self.in_head(Token::StartTag(tag), tokenizer),
+                _ => self.parse_error("unexpected-start-tag"),
+            },
+            Token::EndTag(tag) => match tag.name.as_str() {
+                "optgroup" => {
+                    if self.current_is("option") {
+                        let previous = self
+                            .open_elements
+                            .len()
+                            .checked_sub(2)
+                            .map(|index| self.open_elements[index]);
+
+                        if previous.is_some_and(|node| self.is_html(node, "optgroup")) {
+                            self.pop();
+                        }
+                    }
+
+                    if self.current_is("optgroup") {
+                        self.pop();
+                    } else {
+                        self.parse_error("unexpected-end-tag");
+                    }
+                }
+                "option" => {
+                    if self.current_is("option") {
+                        self.pop();
+                    } else {
+                        self.parse_error("unexpected-end-tag");
+                    }
+                }
+                "select" => {
+                    if !self.close_select() {
+                        self.parse_error("unexpected-end-tag");
+                    }
+                }
+                "template" => self.in_head(Token::EndTag(tag), tokenizer),
+                _ => self.parse_error("unexpected-end-tag"),
+            },
+            Token::Eof => self.in_body(token, tokenizer),
+        }
+    }
+
+    fn in_select_in_table(&mut self, token: Token, tokenizer: &mut Tokenizer) {
+        const TABLE_ELEMENTS: &[&str] = &[
+            "caption", "table", "tbody", "tfoot", "thead", "tr", "td", "th",
+        ];
+
+        match token {
+            Token::StartTag(ref tag) if TABLE_ELEMENTS.contains(&tag.name.as_str()) => {
+                self.parse_error("unexpected-start-tag");
+                self.pop_until("select");
+                self.reset_insertion_mode();
+                self.process_in_mode(self.mode, token, tokenizer);
+            }
+            Token::EndTag(ref tag) if TABLE_ELEMENTS.contains(&tag.name.as_str()) => {
+                self.parse_error("unexpected-end-tag");
+
+                if self.has_in_scope(&tag.name, Scope::Table) {
+                    self.pop_until("select");
+                    self.reset_insertion_mode();
+                    self.process_in_mode(self.mode, token, tokenizer);
+                }
+            }
+            token => self.in_select(token, tokenizer),
+        }
+    }
+
+    fn in_template(&mut self, token: Token, tokenizer: &mut Tokenizer) {
+        let mode = match token {
+            Token::Characters(_) | Token::Comment(_) | Token::Doctype(_) => {
+                return self.in_body(token, tokenizer);
+            }
+            Token::StartTag(ref tag)
+                if matches!(
+                    tag.name.as_str(),
+                    "base"
+                        | "basefont"
+                        | "bgsound"
+                        | "link"
+                        | "meta"
+                        | "noframes"
+                        | "script"
+                        | "style"
+                        | "template"
+                        | "title"
+                ) =>
+            {
+                return self.in_head(token, tokenizer);
+            }
+            Token::EndTag(ref tag) if tag.name == "template" => {
+                return self.in_head(token, tokenizer)
+            }
+            Token::StartTag(ref tag) => match tag.name.as_str() {
+                "caption" | "colgroup" | "tbody" | "tfoot" | "thead" => InsertionMode::InTable,
+                "col" => InsertionMode::InColumnGroup,
+                "tr" => InsertionMode::InTableBody,
+                "td" | "th" => InsertionMode::InRow,
+                _ => InsertionMode::InBody,
+            },
+            Token::EndTag(_) => return self.parse_error("unexpected-end-tag"),
+            Token::Eof => {
+                if !self
+                    .open_elements
+                    .iter()
+                    .any(|&node| self.is_html(node, "template"))
+                {
+                    return self.stop_parsing();
+                }
+
+                // Todos os templates abertos são fechados aqui, em vez de
+                // reprocessar o EOF uma vez para cada um, o que estouraria a
+                // pilha com templates muito aninhados
+                while self.template_modes.pop().is_some() {
+                    self.parse_error("eof-in-template");
+                    self.pop_until("template");
+                    self.clear_active_formatting_to_last_marker();
+                }
+
+                self.reset_insertion_mode();
+                return self.reprocess(self.mode, token, tokenizer);
+            }
+        };
+
+        self.template_modes.pop();
+        self.template_modes.push(mode);
+        self.reprocess(mode, token, tokenizer);
+    }
+
+    fn after_body(&mut self, token: Token, tokenizer: &mut Tokenizer) {
+        let token = match token {
+            Token::Characters(ref text) if text.chars().all(is_whitespace) => {
+                return self.in_body(token, tokenizer);
+            }
+            Token::Comment(text) => {
+                let html = self.open_elements.first().copied();
+                return self.insert_comment(text, html);
+            }
+            Token::Doctype(_) => return self.parse_error("unexpected-doctype"),
+            Token::StartTag(ref tag) if tag.name == "html" => {
+                return self.in_body(token, tokenizer)
+            }
+            Token::EndTag(ref tag) if tag.name == "html" => {
+                self.mode = InsertionMode::AfterAfterBody;
+                return;
+            }
+            Token::Eof => return self.stop_parsing(),
+            token => token,
+        };
+
+        self.parse_error("unexpected-token-after-body");
+        self.reprocess(InsertionMode::InBody, token, tokenizer);
+    }
+
+    fn in_frameset(&mut self, token: Token, tokenizer: &mut Tokenizer) {
+        match token {
+            Token::Characters(text) => {
+                let whitespace = text
+                    .chars()
+                    .filter(|&c| is_whitespace(c))
+                    .collect::<String>();
+                self.insert_characters(&whitespace);
+            }
+            Token::Comment(text) => self.insert_comment(text, None),
+            Token::Doctype(_) => self.parse_error("unexpected-doctype"),
+            Token::StartTag(tag) => match tag.name.as_str() {
+                "html" => self.in_body(Token::StartTag(tag), tokenizer),
+                "frameset" => {
+                    self.insert_element(tag);
+                }
+                "frame" => {
+                    self.insert_element(tag);
+                    self.pop();
+                }
+                "noframes" => self.in_head(Token::StartTag(tag), tokenizer),
+                _ => self.parse_error("unexpected-start-tag"),
+            },
+            Token::EndTag(ref tag) if tag.name == "frameset" => {
+                if self.open_elements.len() <= 1 {
+                    return self.parse_error("unexpected-end-tag");
+                }
+
+                self.pop();
+
+                if !self.current_is("frameset") {
+                    self.mode = InsertionMode::AfterFrameset;
+                }
+            }
+            Token::EndTag(_) => self.parse_error("unexpected-end-tag"),
+            Token::Eof => self.stop_parsing(),
+        }
+    }
+
+    fn after_frameset(&mut self, token: Token, tokenizer: &mut Tokenizer) {
+        match token {
+            Token::Characters(text) => {
+                let whitespace = text
+                    .chars()
+                    .filter(|&c| is_whitespace(c))
+                    .collect::<String>();
+                self.insert_characters(&whitespace);
+            }
+            Token::Comment(text) => self.insert_comment(text, None),
+            Token::Doctype(_) => self.parse_error("unexpected-doctype"),
+            Token::StartTag(ref tag) if tag.name == "html" => self.in_body(token, tokenizer),
+            Token::StartTag(ref tag) if tag.name == "noframes" => self.in_head(token, tokenizer),
+            Token::EndTag(ref tag) if tag.name == "html" => {
+                self.mode = InsertionMode::AfterAfterFrameset
+            }
+            Token::Eof => self.stop_parsing(),
+            _ => self.parse_error("unexpected-token-after-frameset"),
+        }
+    }
+
+    fn after_after_body(&mut self, token: Token, tokenizer: &mut Tokenizer) {
+        match token {
+            Token::Comment(text) => self.insert_comment(text, Some(DOCUMENT)),
+            Token::Doctype(_) => self.in_body(token, tokenizer),
+            Token::Characters(ref text) if text.chars().all(is_whitespace) => {
+                self.in_body(token, tokenizer)
+            }
+            Token::StartTag(ref tag) if tag.name == "html" => self.in_body(token, tokenizer),
+            Token::Eof => self.stop_parsing(),
+            token => {
+                self.parse_error("unexpected-token-after-body");
+                self.reprocess(InsertionMode::InBody, token, tokenizer);
+            }
+        }
+    }
+
+    fn after_after_frameset(&mut self, token: Token, tokenizer: &mut Tokenizer) {
+        match token {
+            Token::Comment(text) => self.insert_comment(text, Some(DOCUMENT)),
+            Token::Doctype(_) => self.in_body(token, tokenizer),
+            Token::Characters(ref text) if text.chars().all(is_whitespace) => {
+                self.in_body(token, tokenizer)
+            }
+            Token::StartTag(ref tag) if tag.name == "html" => self.in_body(token, tokenizer),
+            Token::StartTag(ref tag) if tag.name == "noframes" => self.in_head(token, tokenizer),
+            Token::Eof => self.stop_parsing(),
+            _ => self.parse_error("unexpected-token-after-frameset"),
+        }
+    }
+
+    // Regras para conteúdo estrangeiro (SVG e MathML)
+    fn process_foreign_content(&mut self, token: Token, tokenizer: &mut Tokenizer) {
+        match token {
+            Token::Characters(text) => {
+                let text = if text.contains('\0') {
+                    self.parse_error("unexpected-null-character");
+                    text.replace('\0', "\u{FFFD}")
+                } else {
+                    text
+                };
+
+                if !text.chars().all(is_whitespace) {
+                    self.frameset_ok = false;
+                }
+
+                self.insert_characters(&text);
+            }
+            Token::Comment(text) => self.insert_comment(text, None),
+            Token::Doctype(_) => self.parse_error("unexpected-doctype"),
+            Token::StartTag(tag) if Self::breaks_out_of_foreign_content(&tag) => {
+                self.parse_error("unexpected-html-element-in-foreign-content");
+                self.pop_foreign_elements();
+                self.process_in_mode(self.mode, Token::StartTag(tag), tokenizer);
+            }
+            Token::StartTag(tag) => {
+                let namespace = self
+                    .adjusted_current_node()
+                    .map_or(Namespace::Html, |node| self.namespace(node));
+                let self_closing = tag.self_closing;
+
+                self.insert_foreign_element(tag, namespace);
+
+                if self_closing {
+                    self.pop();
+                }
+            }
+            Token::EndTag(tag) if tag.name == "br" || tag.name == "p" => {
+                self.parse_error("unexpected-html-element-in-foreign-content");
+                self.pop_foreign_elements();
+                self.process_in_mode(self.mode, Token::EndTag(tag), tokenizer);
+            }
+            Token::EndTag(tag) => {
+                let mut index = self.open_elements.len() - 1;
+
+                if !self
+                    .name(self.open_elements[index])
+                    .eq_ignore_ascii_case(&tag.name)
+                {
+                    self.parse_error("unexpected-end-tag");
+                }
+
+                loop {
+                    if index == 0 {
+                        return;
+                    }
+
+                    let node = self.open_elements[index];
+
+                    if self.name(node).eq_ignore_ascii_case(&tag.name) {
+                        self.truncate_open_elements(index);
+                        return;
+                    }
+
+                    index -= 1;
+
+                    if self.namespace(self.open_elements[index]) == Namespace::Html {
+                        return self.process_in_mode(self.mode, Token::EndTag(tag), tokenizer);
+                    }
+                }
+            }
+            Token::Eof => self.process_in_mode(self.mode, Token::Eof, tokenizer),
+        }
+    }
+
+    // Retira da pilha os elementos SVG e MathML até chegar em um elemento
+    // HTML ou em um ponto de integração
+    fn pop_foreign_elements(&mut self) {
+        while let Some(node) = self.current_node() {
+            if self.namespace(node) == Namespace::Html
+                || self.is_mathml_text_integration_point(node)
+                || self.is_html_integration_point(node)
+            {
+                break;
+            }
+
+            self.pop();
+        }
+    }
+
+    fn breaks_out_of_foreign_content(tag: &Tag) -> bool {
+        match tag.name.as_str() {
+            "b" | "big" | "blockquote" | "body" | "br" | "center" | "code" | "dd" | "div"
+            | "dl" | "dt" | "em" | "embed" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "head"
+            | "hr" | "i" | "img" | "li" | "listing" | "menu" | "meta" | "nobr" | "ol" | "p"
+            | "pre" | "ruby" | "s" | "small" | "span" | "strong" | "strike" | "sub" | "sup"
+            | "table" | "tt" | "u" | "ul" | "var" => true,
+            "font" => tag
+                .attributes
+                .iter()
+                .any(|attribute| matches!(attribute.name.as_str(), "color" | "face" | "size")),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dom::{Node, QuirksMode};
+    use crate::html::{parse, parse_with_diagnostics};
+
+    fn html(input: &str) -> String {
+        parse(input.to_owned()).outer_html()
+    }
+
+    #[test]
+    fn implied_elements() {
+        assert_eq!(html(""), "<html><head></head><body></body></html>");
+        assert_eq!(html("x"), "<html><head></head><body>x</body></html>");
+        assert_eq!(
+            html("<title>t</title><p>a<p>b"),
+            "<html><head><title>t</title></head><body><p>a</p><p>b</p></body></html>"
+        );
+    }
+
+    #[test]
+    fn quirks_mode_from_doctype() {
+        let quirks_mode = |input: &str| match parse(input.to_owned()) {
+            Node::Document(document) => document.quirks_mode,
+            _ => unreachable!(),
+        };
+
+        assert_eq!(quirks_mode("<!DOCTYPE html>"), QuirksMode::NoQuirks);
+        assert_eq!(quirks_mode("<p>"), QuirksMode::Quirks);
+        assert_eq!(
+            quirks_mode(r#"<!DOCTYPE html PUBLIC "-//W3C//DTD XHTML 1.0 Transitional//EN" "x">"#),
+            QuirksMode::LimitedQuirks
+        );
+    }
+
+    #[test]
+    fn adoption_agency() {
+        assert_eq!(
+            html("<b>1<p>2</b>3"),
+            "<html><head></head><body><b>1</b><p><b>2</b>3</p></body></html>"
+        );
+        assert_eq!(
+            html("<a><a>x"),
+            "<html><head></head><body><a></a><a>x</a></body></html>"
+        );
+    }
+
+    #[test]
+    fn tables() {
+        assert_eq!(
+            html("<table><td>x</table>"),
+            "<html><head></head><body><table><tbody><tr><td>x</td></tr></tbody></table></body></html>"
+        );
+        assert_eq!(
+            html("<table>a<tr>"),
+            "<html><head></head><body>a<table><tbody><tr></tr></tbody></table></body></html>"
+        );
+    }
+
+    #[test]
+    fn foreign_content() {
+        assert_eq!(
+            html("<svg><foreignObject><p>x</p></foreignObject></svg>"),
+            "<html><head></head><body><svg><foreignObject><p>x</p></foreignObject></svg></body></html>"
+        );
+        assert_eq!(
+            html("<svg><g><div>x"),
+            "<html><head></head><body><svg><g></g></svg><div>x</div></body></html>"
+        );
+        assert_eq!(
+            html("<svg><g></p>x"),
+            "<html><head></head><body><svg><g></g></svg><p></p>x</body></html>"
+        );
+        assert_eq!(
+            html("<math><mrow></br>y"),
+            "<html><head></head><body><math><mrow></mrow></math><br>y</body></html>"
+        );
+    }
+
+    #[test]
+    fn template() {
+        assert_eq!(
+            html("<template><td>x</td></template>"),
+            "<html><head><template><td>x</td></template></head><body></body></html>"
+        );
+        assert_eq!(
+            html("<template><tr><td>a</td></tr></template><p>b"),
+            "<html><head><template><tr><td>a</td></tr></template></head><body><p>b</p></body></html>"
+        );
+        assert_eq!(
+            html("<template><div>a"),
+            "<html><head><template><div>a</div></template></head><body></body></html>"
+        );
+    }
+
+    // Medida sem recursão, para que uma regressão falhe no assert e não
+    // com estouro de pilha
+    fn depth(node: &Node) -> usize {
+        let mut stack = vec![(node, 1)];
+        let mut max = 0;
+
+        while let Some((node, depth)) = stack.pop() {
+            max = max.max(depth);
+            stack.extend(node.children().iter().map(|child| (child, depth + 1)));
+        }
+
+        max
+    }
+
+    #[test]
+    fn deep_nesting_is_limited() {
+        let document = parse("<div>".repeat(2_000));
+
+        assert_eq!(document.descendant_elements().count(), 2_003);
+        assert!(depth(&document) <= super::MAX_TREE_DEPTH + 2);
+    }
+
+    #[test]
+    fn deep_misnested_formatting_is_limited() {
+        // Cada `<a>` roda o adoption agency, que reinsere elementos com
+        // pais explícitos em vez do nó atual
+        let document = parse("<div><span><a><b>".repeat(2_000));
+
+        assert!(depth(&document) <= super::MAX_TREE_DEPTH + 2);
+        assert_eq!(
+            document
+                .descendant_elements()
+                .filter(|element| element.tag_name == "div")
+                .count(),
+            2_000
+        );
+    }
+
+    #[test]
+    fn deep_input_does_not_overflow_the_stack() {
+        // Os testes rodam na pilha padrão das threads de teste, então
+        // qualquer recursão proporcional à profundidade da entrada estoura
+        for input in [
+            "<div><span><a><b>".repeat(5_000),
+            "<template>".repeat(50_000),
+        ] {
+            let document = parse(input);
+            let html = document.outer_html();
+
+            assert!(depth(&document) <= super::MAX_TREE_DEPTH + 2);
+            assert!(html.starts_with("<html><head>"));
+            assert!(html.ends_with("</body></html>"));
+
+            drop(document);
+        }
+    }
+
+    #[test]
+    fn document_span_starts_at_line_one() {
+        let document = parse(String::new());
+
+        assert_eq!(
+            (document.span().start.line, document.span().start.column),
+            (1, 1)
+        );
+        assert_eq!(
+            (document.span().end.line, document.span().end.column),
+            (1, 1)
+        );
+
+        let document = parse("<p>a\nb".to_owned());
+        let span = document.span();
+
+        assert_eq!((span.start.line, span.start.column), (1, 1));
+        assert_eq!((span.end.line, span.end.column), (2, 2));
+    }
+
+    #[test]
+    fn diagnostics() {
+        let result = parse_with_diagnostics("<!DOCTYPE html><p></div>".to_owned());
+
+        assert_eq!(result.diagnostics.len(), 1);
+        assert_eq!(result.diagnostics[0].kind, "unexpected-end-tag");
+        assert_eq!(result.diagnostics[0].span.start.offset, 18);
+    }
+}
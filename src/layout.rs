@@ -1,7 +1,4 @@
-use crate::{
-    css::{CSSUnit, CSSValue},
-    style::StyledNode,
-};
+use crate::style::{Display, LengthPercentageAuto, StyledNode};
 
 #[derive(Debug, Default, Clone)]
 pub struct BoxDimensions {
@@ -81,30 +78,23 @@ pub fn layout_node<'a>(styled_node: &'a StyledNode, mut containing_block: BoxDim
     layout_box
 }
 
-impl LayoutBox<'_> {
-    fn get_style_node(&self) -> &StyledNode {
-        if let BoxType::Block(node) | BoxType::Inline(node) = self.box_type {
-            node
-        } else {
-            panic!("Sem um styled node para este layout node")
-        }
-    }
-
+impl<'a> LayoutBox<'a> {
     fn layout(&mut self, containing_block: &BoxDimensions) {
         // No momento apenas o block layout é implementado
-        if let BoxType::AnonymousBlock | BoxType::Inline(_) = self.box_type {
-            return;
-        }
+        let styled_node = match self.box_type {
+            BoxType::Block(styled_node) => styled_node,
+            BoxType::AnonymousBlock | BoxType::Inline(_) => return,
+        };
 
         // Faz uma passada na árvore de cima para baixo para calcular
         // as larguras das caixas pais e de baixo para cima
         // para calcular a altura das caixas filhas
         
         // Calcula a largura do bloco em relação ao seu containing block
-        self.calculate_block_width(containing_block);
+        self.calculate_block_width(styled_node, containing_block);
 
         // Calcula a posição do bloco em relação ao seu containing block
-        self.calculate_block_position(containing_block);
+        self.calculate_block_position(styled_node, containing_block);
 
         // Calcula a altura do box a partir de seus filhos
         // Assim vamos subindo na pilha de chamadas
@@ -119,23 +109,24 @@ impl LayoutBox<'_> {
 
         // Substitui a altura do bloco pela propriedade `height`
         // Se não houver, irá ser calculado automaticamente.
-        if let Some(&CSSValue::Length(height, CSSUnit::Px)) = self.get_style_node().specified_properties.get("height") {
+        if let LengthPercentageAuto::Length(height) = styled_node.computed_style.height {
             self.dimensions.content.height = height;
         }
     }
 
     // Calcula a posição do bloco junto com o tamanho do padding/border/margin
-    fn calculate_block_position(&mut self, containing_block: &BoxDimensions) {
-        let styled_node = self.get_style_node();
-        
-        let zero = CSSValue::Length(0.0, CSSUnit::Px);
+    fn calculate_block_position(&mut self, styled_node: &StyledNode, containing_block: &BoxDimensions) {
+        let style = &styled_node.computed_style;
 
-        let margin_top = styled_node.lookup_property_value(["margin-top", "margin"], &zero).to_px();
-        let margin_bottom = styled_node.lookup_property_value(["margin-bottom", "margin"], &zero).to_px();
-        let padding_top = styled_node.lookup_property_value(["padding-top", "padding"], &zero).to_px();
-        let padding_bottom = styled_node.lookup_property_value(["padding-bottom", "padding"], &zero).to_px();
-        let border_top = styled_node.lookup_property_value(["border-top", "border"], &zero).to_px();
-        let border_bottom = styled_node.lookup_property_value(["border-bottom", "border"], &zero).to_px();
+        // Porcentagens verticais também são relativas à largura
+        let reference = containing_block.content.width;
+
+        let margin_top = style.margin_top.to_px(reference).unwrap_or(0.0);
+        let margin_bottom = style.margin_bottom.to_px(reference).unwrap_or(0.0);
+        let padding_top = style.padding_top.to_px(reference);
+        let padding_bottom = style.padding_bottom.to_px(reference);
+        let border_top = style.border_top_width;
+        let border_bottom = style.border_bottom_width;
 
         let d = &mut self.dimensions;
 
@@ -148,8 +139,6 @@ impl LayoutBox<'_> {
         d.padding.bottom = padding_bottom;
         d.border.top = border_top;
         d.border.bottom = border_bottom;
-        d.padding.top = padding_top;
-        d.padding.bottom = padding_bottom;
 
         d.content.x = content_x;
         d.content.y = content_y;
@@ -157,125 +146,120 @@ impl LayoutBox<'_> {
 
     // Calcula a largura desta block box relativo
     // às dimensões um containing block (que é outra caixa).
-    fn calculate_block_width(&mut self, containing_block: &BoxDimensions) {
-        let styled_node = self.get_style_node();
-
-        let zero = CSSValue::Length(0.0, CSSUnit::Px);
+    fn calculate_block_width(&mut self, styled_node: &StyledNode, containing_block: &BoxDimensions) {
+        let style = &styled_node.computed_style;
+        let reference = containing_block.content.width;
 
-        let mut margin_left = styled_node.lookup_property_value(["margin-left", "margin"], &zero).to_owned();
-        let mut margin_right = styled_node.lookup_property_value(["margin-right", "margin"], &zero).to_owned();
+        // `None` representa `auto`
+        let mut margin_left = style.margin_left.to_px(reference);
+        let mut margin_right = style.margin_right.to_px(reference);
 
-        let border_left = styled_node.lookup_property_value(["border-left", "border"], &zero).to_owned();
-        let border_right = styled_node.lookup_property_value(["border-right", "border"], &zero).to_owned();
+        let border_left = style.border_left_width;
+        let border_right = style.border_right_width;
 
-        let padding_left = styled_node.lookup_property_value(["padding-left", "padding"], &zero).to_owned();
-        let padding_right = styled_node.lookup_property_value(["padding-right", "padding"], &zero).to_owned();
+        let padding_left = style.padding_left.to_px(reference);
+        let padding_right = style.padding_right.to_px(reference);
 
-        let auto = CSSValue::Keyword("auto".to_owned());
-        let mut width = styled_node.lookup_property_value(["width"], &auto).to_owned();
+        let mut width = style.width.to_px(reference);
 
-        let total = [&margin_left, &margin_right, &border_left, &border_right, &padding_left, &padding_right, &width]
+        let total = [margin_left, margin_right, width]
             .iter()
-            .map(|value| value.to_px())
-            .sum::<f32>();
+            .map(|value| value.unwrap_or(0.0))
+            .sum::<f32>()
+            + border_left
+            + border_right
+            + padding_left
+            + padding_right;
 
         let underflow = containing_block.content.width - total;
 
-        if underflow < 0.0 && width == auto {
-            if margin_left == auto {
-                margin_left = zero.clone();
-            }
-
-            if margin_right == auto {
-                margin_right = zero.clone();
-            }
+        if underflow < 0.0 && width.is_none() {
+            margin_left.get_or_insert(0.0);
+            margin_right.get_or_insert(0.0);
         }
 
         // Distribuir o espaço disponível de tal forma que
         // margin-* + border-* + padding-* + width = largura do containing block
-        match (width == auto, margin_left == auto, margin_right == auto) {
+        match (width, margin_left, margin_right) {
             // Se todos os componentes forem automáticos, o overflow deve ser adicionado à margem direita.
-            (false, false, false) => {
-                margin_right = CSSValue::Length(margin_right.to_px() + underflow, CSSUnit::Px);
+            (Some(_), Some(_), Some(right)) => {
+                margin_right = Some(right + underflow);
             },
 
             // Caso contrário, se apenas a margem direita ou apenas a margem esquerda forem automáticos, coloque o underflow neles.
-            (false, false, true) => {
-                margin_right = CSSValue::Length(underflow, CSSUnit::Px);
+            (Some(_), Some(_), None) => {
+                margin_right = Some(underflow);
             },
 
-            (false, true, false) => {
-                margin_left = CSSValue::Length(underflow, CSSUnit::Px);
+            (Some(_), None, Some(_)) => {
+                margin_left = Some(underflow);
             },
             
             // Caso contrário, se a largura for automática a largura deve ser 
             // o underflow. Se ocorrer overflow, a largura deve ser zero e a
             // margem direita deve receber o overflow.
             // Margens automáticas serão zeradas.
-            (true, _, _) => {
-                if margin_left == auto {
-                    margin_left = zero.clone();
-                }
-
-                if margin_right == auto {
-                    margin_right = zero.clone();
-                }
+            (None, _, _) => {
+                margin_left.get_or_insert(0.0);
+                let right = margin_right.get_or_insert(0.0);
 
                 if underflow >= 0.0 {
-                    width = CSSValue::Length(underflow, CSSUnit::Px);
+                    width = Some(underflow);
                 } else {
-                    width = zero.clone();
-                    margin_right = CSSValue::Length(margin_right.to_px() + underflow, CSSUnit::Px);
+                    width = Some(0.0);
+                    *right += underflow;
                 }
             },
 
             // Caso contrário, então a margem direita e esquerda deve ter o overflow dividido igualmente.
-            (false, _, _) => {
-                margin_left = CSSValue::Length(underflow / 2.0, CSSUnit::Px);
-                margin_right = CSSValue::Length(underflow / 2.0, CSSUnit::Px);
+            (Some(_), None, None) => {
+                margin_left = Some(underflow / 2.0);
+                margin_right = Some(underflow / 2.0);
             }
         }
 
         // Adicionar as dimensões à caixa
-        self.dimensions.content.width = width.to_px();
-        self.dimensions.margin.left = margin_left.to_px();
-        self.dimensions.margin.right = margin_right.to_px();
-        self.dimensions.border.left = border_left.to_px();
-        self.dimensions.border.right = border_right.to_px();
-        self.dimensions.padding.left = padding_left.to_px();
-        self.dimensions.padding.right = padding_right.to_px();
+        self.dimensions.content.width = width.unwrap_or(0.0);
+        self.dimensions.margin.left = margin_left.unwrap_or(0.0);
+        self.dimensions.margin.right = margin_right.unwrap_or(0.0);
+        self.dimensions.border.left = border_left;
+        self.dimensions.border.right = border_right;
+        self.dimensions.padding.left = padding_left;
+        self.dimensions.padding.right = padding_right;
     }
 }
 
 // Construção da layout tree
 fn create_layout_box<'a>(styled_node: &'a StyledNode) -> LayoutBox<'a> {
     let mut layout_box = LayoutBox {
-        box_type: match styled_node.specified_properties.get("display") {
-            Some(CSSValue::Keyword(ref s)) if s == "block" => BoxType::Block(styled_node),
-            Some(CSSValue::Keyword(ref s)) if s == "none" => panic!(
-                "Não é possível construir uma layout box para um nó raiz que tem display: none"
-            ),
-            _ => BoxType::Inline(styled_node),
+        box_type: match styled_node.display() {
+            Display::Block => BoxType::Block(styled_node),
+            // Uma raiz com display: none não gera caixas, então
+            // devolvemos um bloco anônimo vazio no lugar dela
+            Display::None => return LayoutBox {
+                box_type: BoxType::AnonymousBlock,
+                dimensions: Default::default(),
+                children: vec![],
+            },
+            Display::Inline => BoxType::Inline(styled_node),
         },
         dimensions: Default::default(),
         children: vec![],
     };
 
     for child in &styled_node.children {
-        match child.specified_properties.get("display") {
-            Some(CSSValue::Keyword(ref s)) if s == "block" => {
-                layout_box.children.push(create_layout_box(child))
-            },
-            Some(CSSValue::Keyword(ref s)) if s == "none" => {},
-            _ => {
+        match child.display() {
+            Display::Block => layout_box.children.push(create_layout_box(child)),
+            Display::None => {},
+            Display::Inline => {
                 // Tratamos tudo o que não for none ou block como inline
                 // Para adicionarmos um inline node, precisamos de
                 // um inline container
                 // Um inline container será o próprio pai se o mesmo
                 // for um inline node
                 // Ou um anonymous layout box caso o pai seja do tipo bloco
-                let inline_container = match styled_node.specified_properties.get("display") {
-                    Some(CSSValue::Keyword(ref s)) if s == "block" => {
+                let inline_container = match styled_node.display() {
+                    Display::Block => {
                         // Obter o último node
                         // Se for o anonymous box, retornamos ele
                         // Caso não for, criamos um
@@ -1,6 +1,8 @@
 pub mod css;
+pub mod diagnostics;
 pub mod dom;
 pub mod html;
 pub mod layout;
 pub mod style;
-pub mod painting;
\ No newline at end of file
+pub mod painting;
+pub mod scanner;
\ No newline at end of file
@@ -1,5 +1,4 @@
 use toy_browser::{css, html, layout::{self, BoxDimensions, Rect}, style, painting};
-use image;
 
 fn main() {
     let root = html::parse("<div class=\"a\">
@@ -30,7 +29,7 @@ fn main() {
         .to_owned(),
     );
 
-    let styled = style::style_node(&root, &stylesheet);
+    let styled = style::style_node(&root, &[(style::Origin::Author, &stylesheet)]);
 
     let initial_containing_block = BoxDimensions {
         content: Rect {
@@ -1,6 +1,6 @@
-use std::iter::repeat;
+use std::iter::repeat_n;
 
-use crate::{css::{Color, CSSValue}, layout::{Rect, LayoutBox, BoxType}};
+use crate::{css::Color, layout::{Rect, LayoutBox, BoxType}};
 
 #[derive(Debug)]
 enum DisplayCommand {
@@ -19,15 +19,14 @@ impl Canvas {
         Canvas {
             width,
             height,
-            pixels: repeat(Color { r: 0xff, g: 0xff, b: 0xff, a: 0xff })
-                .take(width * height)
+            pixels: repeat_n(Color { r: 0xff, g: 0xff, b: 0xff, a: 0xff }, width * height)
                 .collect()
         }
     }
 
     fn handle_command(&mut self, command: &DisplayCommand) {
         match command {
-            &DisplayCommand::SolidColor(ref color, ref rect) => {
+            DisplayCommand::SolidColor(color, rect) => {
                 let x_start = rect.x.clamp(0.0, self.width as f32) as usize;
                 let y_start = rect.y.clamp(0.0, self.height as f32) as usize;
 
@@ -59,62 +58,67 @@ pub fn paint_node(node: &LayoutBox, bounds: Rect) -> Canvas {
 }
 
 fn render_node(display_list: &mut Vec<DisplayCommand>, node: &LayoutBox) {
-    // Desenha o fundo (a border-box, mas sem cor agora)
-    if let Some(color) = get_color(node, "background-color") {
-        display_list.push(DisplayCommand::SolidColor(color, node.dimensions.clone().border_box()));
+    // Caixas de bloco anônimas não têm fundo nem bordas
+    let style = match node.box_type {
+        BoxType::Block(styled_node) | BoxType::Inline(styled_node) => &styled_node.computed_style,
+        BoxType::AnonymousBlock => {
+            render_children(display_list, node);
+            return;
+        }
+    };
+
+    // Desenha o fundo (a border-box), a não ser que seja transparente
+    if style.background_color.a > 0 {
+        display_list.push(DisplayCommand::SolidColor(style.background_color.clone(), node.dimensions.clone().border_box()));
     }
 
-    // Desenha as bordas se uma cor for especificada
-    if let Some(color) = get_color(node, "border-color") {
-        let d = node.dimensions.clone();
-        let border_box = d.clone().border_box();
+    // Desenha as bordas, cada uma com a sua cor. Sem estilo, a largura já
+    // é zero.
+    let d = node.dimensions.clone();
+    let border_box = d.clone().border_box();
 
+    let sides = [
         // Borda superior
-        display_list.push(DisplayCommand::SolidColor(color.clone(), Rect {
+        (&style.border_top_color, Rect {
             x: border_box.x,
             y: border_box.y,
             width: border_box.width,
             height: d.border.top,
-        }));
-
+        }),
         // Borda esquerda
-        display_list.push(DisplayCommand::SolidColor(color.clone(), Rect {
+        (&style.border_left_color, Rect {
             x: border_box.x,
             y: border_box.y,
             width: d.border.left,
             height: border_box.height,
-        }));
-
+        }),
         // Borda direita
-        display_list.push(DisplayCommand::SolidColor(color.clone(), Rect {
+        (&style.border_right_color, Rect {
             x: border_box.x + border_box.width - d.border.right,
             y: border_box.y,
             width: d.border.right,
             height: border_box.height,
-        }));
-
+        }),
         // Borda inferior
-        display_list.push(DisplayCommand::SolidColor(color.clone(), Rect {
+        (&style.border_bottom_color, Rect {
             x: border_box.x,
             y: border_box.y + border_box.height - d.border.bottom,
             width: border_box.width,
             height: d.border.bottom,
-        }));
+        }),
+    ];
+
+    for (color, rect) in sides {
+        if color.a > 0 && rect.width > 0.0 && rect.height > 0.0 {
+            display_list.push(DisplayCommand::SolidColor(color.clone(), rect));
+        }
     }
 
+    render_children(display_list, node);
+}
+
+fn render_children(display_list: &mut Vec<DisplayCommand>, node: &LayoutBox) {
     for child in &node.children {
         render_node(display_list, child);
     }
 }
-
-fn get_color(layout_box: &LayoutBox, property: &str) -> Option<Color> {
-    match layout_box.box_type {
-        BoxType::Block(styled_node) | BoxType::Inline(styled_node) => match styled_node.specified_properties.get(property) {
-            Some(CSSValue::Color(color)) => Some(color.clone()),
-            _ => None,
-        }
-
-        // Caixas de bloco anônimas não tem cor
-        BoxType::AnonymousBlock => None,
-    }
-}
\ No newline at end of file
@@ -0,0 +1,137 @@
+use std::cell::Cell;
+
+use crate::diagnostics::{Position, Span};
+
+// Leitor de entrada compartilhado pelos parsers de HTML e CSS.
+// O cursor é sempre um offset em bytes que cai numa fronteira de caractere
+// UTF-8, então todas as operações são O(1) ou proporcionais ao que é consumido.
+#[derive(Debug)]
+pub struct Scanner {
+    input: String,
+    cursor: usize,
+    // Offsets de início de cada linha, para converter offsets em linha/coluna
+    line_starts: Vec<usize>,
+    // Última posição calculada, para que consultas em ordem crescente
+    // numa mesma linha longa não recontem a linha inteira
+    last_location: Cell<Position>,
+}
+
+impl Scanner {
+    pub fn new(input: String) -> Scanner {
+        let line_starts = std::iter::once(0)
+            .chain(input.match_indices('\n').map(|(index, _)| index + 1))
+            .collect();
+
+        Scanner {
+            input,
+            cursor: 0,
+            line_starts,
+            last_location: Cell::new(Position {
+                offset: 0,
+                line: 1,
+                column: 1,
+            }),
+        }
+    }
+
+    // Acrescenta entrada ao final, para parsing incremental
+    pub fn push_str(&mut self, input: &str) {
+        let start = self.input.len();
+
+        self.line_starts.extend(
+            input
+                .match_indices('\n')
+                .map(|(index, _)| start + index + 1),
+        );
+        self.input.push_str(input);
+    }
+
+    pub fn eof(&self) -> bool {
+        self.cursor >= self.input.len()
+    }
+
+    pub fn position(&self) -> usize {
+        self.cursor
+    }
+
+    // `position` deve ter sido obtido de `Scanner::position`
+    pub fn set_position(&mut self, position: usize) {
+        debug_assert!(self.input.is_char_boundary(position));
+        self.cursor = position;
+    }
+
+    pub fn rest(&self) -> &str {
+        &self.input[self.cursor..]
+    }
+
+    pub fn slice(&self, start: usize, end: usize) -> &str {
+        &self.input[start..end]
+    }
+
+    pub fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    pub fn consume_char(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.cursor += c.len_utf8();
+        Some(c)
+    }
+
+    // Avança `by` bytes, que devem corresponder a caracteres já inspecionados
+    pub fn advance_by(&mut self, by: usize) {
+        self.set_position(self.cursor + by);
+    }
+
+    pub fn starts_with(&self, s: &str) -> bool {
+        self.rest().starts_with(s)
+    }
+
+    pub fn starts_with_ignore_ascii_case(&self, s: &str) -> bool {
+        let rest = self.rest().as_bytes();
+
+        rest.len() >= s.len() && rest[..s.len()].eq_ignore_ascii_case(s.as_bytes())
+    }
+
+    pub fn location(&self, offset: usize) -> Position {
+        let offset = offset.min(self.input.len());
+        let line = self.line_starts.partition_point(|&start| start <= offset);
+        let line_start = self.line_starts[line - 1];
+        let last = self.last_location.get();
+
+        let (from, column) = if last.line == line && last.offset <= offset {
+            (last.offset, last.column)
+        } else {
+            (line_start, 1)
+        };
+
+        let position = Position {
+            offset,
+            line,
+            column: column + self.input[from..offset].chars().count(),
+        };
+
+        self.last_location.set(position);
+
+        position
+    }
+
+    pub fn span(&self, start: usize, end: usize) -> Span {
+        Span {
+            start: self.location(start),
+            end: self.location(end),
+        }
+    }
+
+    pub fn consume_while<F: Fn(char) -> bool>(&mut self, predicate: F) -> &str {
+        let start = self.cursor;
+        let length = self
+            .rest()
+            .find(|c| !predicate(c))
+            .unwrap_or(self.input.len() - start);
+
+        self.cursor += length;
+
+        &self.input[start..self.cursor]
+    }
+}
@@ -1,8 +1,21 @@
+mod computed;
+mod counters;
+mod document;
+
+pub use computed::{BorderStyle, ComputedStyle, LengthPercentage, LengthPercentageAuto};
+
+use counters::Counters;
+
 use crate::{
-    css::{CSSValue, SimpleSelector, Stylesheet},
+    css::{
+        self, AttributeOperator, AttributeSelector, CSSValue, CaseSensitivity, Combinator,
+        ComplexSelector, CompoundSelector, ContentItem, PseudoClass, PseudoElement,
+        RelativeSelector, Stylesheet,
+    },
+    diagnostics::Diagnostic,
     dom::{Element, Node},
 };
-use std::collections::HashMap;
+use std::{collections::HashMap, path::PathBuf, sync::OnceLock};
 
 type PropertyMap = HashMap<String, CSSValue>;
 
@@ -10,81 +23,784 @@ type PropertyMap = HashMap<String, CSSValue>;
 pub struct StyledNode<'a> {
     pub node: &'a Node,
     pub specified_properties: PropertyMap,
+    // Valores computados a partir de `specified_properties` e do estilo do
+    // pai, incluindo as propriedades herdadas
+    pub computed_style: ComputedStyle,
     pub children: Vec<StyledNode<'a>>,
+    // Presente nas caixas geradas por `::before` e `::after`. Nesse caso
+    // `node` é o elemento que as originou e não há filhos.
+    pub generated_content: Option<GeneratedContent>,
+}
+
+#[derive(Debug)]
+pub struct GeneratedContent {
+    pub pseudo_element: PseudoElement,
+    // Valor de `content` com `attr()` e contadores já resolvidos
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Display {
+    Inline,
+    Block,
+    None,
 }
 
 impl<'a> StyledNode<'a> {
-    pub fn lookup_property_value<'b, P>(&'a self, properties: P, default: &'a CSSValue) -> &'a CSSValue
-    where
-        P: IntoIterator<Item = &'b str>,
-    {
-        for property in properties.into_iter() {
-            if let Some(value) = self.specified_properties.get(property) {
-                return value;
-            }
+    pub fn display(&self) -> Display {
+        match self.node {
+            // O documento gera o bloco que contém o elemento raiz
+            Node::Document(_) => Display::Block,
+            Node::DocumentType(_) | Node::Comment(_) => Display::None,
+            Node::Text(_) | Node::Element(_) => self.computed_style.display,
         }
+    }
+}
 
-        default
+// Origem de uma folha de estilo, que define sua prioridade na cascata
+// (https://www.w3.org/TR/css-cascade-4/#cascading-origins). As variantes
+// estão em ordem crescente de prioridade, que é a usada por `revert`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Origin {
+    UserAgent,
+    User,
+    Author,
+}
+
+impl Origin {
+    // Nível na cascata, do menos ao mais prioritário: declarações normais
+    // seguem a ordem das origens e as `!important` a ordem inversa
+    fn precedence(self, important: bool) -> u8 {
+        match (important, self) {
+            (false, Origin::UserAgent) => 0,
+            (false, Origin::User) => 1,
+            (false, Origin::Author) => 2,
+            (true, Origin::Author) => 3,
+            (true, Origin::User) => 4,
+            (true, Origin::UserAgent) => 5,
+        }
     }
 }
 
-pub fn style_node<'a>(node: &'a Node, stylesheet: &'a Stylesheet) -> StyledNode<'a> {
-    match node {
-        Node::Text(_) => StyledNode {
-            node,
-            children: vec![],
-            specified_properties: HashMap::new(),
-        },
-        Node::Element(element) => StyledNode {
-            node,
-            children: element
-                .children
-                .iter()
-                .map(|node| style_node(node, stylesheet))
-                .collect(),
-            specified_properties: get_specified_properties(element, stylesheet),
-        },
+#[derive(Debug, Clone)]
+pub struct StyleOptions {
+    // Aplica `user_agent_stylesheet` antes das folhas informadas
+    pub user_agent_stylesheet: bool,
+    // Diretório usado para resolver o `href` de `<link rel="stylesheet">`.
+    // Só arquivos dentro dele são lidos; sem ele, os links são ignorados.
+    pub base_path: Option<PathBuf>,
+}
+
+impl Default for StyleOptions {
+    fn default() -> StyleOptions {
+        StyleOptions {
+            user_agent_stylesheet: true,
+            base_path: None,
+        }
     }
 }
 
-fn get_specified_properties(element: &Element, stylesheet: &Stylesheet) -> PropertyMap {
-    let mut specified_properties = PropertyMap::new();
+// Folha de estilo padrão do navegador, com `display`, margens e tamanhos de
+// fonte dos elementos HTML
+pub fn user_agent_stylesheet() -> &'static Stylesheet {
+    static STYLESHEET: OnceLock<Stylesheet> = OnceLock::new();
+
+    STYLESHEET.get_or_init(|| css::parse(include_str!("style/user_agent.css").to_owned()))
+}
+
+// As folhas de estilo são informadas em ordem de aparição, que desempata
+// regras de mesma origem e especificidade. As folhas do próprio documento
+// (`<style>` e `<link rel="stylesheet">`) vêm depois delas, na origem do
+// autor, e o atributo `style` de cada elemento vale como uma regra mais
+// específica que qualquer seletor.
+pub fn style_node<'a>(node: &'a Node, stylesheets: &[(Origin, &Stylesheet)]) -> StyledNode<'a> {
+    style_node_with_options(node, stylesheets, &StyleOptions::default())
+}
+
+pub fn style_node_with_options<'a>(
+    node: &'a Node,
+    stylesheets: &[(Origin, &Stylesheet)],
+    options: &StyleOptions,
+) -> StyledNode<'a> {
+    let document_stylesheets = document::stylesheets(node, options.base_path.as_deref());
+    let mut all_stylesheets =
+        Vec::with_capacity(stylesheets.len() + document_stylesheets.len() + 1);
+
+    if options.user_agent_stylesheet {
+        all_stylesheets.push((Origin::UserAgent, user_agent_stylesheet()));
+    }
+
+    all_stylesheets.extend_from_slice(stylesheets);
+    all_stylesheets.extend(
+        document_stylesheets
+            .iter()
+            .map(|stylesheet| (Origin::Author, stylesheet)),
+    );
+
+    let mut counters = Counters::default();
+
+    style_tree(
+        node,
+        None,
+        std::slice::from_ref(node),
+        0,
+        None,
+        &all_stylesheets,
+        &mut counters,
+    )
+}
+
+// Elemento junto com o pai e os irmãos, que os combinadores precisam
+// consultar. Os pais formam uma lista ligada pela pilha de chamadas do
+// percurso da árvore.
+#[derive(Clone, Copy)]
+struct ElementRef<'a> {
+    element: &'a Element,
+    // `None` se o pai não for um elemento (ex: o documento)
+    parent: Option<&'a ElementRef<'a>>,
+    // Filhos do pai, com o próprio elemento na posição `index`
+    siblings: &'a [Node],
+    index: usize,
+}
+
+impl<'a> ElementRef<'a> {
+    fn ancestors(&self) -> impl Iterator<Item = &'a ElementRef<'a>> {
+        std::iter::successors(self.parent, |parent| parent.parent)
+    }
+
+    // Irmãos anteriores que são elementos, do mais próximo ao mais distante
+    fn previous_siblings(&self) -> impl Iterator<Item = ElementRef<'a>> + '_ {
+        (0..self.index)
+            .rev()
+            .filter_map(|index| self.sibling(index))
+    }
+
+    fn following_siblings(&self) -> impl Iterator<Item = ElementRef<'a>> + '_ {
+        (self.index + 1..self.siblings.len()).filter_map(|index| self.sibling(index))
+    }
+
+    fn sibling(&self, index: usize) -> Option<ElementRef<'a>> {
+        match &self.siblings[index] {
+            Node::Element(element) => Some(ElementRef {
+                element,
+                parent: self.parent,
+                siblings: self.siblings,
+                index,
+            }),
+            _ => None,
+        }
+    }
+
+    fn is_same_type(&self, other: &ElementRef) -> bool {
+        self.element
+            .tag_name
+            .eq_ignore_ascii_case(&other.element.tag_name)
+    }
+
+    // Verifica `predicate` em cada descendente, em ordem de documento, até
+    // que algum o satisfaça
+    fn any_descendant(&self, predicate: &mut dyn FnMut(&ElementRef) -> bool) -> bool {
+        let children = &self.element.children;
+
+        children
+            .iter()
+            .enumerate()
+            .any(|(index, child)| match child {
+                Node::Element(element) => {
+                    let child = ElementRef {
+                        element,
+                        parent: Some(self),
+                        siblings: children,
+                        index,
+                    };
+
+                    predicate(&child) || child.any_descendant(predicate)
+                }
+                _ => false,
+            })
+    }
+}
+
+// Os contadores são atualizados em ordem de documento: o elemento, seu
+// `::before`, seus filhos e por fim seu `::after`
+fn style_tree<'a>(
+    node: &'a Node,
+    parent: Option<&ElementRef>,
+    siblings: &'a [Node],
+    index: usize,
+    parent_style: Option<&ComputedStyle>,
+    stylesheets: &[(Origin, &Stylesheet)],
+    counters: &mut Counters,
+) -> StyledNode<'a> {
+    let element = match node {
+        Node::Element(element) => element,
+        _ => {
+            // Texto e documento só têm as propriedades herdadas
+            let specified_properties = HashMap::new();
+            let computed_style = ComputedStyle::compute(&specified_properties, parent_style);
+
+            let mark = counters.enter();
+            let children = style_children(
+                node.children(),
+                None,
+                &computed_style,
+                stylesheets,
+                counters,
+            );
+
+            counters.leave(mark);
+
+            return StyledNode {
+                node,
+                specified_properties,
+                computed_style,
+                children,
+                generated_content: None,
+            };
+        }
+    };
+
+    let element_ref = ElementRef {
+        element,
+        parent,
+        siblings,
+        index,
+    };
+
+    let specified_properties = get_specified_properties(&element_ref, None, stylesheets);
+    let computed_style = ComputedStyle::compute(&specified_properties, parent_style);
+
+    let mut styled_node = StyledNode {
+        node,
+        specified_properties,
+        computed_style,
+        children: Vec::new(),
+        generated_content: None,
+    };
+
+    // Elementos sem caixa não geram conteúdo nem alteram contadores
+    if styled_node.display() == Display::None {
+        styled_node.children = style_children(
+            &element.children,
+            Some(&element_ref),
+            &styled_node.computed_style,
+            stylesheets,
+            &mut Counters::default(),
+        );
+
+        return styled_node;
+    }
+
+    update_counters(&styled_node.specified_properties, counters);
+
+    let mark = counters.enter();
 
-    let mut matched_rules = stylesheet
-        .rules
+    let parent_style = &styled_node.computed_style;
+    let before = style_pseudo_element(
+        &element_ref,
+        node,
+        PseudoElement::Before,
+        parent_style,
+        stylesheets,
+        counters,
+    );
+    let children = style_children(
+        &element.children,
+        Some(&element_ref),
+        parent_style,
+        stylesheets,
+        counters,
+    );
+    let after = style_pseudo_element(
+        &element_ref,
+        node,
+        PseudoElement::After,
+        parent_style,
+        stylesheets,
+        counters,
+    );
+
+    styled_node.children.extend(before);
+    styled_node.children.extend(children);
+    styled_node.children.extend(after);
+
+    counters.leave(mark);
+
+    styled_node
+}
+
+fn style_children<'a>(
+    children: &'a [Node],
+    parent: Option<&ElementRef>,
+    parent_style: &ComputedStyle,
+    stylesheets: &[(Origin, &Stylesheet)],
+    counters: &mut Counters,
+) -> Vec<StyledNode<'a>> {
+    children
         .iter()
-        .filter_map(|rule| {
-            rule.selectors
-                .iter()
-                .find(|selector| selector_matches(selector, element))
-                .map(|selector| (selector.specificity(), rule))
+        .enumerate()
+        .map(|(index, child)| {
+            style_tree(
+                child,
+                parent,
+                children,
+                index,
+                Some(parent_style),
+                stylesheets,
+                counters,
+            )
         })
-        .collect::<Vec<_>>();
+        .collect()
+}
+
+// A caixa só é gerada se `content` não for `none` ou `normal`
+fn style_pseudo_element<'a>(
+    element: &ElementRef,
+    node: &'a Node,
+    pseudo_element: PseudoElement,
+    parent_style: &ComputedStyle,
+    stylesheets: &[(Origin, &Stylesheet)],
+    counters: &mut Counters,
+) -> Option<StyledNode<'a>> {
+    let specified_properties = get_specified_properties(element, Some(pseudo_element), stylesheets);
 
-    matched_rules.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let Some(CSSValue::Content(content)) = specified_properties.get("content") else {
+        return None;
+    };
 
-    for (_, rule) in matched_rules {
-        for declaration in rule.declarations.iter() {
-            specified_properties.insert(declaration.name.clone(), declaration.value.clone());
+    let content = content.clone();
+    let computed_style = ComputedStyle::compute(&specified_properties, Some(parent_style));
+
+    let mut styled_node = StyledNode {
+        node,
+        specified_properties,
+        computed_style,
+        children: Vec::new(),
+        generated_content: None,
+    };
+
+    if styled_node.display() == Display::None {
+        return None;
+    }
+
+    update_counters(&styled_node.specified_properties, counters);
+
+    let text = content
+        .iter()
+        .map(|item| match item {
+            ContentItem::String(value) => value.clone(),
+            ContentItem::Attr(name) => element
+                .element
+                .attributes
+                .get(name)
+                .unwrap_or_default()
+                .to_owned(),
+            ContentItem::Counter { name, style } => counters::format(counters.value(name), style),
+            ContentItem::Counters {
+                name,
+                separator,
+                style,
+            } => {
+                let values: Vec<String> = counters
+                    .values(name)
+                    .map(|value| counters::format(value, style))
+                    .collect();
+
+                if values.is_empty() {
+                    counters::format(0, style)
+                } else {
+                    values.join(separator)
+                }
+            }
+        })
+        .collect();
+
+    styled_node.generated_content = Some(GeneratedContent {
+        pseudo_element,
+        text,
+    });
+
+    Some(styled_node)
+}
+
+// `counter-reset` é aplicado antes de `counter-increment`
+fn update_counters(properties: &PropertyMap, counters: &mut Counters) {
+    if let Some(CSSValue::Counters(resets)) = properties.get("counter-reset") {
+        for (name, value) in resets {
+            counters.reset(name, *value);
+        }
+    }
+
+    if let Some(CSSValue::Counters(increments)) = properties.get("counter-increment") {
+        for (name, by) in increments {
+            counters.increment(name, *by);
+        }
+    }
+}
+
+// Propriedades de um elemento ou, com `pseudo_element`, de um dos seus
+// pseudo-elementos. As declarações são ordenadas por origem e importância,
+// depois pela especificidade (o atributo `style` acima de qualquer seletor,
+// senão a maior entre os seletores da regra que correspondem) e por fim
+// pela ordem de aparição, e a última vence. Uma declaração vencedora com
+// `revert` dá lugar à que venceria sem a sua origem e as posteriores; sem
+// nenhuma, a propriedade fica ausente, como com `unset`.
+fn get_specified_properties(
+    element: &ElementRef,
+    pseudo_element: Option<PseudoElement>,
+    stylesheets: &[(Origin, &Stylesheet)],
+) -> PropertyMap {
+    let mut specified_properties = PropertyMap::new();
+
+    // Pseudo-elementos não têm atributo `style`
+    let inline_declarations = match (pseudo_element, element.element.attributes.get("style")) {
+        (None, Some(style)) => css::parse_declarations(style.to_owned()).value,
+        _ => Vec::new(),
+    };
+
+    let mut declarations = Vec::new();
+
+    for (origin, stylesheet) in stylesheets {
+        for rule in stylesheet.rules.iter() {
+            let specificity = rule
+                .selectors
+                .iter()
+                .filter(|selector| {
+                    selector.pseudo_element() == pseudo_element
+                        && complex_matches(selector, selector.compounds.len() - 1, element, None)
+                })
+                .map(|selector| selector.specificity())
+                .max();
+
+            if let Some(specificity) = specificity {
+                declarations.extend(rule.declarations.iter().map(|declaration| {
+                    (
+                        origin.precedence(declaration.important),
+                        false,
+                        specificity,
+                        *origin,
+                        declaration,
+                    )
+                }));
+            }
+        }
+    }
+
+    declarations.extend(inline_declarations.iter().map(|declaration| {
+        let origin = Origin::Author;
+
+        (
+            origin.precedence(declaration.important),
+            true,
+            (0, 0, 0),
+            origin,
+            declaration,
+        )
+    }));
+
+    // A ordenação é estável, então a ordem de aparição é preservada
+    declarations.sort_by_key(|(precedence, inline, specificity, _, _)| {
+        (*precedence, *inline, *specificity)
+    });
+
+    let mut cascaded: HashMap<&str, Vec<(Origin, &CSSValue)>> = HashMap::new();
+
+    for (_, _, _, origin, declaration) in declarations {
+        cascaded
+            .entry(&declaration.name)
+            .or_default()
+            .push((origin, &declaration.value));
+    }
+
+    for (name, values) in cascaded {
+        // Declarações de origens a partir desta são ignoradas
+        let mut reverted: Option<Origin> = None;
+
+        // Quem é descartado por um `revert` também seria pelos seguintes,
+        // que só diminuem a origem limite, então basta uma passada
+        let winner = values.iter().rev().find(|(origin, value)| {
+            if reverted.is_some_and(|reverted| *origin >= reverted) {
+                return false;
+            }
+
+            match value {
+                CSSValue::Keyword(keyword) if keyword == "revert" => {
+                    reverted = Some(*origin);
+                    false
+                }
+                _ => true,
+            }
+        });
+
+        if let Some((_, value)) = winner {
+            specified_properties.insert(name.to_owned(), (*value).clone());
         }
     }
 
     specified_properties
 }
 
-fn selector_matches(selector: &SimpleSelector, element: &Element) -> bool {
+// Primeiro elemento descendente de `node` que corresponde à lista de
+// seletores, ou os diagnósticos se a lista for inválida. O próprio `node`
+// faz o papel de raiz: seus ancestrais não são considerados.
+pub fn query_selector<'a>(
+    node: &'a Node,
+    selectors: &str,
+) -> Result<Option<&'a Element>, Vec<Diagnostic>> {
+    let selectors = parse_selector_list(selectors)?;
+    let mut matches = Vec::new();
+
+    matching_elements(node, &selectors, true, &mut matches);
+
+    Ok(matches.first().copied())
+}
+
+// Todos os elementos descendentes que correspondem, em ordem de documento
+pub fn query_selector_all<'a>(
+    node: &'a Node,
+    selectors: &str,
+) -> Result<Vec<&'a Element>, Vec<Diagnostic>> {
+    let selectors = parse_selector_list(selectors)?;
+    let mut matches = Vec::new();
+
+    matching_elements(node, &selectors, false, &mut matches);
+
+    Ok(matches)
+}
+
+fn parse_selector_list(selectors: &str) -> Result<Vec<ComplexSelector>, Vec<Diagnostic>> {
+    let result = css::parse_selectors(selectors.to_owned());
+
+    if result.diagnostics.is_empty() {
+        Ok(result.value)
+    } else {
+        Err(result.diagnostics)
+    }
+}
+
+fn matching_elements<'a>(
+    node: &'a Node,
+    selectors: &[ComplexSelector],
+    first_only: bool,
+    matches: &mut Vec<&'a Element>,
+) {
+    let parent = match node {
+        Node::Element(element) => Some(ElementRef {
+            element,
+            parent: None,
+            siblings: std::slice::from_ref(node),
+            index: 0,
+        }),
+        _ => None,
+    };
+
+    collect_matching_elements(
+        node.children(),
+        parent.as_ref(),
+        selectors,
+        first_only,
+        matches,
+    );
+}
+
+// Percorre os descendentes em ordem de documento. Retorna verdadeiro se a
+// busca deve parar.
+fn collect_matching_elements<'a>(
+    children: &'a [Node],
+    parent: Option<&ElementRef>,
+    selectors: &[ComplexSelector],
+    first_only: bool,
+    matches: &mut Vec<&'a Element>,
+) -> bool {
+    for (index, child) in children.iter().enumerate() {
+        let Node::Element(element) = child else {
+            continue;
+        };
+
+        let element_ref = ElementRef {
+            element,
+            parent,
+            siblings: children,
+            index,
+        };
+
+        if any_selector_matches(selectors, &element_ref) {
+            matches.push(element);
+
+            if first_only {
+                return true;
+            }
+        }
+
+        if collect_matching_elements(
+            &element.children,
+            Some(&element_ref),
+            selectors,
+            first_only,
+            matches,
+        ) {
+            return true;
+        }
+    }
+
+    false
+}
+
+fn any_selector_matches(selectors: &[ComplexSelector], element: &ElementRef) -> bool {
+    selectors
+        .iter()
+        .any(|selector| selector_matches(selector, element))
+}
+
+// Compara da direita para a esquerda: primeiro o seletor composto do próprio
+// elemento, depois os ancestrais ou irmãos indicados por cada combinador
+// Seletores com pseudo-elemento nunca correspondem ao próprio elemento
+fn selector_matches(selector: &ComplexSelector, element: &ElementRef) -> bool {
+    selector.pseudo_element().is_none()
+        && complex_matches(selector, selector.compounds.len() - 1, element, None)
+}
+
+// `scope` é usado pelos seletores relativos de `:has`: o primeiro seletor
+// composto precisa estar ligado ao elemento âncora pelo combinador
+fn complex_matches(
+    selector: &ComplexSelector,
+    index: usize,
+    element: &ElementRef,
+    scope: Option<(Combinator, &ElementRef)>,
+) -> bool {
+    if !compound_matches(&selector.compounds[index], element) {
+        return false;
+    }
+
+    if index == 0 {
+        return scope.is_none_or(|(combinator, anchor)| {
+            any_related(element, combinator, |other| {
+                std::ptr::eq(other.element, anchor.element)
+            })
+        });
+    }
+
+    any_related(element, selector.combinators[index - 1], |other| {
+        complex_matches(selector, index - 1, other, scope)
+    })
+}
+
+// Verifica `predicate` nos elementos à esquerda de `element` pelo combinador
+fn any_related(
+    element: &ElementRef,
+    combinator: Combinator,
+    mut predicate: impl FnMut(&ElementRef) -> bool,
+) -> bool {
+    match combinator {
+        Combinator::Descendant => element.ancestors().any(predicate),
+        Combinator::Child => element.parent.is_some_and(predicate),
+        Combinator::NextSibling => element
+            .previous_siblings()
+            .next()
+            .is_some_and(|sibling| predicate(&sibling)),
+        Combinator::SubsequentSibling => element
+            .previous_siblings()
+            .any(|sibling| predicate(&sibling)),
+    }
+}
+
+fn relative_matches(relative: &RelativeSelector, anchor: &ElementRef) -> bool {
+    let last = relative.selector.compounds.len() - 1;
+    let mut matches = |candidate: &ElementRef| {
+        complex_matches(
+            &relative.selector,
+            last,
+            candidate,
+            Some((relative.combinator, anchor)),
+        )
+    };
+
+    match relative.combinator {
+        Combinator::Descendant | Combinator::Child => anchor.any_descendant(&mut matches),
+        Combinator::NextSibling | Combinator::SubsequentSibling => anchor
+            .following_siblings()
+            .any(|sibling| matches(&sibling) || sibling.any_descendant(&mut matches)),
+    }
+}
+
+fn pseudo_class_matches(pseudo_class: &PseudoClass, element: &ElementRef) -> bool {
+    match pseudo_class {
+        PseudoClass::Root => element.parent.is_none(),
+        // Comentários não contam como conteúdo
+        PseudoClass::Empty => element.element.children.iter().all(|child| match child {
+            Node::Comment(_) => true,
+            Node::Text(text) => text.data.is_empty(),
+            _ => false,
+        }),
+        PseudoClass::FirstChild => element.previous_siblings().next().is_none(),
+        PseudoClass::LastChild => element.following_siblings().next().is_none(),
+        PseudoClass::OnlyChild => {
+            element.previous_siblings().next().is_none()
+                && element.following_siblings().next().is_none()
+        }
+        PseudoClass::FirstOfType => !element
+            .previous_siblings()
+            .any(|sibling| sibling.is_same_type(element)),
+        PseudoClass::LastOfType => !element
+            .following_siblings()
+            .any(|sibling| sibling.is_same_type(element)),
+        PseudoClass::OnlyOfType => !element
+            .previous_siblings()
+            .chain(element.following_siblings())
+            .any(|sibling| sibling.is_same_type(element)),
+        PseudoClass::NthChild(nth, of) | PseudoClass::NthLastChild(nth, of) => {
+            let counts = |sibling: &ElementRef| {
+                of.as_ref()
+                    .is_none_or(|of| any_selector_matches(of, sibling))
+            };
+
+            if !counts(element) {
+                return false;
+            }
+
+            let preceding = match pseudo_class {
+                PseudoClass::NthChild(..) => element.previous_siblings().filter(counts).count(),
+                _ => element.following_siblings().filter(counts).count(),
+            };
+
+            nth.matches(preceding + 1)
+        }
+        PseudoClass::NthOfType(nth) => nth.matches(
+            element
+                .previous_siblings()
+                .filter(|sibling| sibling.is_same_type(element))
+                .count()
+                + 1,
+        ),
+        PseudoClass::NthLastOfType(nth) => nth.matches(
+            element
+                .following_siblings()
+                .filter(|sibling| sibling.is_same_type(element))
+                .count()
+                + 1,
+        ),
+        PseudoClass::Not(selectors) => !any_selector_matches(selectors, element),
+        PseudoClass::Is(selectors) | PseudoClass::Where(selectors) => {
+            any_selector_matches(selectors, element)
+        }
+        PseudoClass::Has(selectors) => selectors
+            .iter()
+            .any(|relative| relative_matches(relative, element)),
+    }
+}
+
+fn compound_matches(selector: &CompoundSelector, element_ref: &ElementRef) -> bool {
+    let element = element_ref.element;
+
     if let Some(ref tag_name) = selector.tag_name {
-        if tag_name != &element.tag_name {
+        // Seletores de tipo não diferenciam maiúsculas de minúsculas no HTML
+        if !tag_name.eq_ignore_ascii_case(&element.tag_name) {
             return false;
         }
     }
 
     if let Some(ref id) = selector.id {
-        if element
-            .attributes
-            .get("id")
-            .map_or(true, |element_id| id != element_id)
-        {
+        if element.id().is_none_or(|element_id| id != element_id) {
             return false;
         }
     }
@@ -98,5 +814,453 @@ fn selector_matches(selector: &SimpleSelector, element: &Element) -> bool {
         return false;
     }
 
-    return true;
+    selector
+        .attributes
+        .iter()
+        .all(|attribute| attribute_matches(attribute, element))
+        && selector
+            .pseudo_classes
+            .iter()
+            .all(|pseudo_class| pseudo_class_matches(pseudo_class, element_ref))
+}
+
+fn attribute_matches(selector: &AttributeSelector, element: &Element) -> bool {
+    let Some(value) = element.attributes.get(&selector.name) else {
+        return false;
+    };
+
+    let Some((operator, expected)) = &selector.value else {
+        return true;
+    };
+
+    let case_sensitivity = selector.case_sensitivity.unwrap_or_else(|| {
+        if CASE_INSENSITIVE_ATTRIBUTES.contains(&selector.name.to_ascii_lowercase().as_str()) {
+            CaseSensitivity::AsciiInsensitive
+        } else {
+            CaseSensitivity::Sensitive
+        }
+    });
+
+    let (value, expected) = match case_sensitivity {
+        CaseSensitivity::Sensitive => (value.to_owned(), expected.to_owned()),
+        CaseSensitivity::AsciiInsensitive => {
+            (value.to_ascii_lowercase(), expected.to_ascii_lowercase())
+        }
+    };
+
+    match operator {
+        AttributeOperator::Equals => value == expected,
+        AttributeOperator::Includes => value.split_ascii_whitespace().any(|word| word == expected),
+        AttributeOperator::DashMatch => {
+            value == expected
+                || value
+                    .strip_prefix(&expected)
+                    .is_some_and(|rest| rest.starts_with('-'))
+        }
+        // Com valor vazio, `^=`, `$=` e `*=` nunca correspondem
+        AttributeOperator::Prefix => !expected.is_empty() && value.starts_with(&expected),
+        AttributeOperator::Suffix => !expected.is_empty() && value.ends_with(&expected),
+        AttributeOperator::Substring => !expected.is_empty() && value.contains(&expected),
+    }
+}
+
+// Atributos cujos valores o HTML compara sem diferenciar maiúsculas de
+// minúsculas quando o seletor não tem a flag `i` ou `s`
+// (https://html.spec.whatwg.org/multipage/semantics-other.html#case-sensitivity-of-selectors)
+const CASE_INSENSITIVE_ATTRIBUTES: &[&str] = &[
+    "accept",
+    "accept-charset",
+    "align",
+    "alink",
+    "axis",
+    "bgcolor",
+    "charset",
+    "checked",
+    "clear",
+    "codetype",
+    "color",
+    "compact",
+    "declare",
+    "defer",
+    "dir",
+    "direction",
+    "disabled",
+    "enctype",
+    "face",
+    "frame",
+    "hreflang",
+    "http-equiv",
+    "lang",
+    "language",
+    "link",
+    "media",
+    "method",
+    "multiple",
+    "nohref",
+    "noresize",
+    "noshade",
+    "nowrap",
+    "readonly",
+    "rel",
+    "rev",
+    "rules",
+    "scope",
+    "scrolling",
+    "selected",
+    "shape",
+    "target",
+    "text",
+    "type",
+    "valign",
+    "valuetype",
+    "vlink",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html;
+
+    fn ids(document: &Node, selectors: &str) -> Vec<String> {
+        query_selector_all(document, selectors)
+            .unwrap()
+            .iter()
+            .map(|element| element.id().unwrap_or_default().to_owned())
+            .collect()
+    }
+
+    // Nó do elemento com o id dado (sem contar os pseudo-elementos)
+    fn find<'a, 'b>(styled: &'b StyledNode<'a>, id: &str) -> &'b StyledNode<'a> {
+        fn search<'a, 'b>(styled: &'b StyledNode<'a>, id: &str) -> Option<&'b StyledNode<'a>> {
+            match styled.node {
+                Node::Element(element)
+                    if element.id() == Some(id) && styled.generated_content.is_none() =>
+                {
+                    Some(styled)
+                }
+                _ => styled.children.iter().find_map(|child| search(child, id)),
+            }
+        }
+
+        search(styled, id).unwrap()
+    }
+
+    fn specificity(selector: &str) -> css::Specificity {
+        css::parse_selectors(selector.to_owned()).value[0].specificity()
+    }
+
+    #[test]
+    fn combinators() {
+        let document = html::parse(
+            "<nav id=n><ul><li id=a><a id=x></a></li></ul></nav><h1 id=h></h1><p id=p1></p><div id=d></div><p id=p2></p>"
+                .to_owned(),
+        );
+
+        assert_eq!(ids(&document, "nav a"), ["x"]);
+        assert_eq!(ids(&document, "nav > a"), Vec::<String>::new());
+        assert_eq!(ids(&document, "ul > li > a"), ["x"]);
+        assert_eq!(ids(&document, "h1 + p"), ["p1"]);
+        assert_eq!(ids(&document, "h1 ~ p"), ["p1", "p2"]);
+        assert_eq!(ids(&document, "nav ~ div, #h + *"), ["p1", "d"]);
+    }
+
+    #[test]
+    fn complex_selector_specificity() {
+        assert_eq!(specificity("*"), (0, 0, 0));
+        assert_eq!(specificity("ul li a"), (0, 0, 3));
+        assert_eq!(specificity("#n > li.item.active + p"), (1, 2, 2));
+
+        // A regra vale com a especificidade do seletor mais específico que
+        // corresponde ao elemento
+        let document = html::parse("<p id=x class=c>".to_owned());
+        let stylesheet = css::parse("#x, .c { color: red } .c { color: blue }".to_owned());
+        let root = style_node(&document, &[(Origin::Author, &stylesheet)]);
+
+        assert_eq!(
+            find(&root, "x").computed_style.color,
+            css::named_color("red").unwrap()
+        );
+    }
+
+    #[test]
+    fn attribute_selectors() {
+        let document = html::parse(
+            "<a id=a data-x=\"One two\" lang=en-US href=\"https://x.org/a.PDF\"></a><a id=b data-x=one></a>"
+                .to_owned(),
+        );
+
+        assert_eq!(ids(&document, "[data-x]"), ["a", "b"]);
+        assert_eq!(ids(&document, "[data-x=one]"), ["b"]);
+        assert_eq!(ids(&document, "[data-x='one' i]"), ["b"]);
+        assert_eq!(ids(&document, "[data-x~=two]"), ["a"]);
+        assert_eq!(ids(&document, "[data-x~=ONE i]"), ["a", "b"]);
+        assert_eq!(ids(&document, "[lang|=en]"), ["a"]);
+        assert_eq!(ids(&document, "[href^='https:']"), ["a"]);
+        assert_eq!(ids(&document, "[href$='.pdf']"), Vec::<String>::new());
+        assert_eq!(ids(&document, "[href$='.pdf' i]"), ["a"]);
+        assert_eq!(ids(&document, "[href*=x\\.org]"), ["a"]);
+        assert!(query_selector(&document, "[data-x=]").is_err());
+    }
+
+    #[test]
+    fn structural_pseudo_classes() {
+        let document = html::parse(
+            "<ul id=u><li id=1></li><li id=2 class=x></li><p id=3></p><li id=4 class=x></li><li id=5>t</li></ul>"
+                .to_owned(),
+        );
+
+        assert_eq!(ids(&document, "li:first-child"), ["1"]);
+        assert_eq!(ids(&document, "li:last-child"), ["5"]);
+        assert_eq!(ids(&document, "li:nth-child(2n+1)"), ["1", "5"]);
+        assert_eq!(ids(&document, "li:nth-child(-n+2)"), ["1", "2"]);
+        assert_eq!(ids(&document, ":nth-child(2 of .x)"), ["4"]);
+        assert_eq!(ids(&document, "li:nth-of-type(3)"), ["4"]);
+        assert_eq!(ids(&document, "li:nth-last-of-type(1)"), ["5"]);
+        assert_eq!(ids(&document, "p:only-of-type"), ["3"]);
+        assert_eq!(ids(&document, "ul :empty:not(li)"), ["3"]);
+        assert_eq!(ids(&document, ":root").len(), 1);
+    }
+
+    #[test]
+    fn logical_pseudo_classes() {
+        let document = html::parse(
+            "<div id=a><p id=b class=x></p></div><div id=c><span id=d></span></div>".to_owned(),
+        );
+
+        assert_eq!(ids(&document, "div:has(> .x)"), ["a"]);
+        assert_eq!(ids(&document, "div:not(:has(p))"), ["c"]);
+        assert_eq!(ids(&document, ":is(p, span)"), ["b", "d"]);
+        assert_eq!(ids(&document, ":where(#a, $) > p"), ["b"]);
+        assert!(query_selector(&document, ":not(p, $)").is_err());
+
+        assert_eq!(specificity(":is(#a, p)"), (1, 0, 0));
+        assert_eq!(specificity(":not(.x, span)"), (0, 1, 0));
+        assert_eq!(specificity(":where(#a) p"), (0, 0, 1));
+        assert_eq!(specificity("li:nth-child(2 of #a)"), (1, 1, 1));
+    }
+
+    // Textos gerados por `::before` e `::after`, em ordem de documento
+    fn generated_text(html: &str, css: &str) -> Vec<String> {
+        fn collect(styled: &StyledNode, output: &mut Vec<String>) {
+            if let Some(ref content) = styled.generated_content {
+                output.push(content.text.clone());
+            }
+
+            for child in &styled.children {
+                collect(child, output);
+            }
+        }
+
+        let document = html::parse(html.to_owned());
+        let stylesheet = css::parse(css.to_owned());
+        let mut output = Vec::new();
+
+        collect(
+            &style_node(&document, &[(Origin::Author, &stylesheet)]),
+            &mut output,
+        );
+
+        output
+    }
+
+    #[test]
+    fn generated_content() {
+        assert_eq!(
+            generated_text(
+                "<p data-x=1>a</p><p>b</p>",
+                "p::before { content: '[' attr(data-x) ']' } p:first-child::after { content: 'fim' }"
+            ),
+            ["[1]", "fim", "[]"]
+        );
+        assert_eq!(
+            generated_text(
+                "<p>a</p>",
+                "p::before { content: 'x'; display: none } p::after { content: none } p:after { content: normal }"
+            ),
+            Vec::<String>::new()
+        );
+        assert!(
+            css::parse("p::before::after, p::before span { color: red }".to_owned())
+                .rules
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn counters() {
+        assert_eq!(
+            generated_text(
+                "<h2>a</h2><h3>b</h3><h3>c</h3><h2>d</h2><h3>e</h3>",
+                "body { counter-reset: h2 } h2 { counter-reset: h3; counter-increment: h2 } \
+                 h3 { counter-increment: h3 } \
+                 h2::before { content: counter(h2, upper-roman) '. ' } \
+                 h3::before { content: counter(h2) '.' counter(h3, lower-alpha) ' ' }"
+            ),
+            ["I. ", "1.a ", "1.b ", "II. ", "2.a "]
+        );
+        assert_eq!(
+            generated_text(
+                "<ol><li>a<ol><li>b</li><li>c</li></ol></li><li>d</li></ol>",
+                "ol { counter-reset: item } li { counter-increment: item } \
+                 li::before { content: counters(item, '.') ' ' }"
+            ),
+            ["1 ", "1.1 ", "1.2 ", "2 "]
+        );
+    }
+
+    // Valor especificado de `color` no elemento `#x` após a cascata, sem a
+    // folha de estilo padrão
+    fn cascaded_color(html: &str, stylesheets: &[(Origin, &str)]) -> Option<CSSValue> {
+        let document = html::parse(html.to_owned());
+        let stylesheets: Vec<_> = stylesheets
+            .iter()
+            .map(|&(origin, css)| (origin, css::parse(css.to_owned())))
+            .collect();
+        let stylesheets: Vec<_> = stylesheets
+            .iter()
+            .map(|(origin, stylesheet)| (*origin, stylesheet))
+            .collect();
+        let options = StyleOptions {
+            user_agent_stylesheet: false,
+            ..StyleOptions::default()
+        };
+        let root = style_node_with_options(&document, &stylesheets, &options);
+
+        find(&root, "x").specified_properties.get("color").cloned()
+    }
+
+    fn keyword(name: &str) -> Option<CSSValue> {
+        Some(CSSValue::Keyword(name.to_owned()))
+    }
+
+    #[test]
+    fn cascade_order() {
+        let html = "<p id=x class=c>";
+
+        // Especificidade e depois ordem de aparição
+        assert_eq!(
+            cascaded_color(
+                html,
+                &[(Origin::Author, ".c { color: red } p { color: blue }")]
+            ),
+            keyword("red")
+        );
+        assert_eq!(
+            cascaded_color(
+                html,
+                &[(Origin::Author, "p.c { color: red } .c.c { color: blue }")]
+            ),
+            keyword("blue")
+        );
+
+        // Origens: autor > usuário > navegador, invertido com `!important`
+        let sheets = [
+            (Origin::UserAgent, "p { color: red }"),
+            (Origin::Author, "p { color: green }"),
+            (Origin::User, "#x { color: blue }"),
+        ];
+        assert_eq!(cascaded_color(html, &sheets), keyword("green"));
+
+        let sheets = [
+            (Origin::UserAgent, "p { color: red !important }"),
+            (Origin::User, "p { color: blue !important }"),
+            (Origin::Author, "#x { color: green !important }"),
+        ];
+        assert_eq!(cascaded_color(html, &sheets), keyword("red"));
+        assert_eq!(cascaded_color(html, &sheets[1..]), keyword("blue"));
+    }
+
+    #[test]
+    fn user_agent_defaults() {
+        let source = include_str!("style/user_agent.css").to_owned();
+        let result = css::parse_with_diagnostics(source);
+
+        assert!(result.diagnostics.is_empty(), "{:?}", result.diagnostics);
+        assert_eq!(
+            result.value.rules.len(),
+            user_agent_stylesheet().rules.len()
+        );
+
+        let document = html::parse("<head id=h><title>t</title></head><p id=p>".to_owned());
+        let styled = |user_agent_stylesheet| {
+            let options = StyleOptions {
+                user_agent_stylesheet,
+                ..StyleOptions::default()
+            };
+            let root = style_node_with_options(&document, &[], &options);
+
+            ["h", "p"].map(|id| find(&root, id).display())
+        };
+
+        assert_eq!(styled(true), [Display::None, Display::Block]);
+        // Sem a folha padrão, todos os elementos ficam com o valor inicial
+        assert_eq!(styled(false), [Display::Inline, Display::Inline]);
+    }
+
+    #[test]
+    fn style_attribute() {
+        let html = "<p id=x style='color: red'>";
+
+        assert_eq!(
+            cascaded_color(html, &[(Origin::Author, "#x#x { color: blue }")]),
+            keyword("red")
+        );
+        assert_eq!(
+            cascaded_color(html, &[(Origin::Author, "p { color: blue !important }")]),
+            keyword("blue")
+        );
+        assert_eq!(
+            cascaded_color(
+                "<p id=x style='color: red !important'>",
+                &[(Origin::Author, "p { color: blue !important }")]
+            ),
+            keyword("red")
+        );
+    }
+
+    #[test]
+    fn revert() {
+        let html = "<p id=x>";
+        let sheets = [
+            (Origin::UserAgent, "p { color: red }"),
+            (Origin::User, "p { color: blue }"),
+            (Origin::Author, "p { color: green } #x { color: revert }"),
+        ];
+
+        assert_eq!(cascaded_color(html, &sheets), keyword("blue"));
+        assert_eq!(
+            cascaded_color(html, &[sheets[0], sheets[2]]),
+            keyword("red")
+        );
+        assert_eq!(cascaded_color(html, &sheets[2..]), None);
+        assert_eq!(
+            cascaded_color(
+                html,
+                &[
+                    (Origin::UserAgent, "p { color: red }"),
+                    (Origin::User, "p { color: revert }")
+                ]
+            ),
+            keyword("red")
+        );
+    }
+
+    #[test]
+    fn deeply_nested_selectors() {
+        let document = html::parse("<div id=a><p id=b></p></div>".to_owned());
+        let nested = |name: &str, depth: usize, argument: &str| {
+            format!(
+                "{}{argument}{}",
+                format!(":{name}(").repeat(depth),
+                ")".repeat(depth)
+            )
+        };
+
+        assert_eq!(ids(&document, &nested("not", 256, "p")), ["b"]);
+        assert_eq!(ids(&document, &nested("is", 256, "p")), ["b"]);
+        assert_eq!(ids(&document, "div:has(p)"), ["a"]);
+        assert!(ids(&document, &nested("has", 256, "p")).is_empty());
+        assert!(query_selector(&document, &nested("not", 10_000, "p")).is_err());
+        assert!(ids(&document, &nested("is", 10_000, "p")).is_empty());
+    }
 }
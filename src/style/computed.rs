@@ -0,0 +1,648 @@
+use super::{Display, PropertyMap};
+use crate::css::{self, CSSValue, Color};
+
+// Valores computados das propriedades que o motor entende, calculados a
+// partir dos valores especificados e do estilo do pai
+// (https://www.w3.org/TR/css-cascade-4/#computed)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComputedStyle {
+    // Herdadas
+    pub color: Color,
+    // Em px
+    pub font_size: f32,
+    // De 1 a 1000: `normal` é 400 e `bold` é 700
+    pub font_weight: u16,
+
+    // Não herdadas
+    pub display: Display,
+    pub background_color: Color,
+    pub width: LengthPercentageAuto,
+    pub height: LengthPercentageAuto,
+    pub margin_top: LengthPercentageAuto,
+    pub margin_right: LengthPercentageAuto,
+    pub margin_bottom: LengthPercentageAuto,
+    pub margin_left: LengthPercentageAuto,
+    pub padding_top: LengthPercentage,
+    pub padding_right: LengthPercentage,
+    pub padding_bottom: LengthPercentage,
+    pub padding_left: LengthPercentage,
+    // Em px. É 0 quando o estilo da borda é `none` ou `hidden`.
+    pub border_top_width: f32,
+    pub border_right_width: f32,
+    pub border_bottom_width: f32,
+    pub border_left_width: f32,
+    pub border_top_style: BorderStyle,
+    pub border_right_style: BorderStyle,
+    pub border_bottom_style: BorderStyle,
+    pub border_left_style: BorderStyle,
+    pub border_top_color: Color,
+    pub border_right_color: Color,
+    pub border_bottom_color: Color,
+    pub border_left_color: Color,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BorderStyle {
+    None,
+    Hidden,
+    Dotted,
+    Dashed,
+    Solid,
+    Double,
+    Groove,
+    Ridge,
+    Inset,
+    Outset,
+}
+
+// Porcentagens só são resolvidas no layout, em relação ao containing block
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LengthPercentage {
+    Length(f32),
+    Percentage(f32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LengthPercentageAuto {
+    Length(f32),
+    Percentage(f32),
+    Auto,
+}
+
+impl LengthPercentage {
+    // Tamanho em px, com porcentagens relativas a `reference`
+    pub fn to_px(self, reference: f32) -> f32 {
+        match self {
+            LengthPercentage::Length(length) => length,
+            LengthPercentage::Percentage(percentage) => reference * percentage / 100.0,
+        }
+    }
+}
+
+impl LengthPercentageAuto {
+    // `None` para `auto`
+    pub fn to_px(self, reference: f32) -> Option<f32> {
+        match self {
+            LengthPercentageAuto::Length(length) => Some(length),
+            LengthPercentageAuto::Percentage(percentage) => Some(reference * percentage / 100.0),
+            LengthPercentageAuto::Auto => None,
+        }
+    }
+}
+
+const TRANSPARENT: Color = Color {
+    r: 0,
+    g: 0,
+    b: 0,
+    a: 0,
+};
+
+const BLACK: Color = Color {
+    r: 0,
+    g: 0,
+    b: 0,
+    a: 255,
+};
+
+// Tamanho de `medium` e de `font-size: initial`
+const MEDIUM_FONT_SIZE: f32 = 16.0;
+
+// Razão entre tamanhos vizinhos em `larger` e `smaller`
+const FONT_SIZE_RATIO: f32 = 1.2;
+
+// Valor inicial de `border-*-width`
+const MEDIUM_BORDER_WIDTH: f32 = 3.0;
+
+impl Default for ComputedStyle {
+    // Valores iniciais de todas as propriedades
+    fn default() -> ComputedStyle {
+        ComputedStyle {
+            color: BLACK,
+            font_size: MEDIUM_FONT_SIZE,
+            font_weight: 400,
+            display: Display::Inline,
+            background_color: TRANSPARENT,
+            width: LengthPercentageAuto::Auto,
+            height: LengthPercentageAuto::Auto,
+            margin_top: LengthPercentageAuto::Length(0.0),
+            margin_right: LengthPercentageAuto::Length(0.0),
+            margin_bottom: LengthPercentageAuto::Length(0.0),
+            margin_left: LengthPercentageAuto::Length(0.0),
+            padding_top: LengthPercentage::Length(0.0),
+            padding_right: LengthPercentage::Length(0.0),
+            padding_bottom: LengthPercentage::Length(0.0),
+            padding_left: LengthPercentage::Length(0.0),
+            border_top_width: MEDIUM_BORDER_WIDTH,
+            border_right_width: MEDIUM_BORDER_WIDTH,
+            border_bottom_width: MEDIUM_BORDER_WIDTH,
+            border_left_width: MEDIUM_BORDER_WIDTH,
+            border_top_style: BorderStyle::None,
+            border_right_style: BorderStyle::None,
+            border_bottom_style: BorderStyle::None,
+            border_left_style: BorderStyle::None,
+            border_top_color: BLACK,
+            border_right_color: BLACK,
+            border_bottom_color: BLACK,
+            border_left_color: BLACK,
+        }
+    }
+}
+
+impl ComputedStyle {
+    // `parent` é `None` na raiz, onde `inherit` equivale a `initial`.
+    // Propriedades ausentes ou com `unset` são herdadas se forem herdáveis e
+    // recebem o valor inicial caso contrário; um valor de tipo inválido para
+    // a propriedade é tratado como `unset`. `revert` já foi resolvido pela
+    // cascata.
+    pub fn compute(specified: &PropertyMap, parent: Option<&ComputedStyle>) -> ComputedStyle {
+        let initial = ComputedStyle::default();
+        let parent = parent.unwrap_or(&initial);
+
+        let mut style = ComputedStyle {
+            color: parent.color.clone(),
+            font_size: parent.font_size,
+            font_weight: parent.font_weight,
+            ..ComputedStyle::default()
+        };
+
+        // As herdadas vêm primeiro porque `currentcolor` e porcentagens de
+        // `font-size` dependem delas
+        compute_property(
+            specified,
+            "color",
+            &mut style.color,
+            &initial.color,
+            &parent.color,
+            |value| match value {
+                CSSValue::Keyword(keyword) if keyword == "currentcolor" => {
+                    Some(parent.color.clone())
+                }
+                value => color(value),
+            },
+        );
+        compute_property(
+            specified,
+            "font-size",
+            &mut style.font_size,
+            &initial.font_size,
+            &parent.font_size,
+            |value| font_size(value, parent.font_size),
+        );
+        compute_property(
+            specified,
+            "font-weight",
+            &mut style.font_weight,
+            &initial.font_weight,
+            &parent.font_weight,
+            |value| font_weight(value, parent.font_weight),
+        );
+
+        let current_color = style.color.clone();
+        let color_or_current = |value: &CSSValue| match value {
+            CSSValue::Keyword(keyword) if keyword == "currentcolor" => Some(current_color.clone()),
+            value => color(value),
+        };
+
+        compute_property(
+            specified,
+            "display",
+            &mut style.display,
+            &initial.display,
+            &parent.display,
+            display,
+        );
+        compute_property(
+            specified,
+            "background-color",
+            &mut style.background_color,
+            &initial.background_color,
+            &parent.background_color,
+            color_or_current,
+        );
+
+        let sizes = [
+            ("width", &mut style.width, parent.width),
+            ("height", &mut style.height, parent.height),
+            ("margin-top", &mut style.margin_top, parent.margin_top),
+            ("margin-right", &mut style.margin_right, parent.margin_right),
+            (
+                "margin-bottom",
+                &mut style.margin_bottom,
+                parent.margin_bottom,
+            ),
+            ("margin-left", &mut style.margin_left, parent.margin_left),
+        ];
+
+        for (name, computed, parent_value) in sizes {
+            let initial_value = *computed;
+
+            compute_property(
+                specified,
+                name,
+                computed,
+                &initial_value,
+                &parent_value,
+                length_percentage_auto,
+            );
+        }
+
+        let paddings = [
+            ("padding-top", &mut style.padding_top, parent.padding_top),
+            (
+                "padding-right",
+                &mut style.padding_right,
+                parent.padding_right,
+            ),
+            (
+                "padding-bottom",
+                &mut style.padding_bottom,
+                parent.padding_bottom,
+            ),
+            ("padding-left", &mut style.padding_left, parent.padding_left),
+        ];
+
+        for (name, computed, parent_value) in paddings {
+            compute_property(
+                specified,
+                name,
+                computed,
+                &LengthPercentage::Length(0.0),
+                &parent_value,
+                length_percentage,
+            );
+        }
+
+        let borders = [
+            (
+                ["border-top-width", "border-top-style", "border-top-color"],
+                (
+                    &mut style.border_top_width,
+                    &mut style.border_top_style,
+                    &mut style.border_top_color,
+                ),
+                (
+                    parent.border_top_width,
+                    parent.border_top_style,
+                    &parent.border_top_color,
+                ),
+            ),
+            (
+                [
+                    "border-right-width",
+                    "border-right-style",
+                    "border-right-color",
+                ],
+                (
+                    &mut style.border_right_width,
+                    &mut style.border_right_style,
+                    &mut style.border_right_color,
+                ),
+                (
+                    parent.border_right_width,
+                    parent.border_right_style,
+                    &parent.border_right_color,
+                ),
+            ),
+            (
+                [
+                    "border-bottom-width",
+                    "border-bottom-style",
+                    "border-bottom-color",
+                ],
+                (
+                    &mut style.border_bottom_width,
+                    &mut style.border_bottom_style,
+                    &mut style.border_bottom_color,
+                ),
+                (
+                    parent.border_bottom_width,
+                    parent.border_bottom_style,
+                    &parent.border_bottom_color,
+                ),
+            ),
+            (
+                [
+                    "border-left-width",
+                    "border-left-style",
+                    "border-left-color",
+                ],
+                (
+                    &mut style.border_left_width,
+                    &mut style.border_left_style,
+                    &mut style.border_left_color,
+                ),
+                (
+                    parent.border_left_width,
+                    parent.border_left_style,
+                    &parent.border_left_color,
+                ),
+            ),
+        ];
+
+        for ([width_name, style_name, color_name], (width, border_style, color), parent_values) in
+            borders
+        {
+            let (parent_width, parent_style, parent_color) = parent_values;
+
+            compute_property(
+                specified,
+                width_name,
+                width,
+                &MEDIUM_BORDER_WIDTH,
+                &parent_width,
+                border_width,
+            );
+            compute_property(
+                specified,
+                style_name,
+                border_style,
+                &BorderStyle::None,
+                &parent_style,
+                self::border_style,
+            );
+
+            // A cor começa como `currentcolor`
+            *color = current_color.clone();
+            compute_property(
+                specified,
+                color_name,
+                color,
+                &current_color,
+                parent_color,
+                color_or_current,
+            );
+
+            if matches!(border_style, BorderStyle::None | BorderStyle::Hidden) {
+                *width = 0.0;
+            }
+        }
+
+        style
+    }
+}
+
+// `computed` já vem com o valor de `unset`: o do pai nas propriedades
+// herdadas ou o inicial nas demais
+fn compute_property<T: Clone>(
+    specified: &PropertyMap,
+    name: &str,
+    computed: &mut T,
+    initial: &T,
+    parent: &T,
+    compute: impl FnOnce(&CSSValue) -> Option<T>,
+) {
+    let value = match specified.get(name) {
+        Some(CSSValue::Keyword(keyword)) if keyword == "inherit" => parent.clone(),
+        Some(CSSValue::Keyword(keyword)) if keyword == "initial" => initial.clone(),
+        Some(value) => match compute(value) {
+            Some(value) => value,
+            None => return,
+        },
+        None => return,
+    };
+
+    *computed = value;
+}
+
+fn color(value: &CSSValue) -> Option<Color> {
+    match value {
+        CSSValue::Color(color) => Some(color.clone()),
+        CSSValue::Keyword(keyword) if keyword == "transparent" => Some(TRANSPARENT),
+        CSSValue::Keyword(keyword) => css::named_color(keyword),
+        _ => None,
+    }
+}
+
+fn display(value: &CSSValue) -> Option<Display> {
+    match value {
+        CSSValue::Keyword(keyword) => match keyword.as_str() {
+            "block" => Some(Display::Block),
+            "none" => Some(Display::None),
+            "inline" => Some(Display::Inline),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn font_size(value: &CSSValue, parent_font_size: f32) -> Option<f32> {
+    let size = match value {
+        CSSValue::Percentage(percentage) if *percentage >= 0.0 => {
+            parent_font_size * percentage / 100.0
+        }
+        CSSValue::Keyword(keyword) => match keyword.as_str() {
+            "xx-small" => MEDIUM_FONT_SIZE * 3.0 / 5.0,
+            "x-small" => MEDIUM_FONT_SIZE * 3.0 / 4.0,
+            "small" => MEDIUM_FONT_SIZE * 8.0 / 9.0,
+            "medium" => MEDIUM_FONT_SIZE,
+            "large" => MEDIUM_FONT_SIZE * 6.0 / 5.0,
+            "x-large" => MEDIUM_FONT_SIZE * 3.0 / 2.0,
+            "xx-large" => MEDIUM_FONT_SIZE * 2.0,
+            "xxx-large" => MEDIUM_FONT_SIZE * 3.0,
+            "larger" => parent_font_size * FONT_SIZE_RATIO,
+            "smaller" => parent_font_size / FONT_SIZE_RATIO,
+            _ => return None,
+        },
+        value => match value.length() {
+            Some(size) if size >= 0.0 => size,
+            _ => return None,
+        },
+    };
+
+    Some(size)
+}
+
+// `bolder` e `lighter` seguem a tabela de pesos relativos
+// (https://www.w3.org/TR/css-fonts-4/#relative-weights)
+fn font_weight(value: &CSSValue, parent_font_weight: u16) -> Option<u16> {
+    match value {
+        CSSValue::Number(weight) if (1.0..=1000.0).contains(weight) => Some(*weight as u16),
+        CSSValue::Keyword(keyword) => match keyword.as_str() {
+            "normal" => Some(400),
+            "bold" => Some(700),
+            "bolder" => Some(match parent_font_weight {
+                0..350 => 400,
+                350..550 => 700,
+                _ => 900,
+            }),
+            "lighter" => Some(match parent_font_weight {
+                0..550 => 100,
+                550..750 => 400,
+                _ => 700,
+            }),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn length_percentage(value: &CSSValue) -> Option<LengthPercentage> {
+    match value {
+        CSSValue::Percentage(percentage) => Some(LengthPercentage::Percentage(*percentage)),
+        value => value.length().map(LengthPercentage::Length),
+    }
+}
+
+fn length_percentage_auto(value: &CSSValue) -> Option<LengthPercentageAuto> {
+    match value {
+        CSSValue::Keyword(keyword) if keyword == "auto" => Some(LengthPercentageAuto::Auto),
+        value => length_percentage(value).map(|value| match value {
+            LengthPercentage::Length(length) => LengthPercentageAuto::Length(length),
+            LengthPercentage::Percentage(percentage) => {
+                LengthPercentageAuto::Percentage(percentage)
+            }
+        }),
+    }
+}
+
+fn border_style(value: &CSSValue) -> Option<BorderStyle> {
+    let style = match value {
+        CSSValue::Keyword(keyword) => match keyword.as_str() {
+            "none" => BorderStyle::None,
+            "hidden" => BorderStyle::Hidden,
+            "dotted" => BorderStyle::Dotted,
+            "dashed" => BorderStyle::Dashed,
+            "solid" => BorderStyle::Solid,
+            "double" => BorderStyle::Double,
+            "groove" => BorderStyle::Groove,
+            "ridge" => BorderStyle::Ridge,
+            "inset" => BorderStyle::Inset,
+            "outset" => BorderStyle::Outset,
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    Some(style)
+}
+
+// `thin`, `medium` e `thick` seguem os valores usados pelos navegadores
+fn border_width(value: &CSSValue) -> Option<f32> {
+    match value {
+        CSSValue::Keyword(keyword) => match keyword.as_str() {
+            "thin" => Some(1.0),
+            "medium" => Some(MEDIUM_BORDER_WIDTH),
+            "thick" => Some(5.0),
+            _ => None,
+        },
+        value => value.length().filter(|width| *width >= 0.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::named_color;
+
+    fn compute(declarations: &str, parent: Option<&ComputedStyle>) -> ComputedStyle {
+        let specified = css::parse_declarations(declarations.to_owned())
+            .value
+            .into_iter()
+            .map(|declaration| (declaration.name, declaration.value))
+            .collect();
+
+        ComputedStyle::compute(&specified, parent)
+    }
+
+    #[test]
+    fn inherited_and_initial_values() {
+        let parent = compute(
+            "color: red; font-size: 20px; font-weight: bold; width: 50px; margin-left: 5px",
+            None,
+        );
+        let child = compute("", Some(&parent));
+
+        assert_eq!(child.color, named_color("red").unwrap());
+        assert_eq!(child.font_size, 20.0);
+        assert_eq!(child.font_weight, 700);
+        assert_eq!(child.width, LengthPercentageAuto::Auto);
+        assert_eq!(child.margin_left, LengthPercentageAuto::Length(0.0));
+        assert_eq!(child.display, Display::Inline);
+    }
+
+    #[test]
+    fn css_wide_keywords() {
+        let parent = compute(
+            "color: red; font-size: 20px; width: 50px; background-color: blue",
+            None,
+        );
+        let child = compute(
+            "color: initial; font-size: unset; width: inherit; background-color: unset",
+            Some(&parent),
+        );
+
+        assert_eq!(child.color, BLACK);
+        assert_eq!(child.font_size, 20.0);
+        assert_eq!(child.width, LengthPercentageAuto::Length(50.0));
+        assert_eq!(child.background_color, TRANSPARENT);
+
+        // Na raiz `inherit` equivale a `initial`
+        assert_eq!(
+            compute("font-size: inherit", None).font_size,
+            MEDIUM_FONT_SIZE
+        );
+    }
+
+    #[test]
+    fn relative_values() {
+        let parent = compute("font-size: 20px; font-weight: 300; color: green", None);
+        let child = compute(
+            "font-size: 150%; font-weight: bolder; border-top-style: solid; border-top-color: currentcolor",
+            Some(&parent),
+        );
+
+        assert_eq!(child.font_size, 30.0);
+        assert_eq!(child.font_weight, 400);
+        assert_eq!(child.border_top_color, named_color("green").unwrap());
+        assert_eq!(child.border_top_width, MEDIUM_BORDER_WIDTH);
+        assert_eq!(child.border_left_width, 0.0);
+        assert_eq!(compute("font-size: larger", Some(&parent)).font_size, 24.0);
+    }
+
+    #[test]
+    fn invalid_values_behave_as_unset() {
+        let parent = compute("color: red; font-size: 20px", None);
+        let child = compute(
+            "color: 10px; font-size: -1px; width: red; border-top-width: -2px",
+            Some(&parent),
+        );
+
+        assert_eq!(child.color, named_color("red").unwrap());
+        assert_eq!(child.font_size, 20.0);
+        assert_eq!(child.width, LengthPercentageAuto::Auto);
+        assert_eq!(child.border_top_width, 0.0);
+    }
+
+    #[test]
+    fn unitless_zero_is_a_length() {
+        let style = compute(
+            "width: 0; margin-left: 0; padding-top: 0; font-size: 0; border-top-width: 0; border-top-style: solid",
+            None,
+        );
+
+        assert_eq!(style.width, LengthPercentageAuto::Length(0.0));
+        assert_eq!(style.margin_left, LengthPercentageAuto::Length(0.0));
+        assert_eq!(style.padding_top, LengthPercentage::Length(0.0));
+        assert_eq!(style.font_size, 0.0);
+        assert_eq!(style.border_top_width, 0.0);
+        assert_eq!(compute("width: 1", None).width, LengthPercentageAuto::Auto);
+    }
+
+    #[test]
+    fn inheritance_through_the_tree() {
+        let document = crate::html::parse("<body><div><p>x</p></div></body>".to_owned());
+        let stylesheet =
+            css::parse("body { color: blue; font-size: 10px } div { font-size: 2px }".to_owned());
+        let root =
+            super::super::style_node(&document, &[(super::super::Origin::Author, &stylesheet)]);
+
+        let mut node = &root;
+        while !node.children.is_empty() {
+            node = node.children.last().unwrap();
+        }
+
+        // O texto dentro de `p` herda a cor de `body` e o tamanho de `div`
+        assert_eq!(node.computed_style.color, named_color("blue").unwrap());
+        assert_eq!(node.computed_style.font_size, 2.0);
+    }
+}
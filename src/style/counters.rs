@@ -0,0 +1,174 @@
+// Contadores CSS em escopo durante o percurso da árvore em ordem de
+// documento (https://www.w3.org/TR/css-lists-3/#auto-numbering).
+//
+// Um contador criado por um elemento vale para os seus descendentes e para
+// os irmãos seguintes e seus descendentes, então ele é descartado quando o
+// percurso sai do pai desse elemento.
+#[derive(Debug, Default)]
+pub(super) struct Counters {
+    counters: Vec<Counter>,
+    // Profundidade atual do percurso, para saber se um contador foi criado
+    // por um irmão
+    depth: usize,
+}
+
+#[derive(Debug)]
+struct Counter {
+    name: String,
+    value: i32,
+    depth: usize,
+}
+
+impl Counters {
+    // Chamado ao entrar nos filhos de um elemento. Retorna a marca que deve
+    // ser passada para `leave`.
+    pub(super) fn enter(&mut self) -> usize {
+        self.depth += 1;
+        self.counters.len()
+    }
+
+    pub(super) fn leave(&mut self, mark: usize) {
+        self.depth -= 1;
+        self.counters.truncate(mark);
+    }
+
+    // Um `counter-reset` num irmão substitui o contador criado pelo irmão
+    // anterior em vez de aninhar um novo
+    pub(super) fn reset(&mut self, name: &str, value: i32) {
+        let depth = self.depth;
+
+        match self.innermost(name) {
+            Some(counter) if counter.depth == depth => counter.value = value,
+            _ => self.create(name, value),
+        }
+    }
+
+    // Sem nenhum contador com esse nome em escopo, cria um começando em 0
+    pub(super) fn increment(&mut self, name: &str, by: i32) {
+        match self.innermost(name) {
+            Some(counter) => counter.value = counter.value.saturating_add(by),
+            None => self.create(name, by),
+        }
+    }
+
+    pub(super) fn value(&self, name: &str) -> i32 {
+        self.values(name).last().unwrap_or(0)
+    }
+
+    // Valores de todos os contadores com esse nome, do mais externo ao mais
+    // interno
+    pub(super) fn values<'a>(&'a self, name: &'a str) -> impl DoubleEndedIterator<Item = i32> + 'a {
+        self.counters
+            .iter()
+            .filter(move |counter| counter.name == name)
+            .map(|counter| counter.value)
+    }
+
+    fn innermost(&mut self, name: &str) -> Option<&mut Counter> {
+        self.counters
+            .iter_mut()
+            .rev()
+            .find(|counter| counter.name == name)
+    }
+
+    fn create(&mut self, name: &str, value: i32) {
+        self.counters.push(Counter {
+            name: name.to_owned(),
+            value,
+            depth: self.depth,
+        });
+    }
+}
+
+// Representação de um valor de contador no estilo informado, ex:
+// `lower-roman` ou `upper-alpha`. Estilos desconhecidos e valores fora do
+// alcance de um estilo usam `decimal`.
+pub(super) fn format(value: i32, style: &str) -> String {
+    match style {
+        "none" => String::new(),
+        "disc" => "•".to_owned(),
+        "circle" => "◦".to_owned(),
+        "square" => "▪".to_owned(),
+        "decimal-leading-zero" if (0..10).contains(&value) => format!("0{}", value),
+        "decimal-leading-zero" if (-9..0).contains(&value) => format!("-0{}", -value),
+        "lower-alpha" | "lower-latin" if value > 0 => alphabetic(value, b'a'),
+        "upper-alpha" | "upper-latin" if value > 0 => alphabetic(value, b'A'),
+        "lower-roman" if (1..4000).contains(&value) => roman(value).to_ascii_lowercase(),
+        "upper-roman" if (1..4000).contains(&value) => roman(value),
+        _ => value.to_string(),
+    }
+}
+
+// 1 = a, 26 = z, 27 = aa...
+fn alphabetic(mut value: i32, first: u8) -> String {
+    let mut letters = Vec::new();
+
+    while value > 0 {
+        value -= 1;
+        letters.push(first + (value % 26) as u8);
+        value /= 26;
+    }
+
+    letters.iter().rev().map(|&letter| letter as char).collect()
+}
+
+fn roman(mut value: i32) -> String {
+    const NUMERALS: [(i32, &str); 13] = [
+        (1000, "M"),
+        (900, "CM"),
+        (500, "D"),
+        (400, "CD"),
+        (100, "C"),
+        (90, "XC"),
+        (50, "L"),
+        (40, "XL"),
+        (10, "X"),
+        (9, "IX"),
+        (5, "V"),
+        (4, "IV"),
+        (1, "I"),
+    ];
+
+    let mut numeral = String::new();
+
+    for (amount, symbols) in NUMERALS {
+        while value >= amount {
+            numeral.push_str(symbols);
+            value -= amount;
+        }
+    }
+
+    numeral
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counter_styles() {
+        assert_eq!(format(4, "decimal"), "4");
+        assert_eq!(format(7, "decimal-leading-zero"), "07");
+        assert_eq!(format(28, "lower-alpha"), "ab");
+        assert_eq!(format(1994, "upper-roman"), "MCMXCIV");
+        assert_eq!(format(0, "lower-roman"), "0");
+        assert_eq!(format(3, "disc"), "•");
+        assert_eq!(format(3, "unknown"), "3");
+    }
+
+    #[test]
+    fn scopes() {
+        let mut counters = Counters::default();
+        counters.reset("c", 0);
+        counters.increment("c", 1);
+
+        let mark = counters.enter();
+        counters.reset("c", 5);
+        counters.increment("c", 1);
+        assert_eq!(counters.values("c").collect::<Vec<_>>(), [1, 6]);
+
+        counters.leave(mark);
+        assert_eq!(counters.value("c"), 1);
+        assert_eq!(counters.value("missing"), 0);
+    }
+}
@@ -0,0 +1,118 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    css::{self, Stylesheet},
+    dom::{Element, Node},
+};
+
+// Folhas de estilo do próprio documento, em ordem de documento: o conteúdo
+// dos elementos `<style>` e os arquivos de `<link rel="stylesheet">`, com
+// `href` relativo a `base_path`. Sem `base_path`, ou com arquivos fora dele
+// ou que não puderem ser lidos, os links são ignorados.
+pub(super) fn stylesheets(node: &Node, base_path: Option<&Path>) -> Vec<Stylesheet> {
+    let base_path = base_path.and_then(|path| path.canonicalize().ok());
+
+    node.descendant_elements()
+        .filter_map(|element| match element.tag_name.as_str() {
+            "style" if is_css(element) => Some(css::parse(text_content(element))),
+            "link" if is_stylesheet_link(element) => {
+                let path = resolve(base_path.as_deref()?, element.attributes.get("href")?)?;
+
+                fs::read_to_string(path).ok().map(css::parse)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+// Caminho do arquivo de `href` dentro de `base_path`, que deve estar na forma
+// canônica. Caminhos absolutos, com `..` ou links simbólicos que levem para
+// fora de `base_path` são recusados.
+fn resolve(base_path: &Path, href: &str) -> Option<PathBuf> {
+    let href = href.trim();
+    let href = href.strip_prefix("file://").unwrap_or(href);
+    let path = base_path.join(href).canonicalize().ok()?;
+
+    path.starts_with(base_path).then_some(path)
+}
+
+// `type` ausente ou vazio também indica CSS
+fn is_css(element: &Element) -> bool {
+    element
+        .attributes
+        .get("type")
+        .is_none_or(|kind| kind.is_empty() || kind.eq_ignore_ascii_case("text/css"))
+}
+
+// Folhas alternativas (`rel="alternate stylesheet"`) não são aplicadas
+fn is_stylesheet_link(element: &Element) -> bool {
+    let rel = element.attributes.get("rel").unwrap_or_default();
+    let has_keyword = |keyword: &str| {
+        rel.split_ascii_whitespace()
+            .any(|value| value.eq_ignore_ascii_case(keyword))
+    };
+
+    has_keyword("stylesheet") && !has_keyword("alternate") && is_css(element)
+}
+
+fn text_content(element: &Element) -> String {
+    element
+        .children
+        .iter()
+        .filter_map(|child| match child {
+            Node::Text(text) => Some(text.data.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html;
+
+    #[test]
+    fn linked_stylesheets_stay_inside_base_path() {
+        let root = std::env::temp_dir().join(format!("toy_browser_{}", std::process::id()));
+        let base_path = root.join("site");
+
+        fs::create_dir_all(base_path.join("css")).unwrap();
+        fs::write(base_path.join("css/a.css"), "p { color: red }").unwrap();
+        fs::write(root.join("secret.css"), "p { color: blue }").unwrap();
+
+        let secret = root.join("secret.css");
+        let document = html::parse(format!(
+            "<link rel=stylesheet href=css/a.css>\
+             <link rel=stylesheet href=../secret.css>\
+             <link rel=stylesheet href='{}'>\
+             <link rel=stylesheet href='file://{}'>\
+             <link rel=stylesheet href=missing.css>\
+             <style>a {{ color: red }}</style>",
+            secret.display(),
+            secret.display()
+        ));
+
+        let linked = stylesheets(&document, Some(&base_path));
+        let unlinked = stylesheets(&document, None);
+
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(linked.len(), 2);
+        assert_eq!(
+            linked[0].rules[0].selectors[0].compounds[0]
+                .tag_name
+                .as_deref(),
+            Some("p")
+        );
+        assert_eq!(unlinked.len(), 1);
+        assert_eq!(
+            unlinked[0].rules[0].selectors[0].compounds[0]
+                .tag_name
+                .as_deref(),
+            Some("a")
+        );
+    }
+}